@@ -1,141 +1,54 @@
+pub mod quiz;
 mod tests;
 
-#[allow(unused_doc_comments)]
+// Thin wrappers over the registry in crate::quiz, which holds the text of the questions
 pub mod questions {
+    use crate::quiz::answer;
+
     pub fn question_1() -> char {
-        /// What technology does Solana employ to maintain synchronized time across all network nodes?
-        ///
-        /// a) Turbine
-        /// b) Proof-of-Stake
-        /// c) Proof-of-History (PoH)
-        /// d) Sealevel
-        'c'
+        answer(1)
     }
     pub fn question_2() -> char {
-        /// What happens if the current leader appears to be malicious or faulty?
-        ///
-        /// a) The network temporarily doubles transaction fees.
-        /// b) The network moves on to the next validator in line.
-        /// c) The protocol halts until the leader returns.
-        /// d) PoH counters are reset.
-        'b'
+        answer(2)
     }
     pub fn question_3() -> char {
-        /// Which statement best describes the nature of PoH in relation to its production and verification?
-        ///
-        /// a) PoH is easy to produce but difficult to verify.
-        /// b) PoH is difficult to produce and difficult to verify.
-        /// c) PoH is easy to produce and easy to verify.
-        /// d) PoH is difficult to produce but easy to verify.
-        'd'
+        answer(3)
     }
     pub fn question_4() -> char {
-        /// Which of the following is NOT a pipeline stage of the TPU?
-        ///
-        /// a) Data fetch in kernel space via network card (I/O).
-        /// b) Encryption of data using the GPU.
-        /// c) Change of the state using CPU (banking).
-        /// d) Write to the disk in kernel space and send out via network card (I/O).
-        'b'
+        answer(4)
     }
     pub fn question_5() -> char {
-        /// In Turbine's data propagation system, what determines which nodes receive priority for message forwarding?
-        ///
-        /// a) Node`s uptime and reliability.
-        /// b) Node`s proximity to the current leader.
-        /// c) Node`s computational power.
-        /// d) Stake-weighted selection algorithm.
-        'd'
+        answer(5)
     }
     pub fn question_6() -> char {
-        /// How are the nodes in the network organized in the Turbine protocol?
-        ///
-        /// a) Into chains.
-        /// b) Into shreds.
-        /// c) Into neighborhoods.
-        /// d) Into clusters.
-        'c'
+        answer(6)
     }
     pub fn question_7() -> char {
-        /// What does Gulf Stream serve as in Solana?
-        ///
-        /// a) A mempool-less solution for forwarding and storing transactions before processing.
-        /// b) A memory pool solution for storing processed transactions.
-        /// c) A protocol for communication overhead reduction.
-        /// d) A protocol to speed up consensus decision.
-        'a'
+        answer(7)
     }
     pub fn question_8() -> char {
-        /// Which statement about PoH is correct?
-        ///
-        /// a) PoH is a consensus mechanism.
-        /// b) PoH replaces communication with local computation.
-        /// c) PoH is a Sybil resistance algorithm.
-        /// d) The evaluation phase of PoH is very fast because it utilizes thousands of GPU cores.
-        'b'
+        answer(8)
     }
     pub fn question_9() -> char {
-        /// Why can Solana execute transactions in parallel?
-        ///
-        /// a) It uses Ethereum Virtual Machine (EVM).
-        /// b) It describes all the states required to read and write to.
-        /// c) It uses proof of stake consensus.
-        /// d) It splits each transaction into micro-transactions that can run independently.
-        'b'
+        answer(9)
     }
     pub fn question_10() -> char {
-        /// How does Cloudbreak handle data storage?
-        ///
-        /// a) It uses cloud-based storage systems.
-        /// b) It makes use of memory-mapped files.
-        /// c) It prioritizes CPU storage over disk storage.
-        /// d) It employs traditional databases for optimized reading and writing.
-        'b'
+        answer(10)
     }
     pub fn question_11() -> char {
-        /// What is Sealevel in Solana?
-        ///
-        /// a) A runtime for parallel smart contract execution.
-        /// b) A protocol for network time synchronization.
-        /// c) A framework for Solana program development.
-        /// d) A system for managing validator stakes.
-        'a'
+        answer(11)
     }
     pub fn question_12() -> char {
-        /// What does Turbine aim to reduce?
-        ///
-        /// a) Time needed for transaction validation.
-        /// b) Time needed for block propagation.
-        /// c) Time needed for consensus voting.
-        /// d) Time needed for PoH validation.
-        'b'
+        answer(12)
     }
     pub fn question_13() -> char {
-        /// What is the primary role of the mempool in traditional blockchains?
-        ///
-        /// a) To process transactions instantaneously.
-        /// b) To reserve memory for block processing.
-        /// c) To store transactions that have been added to the blockchain.
-        /// d) To store transactions that are being broadcasted but have not yet been processed.
-        'd'
+        answer(13)
     }
     pub fn question_14() -> char {
-        /// How many transactions can Solana's mempool hold?
-        ///
-        /// a) Solana doesn't use a traditional mempool.
-        /// b) Up to 50,000 transactions.
-        /// c) Up to 100,000 transactions.
-        /// d) Unlimited transactions until memory is full.
-        'a'
+        answer(14)
     }
     pub fn question_15() -> char {
-        /// Which of these statements about transaction processing in parallel on Solana is true?
-        ///
-        /// a) Two transactions processed in parallel can read from the same account.
-        /// b) Two transactions processed in parallel can write to the same account.
-        /// c) Two transactions can be processed in parallel ONLY if the accounts they read from do not overlap and the accounts they write to do not overlap.
-        /// d) Any two transactions can be processed in parallel.
-        'a'
+        answer(15)
     }
 }
 
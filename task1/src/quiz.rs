@@ -0,0 +1,321 @@
+//! The quiz questions as data.
+//!
+//! Every question of `crate::questions` is a `Question` in `QUESTIONS`, with
+//! its prompt, its four options (a to d), the letter of the correct option, an
+//! explanation of the answer and the topic it covers. The `question_N()`
+//! functions return the answer of the question with id N.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topic {
+    ProofOfHistory,
+    // Leader rotation and what happens with a faulty leader
+    Consensus,
+    // The Transaction Processing Unit and its pipeline
+    Pipelining,
+    Turbine,
+    GulfStream,
+    Sealevel,
+    Cloudbreak,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Question {
+    // Numbered from 1, as the question_N() functions
+    pub id: u32,
+    pub prompt: &'static str,
+    // The options a) to d), without their letter
+    pub options: [&'static str; 4],
+    pub answer: char,
+    pub explanation: &'static str,
+    pub topic: Topic,
+}
+
+pub const LETTERS: [char; 4] = ['a', 'b', 'c', 'd'];
+
+impl Topic {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topic::ProofOfHistory => "Proof of History",
+            Topic::Consensus => "Consensus",
+            Topic::Pipelining => "Pipelining",
+            Topic::Turbine => "Turbine",
+            Topic::GulfStream => "Gulf Stream",
+            Topic::Sealevel => "Sealevel",
+            Topic::Cloudbreak => "Cloudbreak",
+        }
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Question {
+    // The text of the option with the given letter
+    pub fn option(&self, letter: char) -> Option<&'static str> {
+        LETTERS
+            .iter()
+            .position(|given| *given == letter)
+            .map(|index| self.options[index])
+    }
+
+    pub fn is_correct(&self, letter: char) -> bool {
+        letter == self.answer
+    }
+}
+
+// The prompt followed by one line per option, as in the original doc comments
+impl fmt::Display for Question {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.prompt)?;
+        writeln!(f)?;
+        for (letter, option) in LETTERS.iter().zip(self.options) {
+            writeln!(f, "{}) {}", letter, option)?;
+        }
+        Ok(())
+    }
+}
+
+// The question with the given id
+pub fn question(id: u32) -> Option<&'static Question> {
+    QUESTIONS.iter().find(|question| question.id == id)
+}
+
+// The correct answer of the question with the given id
+// Panics if there is no such question
+pub fn answer(id: u32) -> char {
+    question(id).expect("unknown question").answer
+}
+
+pub fn by_topic(topic: Topic) -> impl Iterator<Item = &'static Question> {
+    QUESTIONS
+        .iter()
+        .filter(move |question| question.topic == topic)
+}
+
+pub static QUESTIONS: [Question; 15] = [
+    Question {
+        id: 1,
+        prompt: "What technology does Solana employ to maintain synchronized time across all \
+                 network nodes?",
+        options: [
+            "Turbine",
+            "Proof-of-Stake",
+            "Proof-of-History (PoH)",
+            "Sealevel",
+        ],
+        answer: 'c',
+        explanation: "Proof of History is a sequential chain of SHA-256 hashes that timestamps \
+                      events, giving every node the same clock without exchanging messages.",
+        topic: Topic::ProofOfHistory,
+    },
+    Question {
+        id: 2,
+        prompt: "What happens if the current leader appears to be malicious or faulty?",
+        options: [
+            "The network temporarily doubles transaction fees.",
+            "The network moves on to the next validator in line.",
+            "The protocol halts until the leader returns.",
+            "PoH counters are reset.",
+        ],
+        answer: 'b',
+        explanation: "Leaders rotate on a known schedule; the slots of a faulty leader are \
+                      skipped and the next validator of the schedule takes over.",
+        topic: Topic::Consensus,
+    },
+    Question {
+        id: 3,
+        prompt: "Which statement best describes the nature of PoH in relation to its production \
+                 and verification?",
+        options: [
+            "PoH is easy to produce but difficult to verify.",
+            "PoH is difficult to produce and difficult to verify.",
+            "PoH is easy to produce and easy to verify.",
+            "PoH is difficult to produce but easy to verify.",
+        ],
+        answer: 'd',
+        explanation: "Each hash depends on the previous one, so producing the chain cannot be \
+                      parallelized, while its segments can be verified in parallel.",
+        topic: Topic::ProofOfHistory,
+    },
+    Question {
+        id: 4,
+        prompt: "Which of the following is NOT a pipeline stage of the TPU?",
+        options: [
+            "Data fetch in kernel space via network card (I/O).",
+            "Encryption of data using the GPU.",
+            "Change of the state using CPU (banking).",
+            "Write to the disk in kernel space and send out via network card (I/O).",
+        ],
+        answer: 'b',
+        explanation: "The GPU stage of the TPU verifies signatures; transactions are not \
+                      encrypted.",
+        topic: Topic::Pipelining,
+    },
+    Question {
+        id: 5,
+        prompt: "In Turbine's data propagation system, what determines which nodes receive \
+                 priority for message forwarding?",
+        options: [
+            "Node`s uptime and reliability.",
+            "Node`s proximity to the current leader.",
+            "Node`s computational power.",
+            "Stake-weighted selection algorithm.",
+        ],
+        answer: 'd',
+        explanation: "The propagation tree is built by a stake-weighted selection, so nodes \
+                      with more stake are closer to the leader and receive shreds first.",
+        topic: Topic::Turbine,
+    },
+    Question {
+        id: 6,
+        prompt: "How are the nodes in the network organized in the Turbine protocol?",
+        options: [
+            "Into chains.",
+            "Into shreds.",
+            "Into neighborhoods.",
+            "Into clusters.",
+        ],
+        answer: 'c',
+        explanation: "Nodes are grouped into neighborhoods forming the layers of the \
+                      propagation tree; shreds are the pieces blocks are split into.",
+        topic: Topic::Turbine,
+    },
+    Question {
+        id: 7,
+        prompt: "What does Gulf Stream serve as in Solana?",
+        options: [
+            "A mempool-less solution for forwarding and storing transactions before \
+             processing.",
+            "A memory pool solution for storing processed transactions.",
+            "A protocol for communication overhead reduction.",
+            "A protocol to speed up consensus decision.",
+        ],
+        answer: 'a',
+        explanation: "Gulf Stream forwards transactions to the upcoming leaders ahead of time, \
+                      so they do not wait in a mempool.",
+        topic: Topic::GulfStream,
+    },
+    Question {
+        id: 8,
+        prompt: "Which statement about PoH is correct?",
+        options: [
+            "PoH is a consensus mechanism.",
+            "PoH replaces communication with local computation.",
+            "PoH is a Sybil resistance algorithm.",
+            "The evaluation phase of PoH is very fast because it utilizes thousands of GPU \
+             cores.",
+        ],
+        answer: 'b',
+        explanation: "PoH is a clock rather than a consensus or Sybil resistance mechanism: \
+                      nodes compute the passage of time locally instead of agreeing on it \
+                      through messages.",
+        topic: Topic::ProofOfHistory,
+    },
+    Question {
+        id: 9,
+        prompt: "Why can Solana execute transactions in parallel?",
+        options: [
+            "It uses Ethereum Virtual Machine (EVM).",
+            "It describes all the states required to read and write to.",
+            "It uses proof of stake consensus.",
+            "It splits each transaction into micro-transactions that can run independently.",
+        ],
+        answer: 'b',
+        explanation: "Every transaction lists the accounts it reads and writes up front, so \
+                      the runtime can schedule transactions that do not conflict together.",
+        topic: Topic::Sealevel,
+    },
+    Question {
+        id: 10,
+        prompt: "How does Cloudbreak handle data storage?",
+        options: [
+            "It uses cloud-based storage systems.",
+            "It makes use of memory-mapped files.",
+            "It prioritizes CPU storage over disk storage.",
+            "It employs traditional databases for optimized reading and writing.",
+        ],
+        answer: 'b',
+        explanation: "Cloudbreak keeps accounts in memory-mapped files spread over several \
+                      disks, so reads and writes can run concurrently.",
+        topic: Topic::Cloudbreak,
+    },
+    Question {
+        id: 11,
+        prompt: "What is Sealevel in Solana?",
+        options: [
+            "A runtime for parallel smart contract execution.",
+            "A protocol for network time synchronization.",
+            "A framework for Solana program development.",
+            "A system for managing validator stakes.",
+        ],
+        answer: 'a',
+        explanation: "Sealevel is the runtime executing transactions that do not conflict on \
+                      all the cores of a validator at once.",
+        topic: Topic::Sealevel,
+    },
+    Question {
+        id: 12,
+        prompt: "What does Turbine aim to reduce?",
+        options: [
+            "Time needed for transaction validation.",
+            "Time needed for block propagation.",
+            "Time needed for consensus voting.",
+            "Time needed for PoH validation.",
+        ],
+        answer: 'b',
+        explanation: "Turbine splits blocks into shreds and spreads them through a tree of \
+                      nodes, so the leader does not send whole blocks to every validator.",
+        topic: Topic::Turbine,
+    },
+    Question {
+        id: 13,
+        prompt: "What is the primary role of the mempool in traditional blockchains?",
+        options: [
+            "To process transactions instantaneously.",
+            "To reserve memory for block processing.",
+            "To store transactions that have been added to the blockchain.",
+            "To store transactions that are being broadcasted but have not yet been \
+             processed.",
+        ],
+        answer: 'd',
+        explanation: "The mempool holds the broadcast transactions waiting to be included in \
+                      a block.",
+        topic: Topic::GulfStream,
+    },
+    Question {
+        id: 14,
+        prompt: "How many transactions can Solana's mempool hold?",
+        options: [
+            "Solana doesn't use a traditional mempool.",
+            "Up to 50,000 transactions.",
+            "Up to 100,000 transactions.",
+            "Unlimited transactions until memory is full.",
+        ],
+        answer: 'a',
+        explanation: "Gulf Stream forwards transactions straight to the expected leaders, \
+                      which replaces the mempool.",
+        topic: Topic::GulfStream,
+    },
+    Question {
+        id: 15,
+        prompt: "Which of these statements about transaction processing in parallel on Solana \
+                 is true?",
+        options: [
+            "Two transactions processed in parallel can read from the same account.",
+            "Two transactions processed in parallel can write to the same account.",
+            "Two transactions can be processed in parallel ONLY if the accounts they read \
+             from do not overlap and the accounts they write to do not overlap.",
+            "Any two transactions can be processed in parallel.",
+        ],
+        answer: 'a',
+        explanation: "Only writes conflict: transactions sharing read-only accounts can run \
+                      in parallel, while an account written by one cannot be used by the \
+                      other.",
+        topic: Topic::Sealevel,
+    },
+];
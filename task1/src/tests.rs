@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod quiz_tests {
+    use crate::questions::*;
+    use crate::quiz::*;
+
+    #[test]
+    fn registry() {
+        for (index, question) in QUESTIONS.iter().enumerate() {
+            assert_eq!(question.id as usize, index + 1);
+            assert!(LETTERS.contains(&question.answer));
+            assert!(!question.prompt.is_empty());
+            assert!(!question.explanation.is_empty());
+            assert!(question.options.iter().all(|option| !option.is_empty()));
+        }
+        assert_eq!(question(0), None);
+        assert_eq!(question(16), None);
+    }
+
+    #[test]
+    fn wrappers() {
+        let answers = [
+            question_1(),
+            question_2(),
+            question_3(),
+            question_4(),
+            question_5(),
+            question_6(),
+            question_7(),
+            question_8(),
+            question_9(),
+            question_10(),
+            question_11(),
+            question_12(),
+            question_13(),
+            question_14(),
+            question_15(),
+        ];
+        let expected: Vec<char> = QUESTIONS.iter().map(|question| question.answer).collect();
+        assert_eq!(answers.to_vec(), expected);
+        assert_eq!(answers.iter().collect::<String>(), "cbdbdcabbbabdaa");
+    }
+
+    #[test]
+    fn options() {
+        let question = question(1).unwrap();
+        assert_eq!(question.option('c'), Some("Proof-of-History (PoH)"));
+        assert_eq!(question.option('e'), None);
+        assert!(question.is_correct('c'));
+        assert!(!question.is_correct('a'));
+        assert_eq!(
+            question.to_string(),
+            "What technology does Solana employ to maintain synchronized time across all \
+             network nodes?\n\na) Turbine\nb) Proof-of-Stake\nc) Proof-of-History (PoH)\n\
+             d) Sealevel\n"
+        );
+    }
+
+    #[test]
+    fn topics() {
+        let ids: Vec<u32> = by_topic(Topic::Turbine)
+            .map(|question| question.id)
+            .collect();
+        assert_eq!(ids, vec![5, 6, 12]);
+        assert_eq!(Topic::ProofOfHistory.to_string(), "Proof of History");
+    }
+}
@@ -0,0 +1,440 @@
+//! Arbitrary precision signed integer used as a calculator backend.
+//!
+//! The value is stored as a sign and a little endian magnitude of 32 bit
+//! limbs. Division truncates toward zero and the remainder takes the sign of
+//! the dividend, exactly like the primitive integers. Nothing overflows, so
+//! the overflow policy has no effect; the only limit is `MAX_POWER_BITS`,
+//! which stops `^` from producing numbers that would exhaust memory.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::calculator::{CalcError, OperationType, OverflowPolicy};
+use crate::numeric::{unsupported, Number};
+
+// Largest result of a power, in bits, before it is reported as an overflow
+pub const MAX_POWER_BITS: u64 = 1 << 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    // Little endian limbs without trailing zeros; zero has no limbs and is never negative
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self {
+            negative: false,
+            magnitude: vec![],
+        }
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // Number of significant bits of the magnitude
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            None => 0,
+            Some(top) => self.magnitude.len() as u64 * 32 - u64::from(top.leading_zeros()),
+        }
+    }
+
+    // The value as an i128, if it fits
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.magnitude.len() > 4 {
+            return None;
+        }
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u128, |acc, limb| (acc << 32) | u128::from(*limb));
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    fn abs(&self) -> Self {
+        Self::from_parts(false, self.magnitude.clone())
+    }
+
+    fn neg(&self) -> Self {
+        Self::from_parts(!self.negative, self.magnitude.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::from_parts(
+                self.negative,
+                add_magnitudes(&self.magnitude, &other.magnitude),
+            );
+        }
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => Self::from_parts(
+                other.negative,
+                sub_magnitudes(&other.magnitude, &self.magnitude),
+            ),
+            _ => Self::from_parts(
+                self.negative,
+                sub_magnitudes(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::from_parts(
+            self.negative != other.negative,
+            mul_magnitudes(&self.magnitude, &other.magnitude),
+        )
+    }
+
+    // Quotient rounded toward zero and remainder with the sign of self
+    // Return None when dividing by zero
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitudes(&self.magnitude, &other.magnitude);
+        Some((
+            Self::from_parts(self.negative != other.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        ))
+    }
+
+    // Return None when the result would be larger than MAX_POWER_BITS
+    pub fn pow(&self, exponent: u64) -> Option<Self> {
+        if self.abs() == Self::from(1i64) || self.is_zero() {
+            let negative = self.negative && exponent % 2 == 1;
+            let base = if exponent == 0 {
+                Self::from(1i64)
+            } else {
+                self.abs()
+            };
+            return Some(Self::from_parts(negative, base.magnitude));
+        }
+        if (self.bits() - 1).checked_mul(exponent)? > MAX_POWER_BITS {
+            return None;
+        }
+
+        let (mut base, mut exponent, mut res) = (self.clone(), exponent, Self::from(1i64));
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                res = res.mul(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base);
+            }
+        }
+        Some(res)
+    }
+
+    // Integer square root rounded down
+    // Return None for negative numbers
+    pub fn isqrt(&self) -> Option<Self> {
+        if self.negative {
+            return None;
+        }
+        if self.is_zero() {
+            return Some(Self::zero());
+        }
+        // Newton's iteration starting above the root decreases monotonically to it
+        let two = Self::from(2i64);
+        let mut root = Self::from(1i64).shl(self.bits().div_ceil(2));
+        loop {
+            let (quotient, _) = self.div_rem(&root)?;
+            let (next, _) = root.add(&quotient).div_rem(&two)?;
+            if compare_magnitudes(&next.magnitude, &root.magnitude) != Ordering::Less {
+                return Some(root);
+            }
+            root = next;
+        }
+    }
+
+    fn shl(&self, bits: u64) -> Self {
+        let mut magnitude = vec![0; (bits / 32) as usize];
+        let shift = bits % 32;
+        let mut carry = 0u32;
+        for limb in &self.magnitude {
+            let wide = (u64::from(*limb) << shift) | u64::from(carry);
+            magnitude.push(wide as u32);
+            carry = (wide >> 32) as u32;
+        }
+        magnitude.push(carry);
+        Self::from_parts(self.negative, magnitude)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        Self::from(i128::from(value))
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(value: i128) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        Self::from_parts(value < 0, limbs)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Peel off nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+        let mut res = String::new();
+        if self.negative {
+            res.push('-');
+        }
+        res.push_str(&chunks.pop().unwrap_or(0).to_string());
+        for chunk in chunks.iter().rev() {
+            res.push_str(&format!("{:09}", chunk));
+        }
+        f.pad(&res)
+    }
+}
+
+impl std::str::FromStr for BigInt {
+    type Err = ();
+
+    // Parse an optionally negative decimal number
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match input.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, input),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+        let mut magnitude = Vec::new();
+        for chunk in digits.as_bytes().chunks(9) {
+            let chunk = std::str::from_utf8(chunk).map_err(|_| ())?;
+            let scale = 10u32.pow(chunk.len() as u32);
+            mul_small_add(&mut magnitude, scale, chunk.parse().map_err(|_| ())?);
+        }
+        Ok(Self::from_parts(negative, magnitude))
+    }
+}
+
+impl Number for BigInt {
+    const NAME: &'static str = "bigint";
+    const INTEGER: bool = true;
+
+    fn zero() -> Self {
+        BigInt::zero()
+    }
+
+    fn parse_literal(literal: &str) -> Option<Self> {
+        literal.parse().ok()
+    }
+
+    // The overflow policy is ignored, there is no overflow to handle
+    fn compute(
+        operation_type: &OperationType,
+        x: &Self,
+        y: &Self,
+        _policy: OverflowPolicy,
+    ) -> Result<Self, CalcError> {
+        match operation_type {
+            OperationType::Addition => Ok(x.add(y)),
+            OperationType::Subtraction => Ok(x.sub(y)),
+            OperationType::Multiplication => Ok(x.mul(y)),
+            OperationType::Division => x
+                .div_rem(y)
+                .map(|(quotient, _)| quotient)
+                .ok_or(CalcError::DivisionByZero),
+            OperationType::Remainder => x
+                .div_rem(y)
+                .map(|(_, remainder)| remainder)
+                .ok_or(CalcError::DivisionByZero),
+            OperationType::Power => {
+                if y.is_negative() {
+                    return Err(CalcError::NegativeExponent);
+                }
+                let exponent = match y.magnitude.as_slice() {
+                    [] => 0,
+                    [low] => u64::from(*low),
+                    [low, high] => u64::from(*high) << 32 | u64::from(*low),
+                    _ => u64::MAX,
+                };
+                x.pow(exponent).ok_or(CalcError::Overflow)
+            }
+            OperationType::SquareRoot => x.isqrt().ok_or(CalcError::NegativeSquareRoot),
+            OperationType::Assignment => Ok(x.clone()),
+            // Without a bit width shifts, rotations and ~ have no meaning
+            _ => Err(unsupported::<Self>(operation_type)),
+        }
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut res = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (index, limb) in long.iter().enumerate() {
+        let sum = u64::from(*limb) + u64::from(short.get(index).copied().unwrap_or(0)) + carry;
+        res.push(sum as u32);
+        carry = sum >> 32;
+    }
+    res.push(carry as u32);
+    trim(&mut res);
+    res
+}
+
+// a - b, where a >= b
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (index, limb) in a.iter().enumerate() {
+        let mut diff = i64::from(*limb) - i64::from(b.get(index).copied().unwrap_or(0)) - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        res.push(diff as u32);
+    }
+    trim(&mut res);
+    res
+}
+
+fn mul_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut res = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let product = u64::from(*x) * u64::from(*y) + u64::from(res[i + j]) + carry;
+            res[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        res[i + b.len()] = carry as u32;
+    }
+    trim(&mut res);
+    res
+}
+
+// magnitude * factor + addend, in place
+fn mul_small_add(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = u64::from(addend);
+    for limb in magnitude.iter_mut() {
+        let product = u64::from(*limb) * u64::from(factor) + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+fn div_rem_small(magnitude: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; magnitude.len()];
+    let mut remainder = 0u64;
+    for (index, limb) in magnitude.iter().enumerate().rev() {
+        let current = (remainder << 32) | u64::from(*limb);
+        quotient[index] = (current / u64::from(divisor)) as u32;
+        remainder = current % u64::from(divisor);
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+// Schoolbook binary long division, one bit of the dividend at a time
+fn div_rem_magnitudes(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [single] = divisor {
+        let (quotient, remainder) = div_rem_small(dividend, *single);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+    if compare_magnitudes(dividend, divisor) == Ordering::Less {
+        return (vec![], dividend.to_vec());
+    }
+
+    let mut quotient = vec![0u32; dividend.len()];
+    let mut remainder: Vec<u32> = Vec::with_capacity(divisor.len() + 1);
+    for bit in (0..dividend.len() * 32).rev() {
+        // remainder = remainder * 2 + next bit of the dividend
+        let mut carry = (dividend[bit / 32] >> (bit % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next_carry = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next_carry;
+        }
+        if carry > 0 {
+            remainder.push(carry);
+        }
+        if compare_magnitudes(&remainder, divisor) != Ordering::Less {
+            remainder = sub_magnitudes(&remainder, divisor);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
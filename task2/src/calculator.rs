@@ -6,7 +6,8 @@
 /// Complete the implementation of the Calculator struct and its methods.
 ///
 /// The calculator should support basic arithmetic
-/// operations (addition, subtraction, multiplication)
+/// operations (addition, subtraction, multiplication,
+/// division, remainder, power, square root)
 /// with overflow protection and maintain a history
 /// of operations.
 ///
@@ -17,139 +18,920 @@
 ///
 ///-------------------------------------------------------------------------------
 
-#[derive(Clone)]
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::expression::{self, Expr, ParseError, Statement};
+use crate::format::{self, DefaultFormatter, HistoryFormatter};
+use crate::macros::Macro;
+use crate::numeric::{Number, Rounding};
+
+// Reason why an operation or an expression could not be evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    Overflow,
+    // Exact result of a widened operation that does not fit back into the number type
+    OutOfRange(i128),
+    DivisionByZero,
+    NegativeExponent,
+    // A fixed point number raised to a power with decimals
+    NonIntegerExponent,
+    NegativeSquareRoot,
+    // Floating point operation without a meaningful result, such as inf - inf
+    NotANumber,
+    InvalidIndex(u64),
+    UnknownVariable(String),
+    // Not an identifier, or one of the reserved names (see RESERVED_NAMES)
+    InvalidVariableName(String),
+    UnknownMacro(String),
+    // No formatter is registered under the name, see crate::format
+    UnknownFormatter(String),
+    // A macro needs at least one successful operation
+    EmptyMacro,
+    // stop_recording was called without start_recording
+    NotRecording,
+    // A replay was abandoned because step (counted from 0) failed; nothing was recorded
+    MacroFailed { step: usize, error: Box<CalcError> },
+    // Shift or rotation by a negative amount, or by at least the bit width of the number type
+    InvalidShift { amount: i128, bits: u32 },
+    // The number type has no meaning for the operator, e.g. bitwise operators on f64
+    UnsupportedOperation { operator: String, number: &'static str },
+    // An RPN command needed more values than the stack holds
+    StackUnderflow { needed: usize, found: usize },
+    Parse(ParseError),
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::Overflow => write!(f, "overflow"),
+            CalcError::OutOfRange(value) => write!(f, "result {} is out of range", value),
+            CalcError::DivisionByZero => write!(f, "division by zero"),
+            CalcError::NegativeExponent => write!(f, "negative exponent"),
+            CalcError::NonIntegerExponent => write!(f, "exponent is not a whole number"),
+            CalcError::NegativeSquareRoot => write!(f, "square root of a negative number"),
+            CalcError::NotANumber => write!(f, "result is not a number"),
+            CalcError::InvalidIndex(index) => write!(f, "no operation at index {}", index),
+            CalcError::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            CalcError::InvalidVariableName(name) => write!(f, "invalid variable name '{}'", name),
+            CalcError::UnknownMacro(name) => write!(f, "unknown macro '{}'", name),
+            CalcError::UnknownFormatter(name) => write!(f, "unknown history format '{}'", name),
+            CalcError::EmptyMacro => write!(f, "macro has no successful operation"),
+            CalcError::NotRecording => write!(f, "no macro is being recorded"),
+            CalcError::MacroFailed { step, error } => {
+                write!(f, "macro step {} failed: {}", step, error)
+            }
+            CalcError::InvalidShift { amount, bits } => {
+                write!(f, "shift by {} is out of range for {} bit numbers", amount, bits)
+            }
+            CalcError::UnsupportedOperation { operator, number } => {
+                write!(f, "operator {} is not supported for {} numbers", operator, number)
+            }
+            CalcError::StackUnderflow { needed, found } => {
+                write!(f, "stack holds {} values, {} needed", found, needed)
+            }
+            CalcError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+impl From<ParseError> for CalcError {
+    fn from(err: ParseError) -> Self {
+        CalcError::Parse(err)
+    }
+}
+
+// How an operation behaves when its result does not fit into the number type
+//
+// Checked:    the operation fails with CalcError::Overflow
+// Saturating: the result is clamped to the type's MIN or MAX
+// Wrapping:   the result wraps around (two's complement)
+// Widening:   the operation is computed in i128; a result that does not fit
+//             back into the number type fails with CalcError::OutOfRange carrying it
+//
+// Division by zero, negative exponents and negative square roots fail under every policy.
+// This describes the integer types, f64 and BigInt interpret the policies in
+// their own way (see crate::numeric).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OverflowPolicy {
+    #[default]
+    Checked,
+    Saturating,
+    Wrapping,
+    Widening,
+}
+
+impl OverflowPolicy {
+    pub fn name(&self) -> &str {
+        match self {
+            OverflowPolicy::Checked => "checked",
+            OverflowPolicy::Saturating => "saturating",
+            OverflowPolicy::Wrapping => "wrapping",
+            OverflowPolicy::Widening => "widening",
+        }
+    }
+
+    // Inverse of name
+    pub fn from_name(name: &str) -> Option<OverflowPolicy> {
+        match name {
+            "checked" => Some(OverflowPolicy::Checked),
+            "saturating" => Some(OverflowPolicy::Saturating),
+            "wrapping" => Some(OverflowPolicy::Wrapping),
+            "widening" => Some(OverflowPolicy::Widening),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Where an operand of a recorded operation came from, so that macros chain
+// the steps that actually used each other's results (see crate::macros)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Operand {
+    // A number given as such, the value of a variable, or the result of an
+    // operation that is not the last successful one
+    #[default]
+    Literal,
+    // The result of the last successful operation recorded before this one
+    Previous,
+}
+
+impl Operand {
+    pub fn name(&self) -> &str {
+        match self {
+            Operand::Literal => "literal",
+            Operand::Previous => "previous",
+        }
+    }
+
+    // Inverse of name
+    pub fn from_name(name: &str) -> Option<Operand> {
+        match name {
+            "literal" => Some(Operand::Literal),
+            "previous" => Some(Operand::Previous),
+            _ => None,
+        }
+    }
+}
+
+// Ordered as declared, so statistics per operator are listed in this order
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum OperationType {
     Addition,
     Subtraction,
     Multiplication,
+    Division,
+    Remainder,
+    Power,
+    SquareRoot,
+    // Stores the first operand in the operation's variable, never fails
+    Assignment,
+    // Bitwise operators, only supported by the primitive integer types
+    And,
+    Or,
+    Xor,
+    Not,
+    // Shifts and rotations by the second operand, which has to be below the bit width
+    ShiftLeft,
+    ShiftRight,
+    RotateLeft,
+    RotateRight,
 }
 
 impl OperationType {
     // Return the string representation of the operation sign
-    // Addition -> "+", Subtraction -> "-", Multiplication -> "*"
+    // Addition -> "+", Subtraction -> "-", Multiplication -> "*",
+    // Division -> "/", Remainder -> "%", Power -> "^", SquareRoot -> "sqrt",
+    // Assignment -> "=", And -> "&", Or -> "|", Xor -> "xor", Not -> "~",
+    // ShiftLeft -> "<<", ShiftRight -> ">>", RotateLeft -> "rotl", RotateRight -> "rotr"
     pub fn get_sign(&self) -> &str {
         match self {
             OperationType::Addition => "+",
             OperationType::Subtraction => "-",
             OperationType::Multiplication => "*",
+            OperationType::Division => "/",
+            OperationType::Remainder => "%",
+            OperationType::Power => "^",
+            OperationType::SquareRoot => "sqrt",
+            OperationType::Assignment => "=",
+            OperationType::And => "&",
+            OperationType::Or => "|",
+            OperationType::Xor => "xor",
+            OperationType::Not => "~",
+            OperationType::ShiftLeft => "<<",
+            OperationType::ShiftRight => ">>",
+            OperationType::RotateLeft => "rotl",
+            OperationType::RotateRight => "rotr",
         }
     }
 
+    // Inverse of get_sign, used when reading persisted history
+    pub fn from_sign(sign: &str) -> Option<OperationType> {
+        match sign {
+            "+" => Some(OperationType::Addition),
+            "-" => Some(OperationType::Subtraction),
+            "*" => Some(OperationType::Multiplication),
+            "/" => Some(OperationType::Division),
+            "%" => Some(OperationType::Remainder),
+            "^" => Some(OperationType::Power),
+            "sqrt" => Some(OperationType::SquareRoot),
+            "=" => Some(OperationType::Assignment),
+            "&" => Some(OperationType::And),
+            "|" => Some(OperationType::Or),
+            "xor" => Some(OperationType::Xor),
+            "~" => Some(OperationType::Not),
+            "<<" => Some(OperationType::ShiftLeft),
+            ">>" => Some(OperationType::ShiftRight),
+            "rotl" => Some(OperationType::RotateLeft),
+            "rotr" => Some(OperationType::RotateRight),
+            _ => None,
+        }
+    }
+
+    // Unary operations only use the first operand, the second one is ignored
+    pub fn is_unary(&self) -> bool {
+        matches!(
+            self,
+            OperationType::SquareRoot | OperationType::Assignment | OperationType::Not
+        )
+    }
+
+    // Whether the operation works on the bits of its operands rather than on their value
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            OperationType::And
+                | OperationType::Or
+                | OperationType::Xor
+                | OperationType::Not
+                | OperationType::ShiftLeft
+                | OperationType::ShiftRight
+                | OperationType::RotateLeft
+                | OperationType::RotateRight
+        )
+    }
+
     // Perform the operation on two i64 numbers with overflow protection
-    // Return Some(result) on success, None on overflow
+    // Return Ok(result) on success, or the CalcError describing the failure
     //
     // Example: OperationType::Multiplication.perform(x, y)
-    pub fn perform(&self, x: i64, y: i64) -> Option<i64> {
-        match self {
-            OperationType::Addition => x.checked_add(y),
-            OperationType::Subtraction => x.checked_sub(y),
-            OperationType::Multiplication => x.checked_mul(y),
-        }
+    pub fn perform(&self, x: i64, y: i64) -> Result<i64, CalcError> {
+        self.perform_with(x, y, OverflowPolicy::Checked)
+    }
+
+    // Perform the operation, resolving overflow according to the given policy
+    pub fn perform_with(&self, x: i64, y: i64, policy: OverflowPolicy) -> Result<i64, CalcError> {
+        self.compute(&x, &y, policy)
+    }
+
+    // Perform the operation on any number type, see crate::numeric for what each one does
+    pub fn compute<N: Number>(&self, x: &N, y: &N, policy: OverflowPolicy) -> Result<N, CalcError> {
+        N::compute(self, x, y, policy)
+    }
+
+    // Same as compute, rounding inexact results of fixed point numbers as requested
+    pub fn compute_rounded<N: Number>(
+        &self,
+        x: &N,
+        y: &N,
+        policy: OverflowPolicy,
+        rounding: Rounding,
+    ) -> Result<N, CalcError> {
+        N::compute_rounded(self, x, y, policy, rounding)
     }
 }
 
 #[derive(Clone)]
-pub struct Operation {
-    pub first_num: i64,
-    pub second_num: i64,
+pub struct Operation<N = i64> {
+    pub first_num: N,
+    pub second_num: N,
     pub operation_type: OperationType,
+    // Overflow policy the operation was performed with
+    pub policy: OverflowPolicy,
+    // Rounding the operation was performed with, only used by fixed point numbers
+    pub rounding: Rounding,
+    // Outcome computed when the operation was performed
+    pub result: Result<N, CalcError>,
+    // Position of the operation in the calculator's log, assigned when it is recorded
+    pub sequence: u64,
+    // Seconds since the Unix epoch at which the operation was performed
+    pub timestamp: u64,
+    // Variable receiving the value, only set for assignments
+    pub variable: Option<String>,
+    // Value the variable had before the assignment, set when it is recorded and restored by undo
+    pub previous_value: Option<N>,
+    // Shared by all steps of one macro replay: the sequence number of its first step
+    pub macro_id: Option<u64>,
+    // Where first_num and second_num came from, set when the operation is recorded
+    pub first_source: Operand,
+    pub second_source: Operand,
 }
 
 impl Operation {
-    // Create a new Operation with the given parameters
+    // Create a new Operation with the given parameters, performing it now
     pub fn new(first_num: i64, second_num: i64, operation_type: OperationType) -> Self {
+        Self::with_policy(first_num, second_num, operation_type, OverflowPolicy::Checked)
+    }
+}
+
+impl<N: Number> Operation<N> {
+    // Same as new, for any number type and performing the operation with the given overflow policy
+    pub fn with_policy(
+        first_num: N,
+        second_num: N,
+        operation_type: OperationType,
+        policy: OverflowPolicy,
+    ) -> Self {
+        Self::with_rounding(first_num, second_num, operation_type, policy, Rounding::default())
+    }
+
+    // Same as with_policy, rounding the result as requested
+    pub fn with_rounding(
+        first_num: N,
+        second_num: N,
+        operation_type: OperationType,
+        policy: OverflowPolicy,
+        rounding: Rounding,
+    ) -> Self {
+        let result = operation_type.compute_rounded(&first_num, &second_num, policy, rounding);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
         Self {
             first_num,
             second_num,
             operation_type,
+            policy,
+            rounding,
+            result,
+            sequence: 0,
+            timestamp,
+            variable: None,
+            previous_value: None,
+            macro_id: None,
+            first_source: Operand::Literal,
+            second_source: Operand::Literal,
         }
     }
+
+    // Create an assignment of value to the named variable
+    pub fn assignment(variable: &str, value: N) -> Self {
+        let mut op = Self::with_policy(
+            value,
+            N::zero(),
+            OperationType::Assignment,
+            OverflowPolicy::Checked,
+        );
+        op.variable = Some(variable.to_string());
+        op
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
 }
 
-pub struct Calculator {
-    pub history: Vec<Operation>,
+// Selects which history entries are rendered by Calculator::show_history_view
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoryView {
+    All,
+    Successful,
+    Failed,
+}
+
+impl HistoryView {
+    pub fn includes<N: Number>(&self, op: &Operation<N>) -> bool {
+        match self {
+            HistoryView::All => true,
+            HistoryView::Successful => op.is_success(),
+            HistoryView::Failed => !op.is_success(),
+        }
+    }
+}
+
+// Name of the variable holding the memory register used by M+, M-, MR and MC
+pub const MEMORY: &str = "M";
+
+// Names with a special meaning in expressions, which cannot be assigned to with let
+pub const RESERVED_NAMES: [&str; 9] =
+    ["ans", MEMORY, "MR", "MC", "let", "sqrt", "xor", "rotl", "rotr"];
+
+// A value with the sequence number of the operation producing it, None for
+// numbers and variables
+type Traced<N> = (N, Option<u64>);
+
+// Whether name can be assigned to: an identifier that is not reserved
+pub fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    starts_well
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED_NAMES.contains(&name)
+}
+
+// The number type defaults to i64; other backends are created with
+// Calculator::<f64>::default() and the like
+pub struct Calculator<N = i64> {
+    // Recorded operations, oldest first, ordered by sequence number
+    pub history: VecDeque<Operation<N>>,
+    // Operations removed by undo, most recently undone last
+    redo_stack: Vec<Operation<N>>,
+    // Oldest operations are evicted once the history grows beyond this size
+    max_history: Option<usize>,
+    // Policy used for every new operation
+    policy: OverflowPolicy,
+    // Rounding used for every new operation
+    rounding: Rounding,
+    next_sequence: u64,
+    // Current value of every assigned variable, including the memory register
+    variables: BTreeMap<String, N>,
+    // Last successful result, available as ans in expressions
+    ans: Option<N>,
+    // Macros by name, see crate::macros
+    pub(crate) macros: BTreeMap<String, Macro<N>>,
+    // Name of the macro being recorded live and the sequence number it starts at
+    pub(crate) recording: Option<(String, u64)>,
+    // History formatters by name, see crate::format
+    pub(crate) formatters: BTreeMap<String, Box<dyn HistoryFormatter<N>>>,
+    // Operand stack of the RPN mode, top last, see crate::rpn
+    pub(crate) stack: Vec<N>,
+}
+
+impl<N: Number> Default for Calculator<N> {
+    fn default() -> Self {
+        Self {
+            history: VecDeque::new(),
+            redo_stack: vec![],
+            max_history: None,
+            policy: OverflowPolicy::Checked,
+            rounding: Rounding::default(),
+            next_sequence: 0,
+            variables: BTreeMap::new(),
+            ans: None,
+            macros: BTreeMap::new(),
+            recording: None,
+            formatters: format::built_in()
+                .into_iter()
+                .map(|formatter| (formatter.name().to_string(), formatter))
+                .collect(),
+            stack: Vec::new(),
+        }
+    }
 }
 
 impl Calculator {
-    // Create a new Calculator with empty history
+    // Create a new Calculator with empty, unbounded history
     pub fn new() -> Self {
-        Self { history: vec![] }
+        Self::default()
+    }
+
+    // Create a new Calculator keeping at most max_history operations
+    pub fn with_max_history(max_history: usize) -> Self {
+        let mut calculator = Self::new();
+        calculator.max_history = Some(max_history);
+        calculator
+    }
+}
+
+impl<N: Number> Calculator<N> {
+    pub fn max_history(&self) -> Option<usize> {
+        self.max_history
+    }
+
+    // Change the history limit (None for unbounded), evicting the oldest operations if needed
+    pub fn set_max_history(&mut self, max_history: Option<usize>) {
+        self.max_history = max_history;
+        self.evict();
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.policy
     }
 
-    // Perform addition and store successful operations in history
-    // Return Some(result) on success, None on overflow
-    pub fn addition(&mut self, x: i64, y: i64) -> Option<i64> {
-        let add = OperationType::Addition.perform(x, y);
-        if add.is_some() {
-            self.history
-                .push(Operation::new(x, y, OperationType::Addition));
+    // Select how subsequent operations handle overflow
+    // Operations already in history keep the policy they were performed with
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    // Select how subsequent operations round inexact results
+    // Only fixed point numbers round (see crate::fixed), the other number types ignore it
+    pub fn set_rounding(&mut self, rounding: Rounding) {
+        self.rounding = rounding;
+    }
+
+    // Perform addition and store the operation in history
+    // Return Ok(result) on success, Err(CalcError::Overflow) on overflow
+    pub fn addition(&mut self, x: N, y: N) -> Result<N, CalcError> {
+        self.apply(OperationType::Addition, x, y)
+    }
+
+    // Perform subtraction and store the operation in history
+    // Return Ok(result) on success, Err(CalcError::Overflow) on overflow
+    pub fn subtraction(&mut self, x: N, y: N) -> Result<N, CalcError> {
+        self.apply(OperationType::Subtraction, x, y)
+    }
+
+    // Perform multiplication and store the operation in history
+    // Return Ok(result) on success, Err(CalcError::Overflow) on overflow
+    pub fn multiplication(&mut self, x: N, y: N) -> Result<N, CalcError> {
+        self.apply(OperationType::Multiplication, x, y)
+    }
+
+    // Perform integer division (rounding toward zero) and store the operation in history
+    // Return Err(CalcError::DivisionByZero) when y is 0, Err(CalcError::Overflow) for i64::MIN / -1
+    pub fn division(&mut self, x: N, y: N) -> Result<N, CalcError> {
+        self.apply(OperationType::Division, x, y)
+    }
+
+    // Compute the remainder of x / y and store the operation in history
+    // Return Err(CalcError::DivisionByZero) when y is 0, Err(CalcError::Overflow) for i64::MIN % -1
+    pub fn remainder(&mut self, x: N, y: N) -> Result<N, CalcError> {
+        self.apply(OperationType::Remainder, x, y)
+    }
+
+    // Raise x to the power of y and store the operation in history
+    // Return Err(CalcError::NegativeExponent) when y is negative, Err(CalcError::Overflow) on overflow
+    pub fn power(&mut self, x: N, y: N) -> Result<N, CalcError> {
+        self.apply(OperationType::Power, x, y)
+    }
+
+    // Compute the integer square root (rounded down) and store the operation in history
+    // Return Err(CalcError::NegativeSquareRoot) when x is negative
+    pub fn square_root(&mut self, x: N) -> Result<N, CalcError> {
+        self.apply(OperationType::SquareRoot, x, N::zero())
+    }
+
+    // Store value in the named variable and record the assignment in history
+    // Return Err(CalcError::InvalidVariableName) if the name is not an identifier or is reserved
+    pub fn assign(&mut self, name: &str, value: N) -> Result<N, CalcError> {
+        if !is_variable_name(name) {
+            return Err(CalcError::InvalidVariableName(name.to_string()));
+        }
+        Ok(self.store(name, value))
+    }
+
+    // Current value of a variable; "ans" gives the last result, "MR" and "M" the memory register
+    // Return Err(CalcError::UnknownVariable) if nothing was assigned to it
+    pub fn lookup(&self, name: &str) -> Result<N, CalcError> {
+        let value = match name {
+            "ans" => self.ans.clone(),
+            "MR" | MEMORY => Some(self.memory_recall()),
+            _ => self.variables.get(name).cloned(),
+        };
+        value.ok_or_else(|| CalcError::UnknownVariable(name.to_string()))
+    }
+
+    // Assigned variables ordered by name, the memory register excluded
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &N)> {
+        self.variables
+            .iter()
+            .filter(|(name, _)| name.as_str() != MEMORY)
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    // Result of the last successful operation or expression
+    pub fn ans(&self) -> Option<&N> {
+        self.ans.as_ref()
+    }
+
+    // Value of the memory register, zero until something is stored in it
+    pub fn memory_recall(&self) -> N {
+        self.variables.get(MEMORY).cloned().unwrap_or_else(N::zero)
+    }
+
+    // Add x to the memory register (M+), recorded as the addition followed by the assignment
+    // Return the new memory value, or the error of the addition, which leaves memory untouched
+    pub fn memory_add(&mut self, x: N) -> Result<N, CalcError> {
+        self.memory_apply(OperationType::Addition, (x, None))
+    }
+
+    // Subtract x from the memory register (M-), see memory_add
+    pub fn memory_subtract(&mut self, x: N) -> Result<N, CalcError> {
+        self.memory_apply(OperationType::Subtraction, (x, None))
+    }
+
+    // Apply the operation to memory and x, then assign its result to memory
+    fn memory_apply(
+        &mut self,
+        operation_type: OperationType,
+        x: Traced<N>,
+    ) -> Result<N, CalcError> {
+        let memory = (self.memory_recall(), None);
+        let value = self.apply_traced(operation_type, memory, x)?;
+        Ok(self.store_traced(MEMORY, value))
+    }
+
+    // Reset the memory register to zero (MC)
+    pub fn memory_clear(&mut self) {
+        self.store(MEMORY, N::zero());
+    }
+
+    // Record the assignment of value to name, which cannot fail
+    fn store(&mut self, name: &str, value: N) -> N {
+        self.store_traced(name, (value, None))
+    }
+
+    fn store_traced(&mut self, name: &str, (value, from): Traced<N>) -> N {
+        let mut op = Operation::assignment(name, value.clone());
+        op.first_source = self.source(from);
+        let _ = self.record(op);
+        value
+    }
+
+    // Parse and evaluate an infix expression such as "(-4 + 9) * 3 - 2"
+    //
+    // Every step (and every negation of a non-literal, recorded as 0 - x)
+    // goes through OperationType::perform and is stored in history.
+    // Evaluation stops at the first failing step, which is recorded as well.
+    //
+    // Expressions can refer to variables, ans and MR (or M). The input can also be
+    // an assignment "let fee = 5000" or a memory command "M+ 5", "M- 5"
+    // ("M+" and "M-" alone use ans) or "MC". "M - 5", with a space after M,
+    // is an expression reading memory, see expression::parse_statement_as.
+    pub fn evaluate(&mut self, expr: &str) -> Result<N, CalcError> {
+        let statement = expression::parse_statement_as::<N>(expr)?;
+        // ans refers to the result before this input, not to its intermediate steps
+        let ans = self.ans.clone().map(|value| (value, self.last_success()));
+        let res = match statement {
+            Statement::Expression(ast) => self.evaluate_node(&ast, &ans)?.0,
+            Statement::Assignment { name, value } => {
+                // Checked first so that an invalid name does not record the steps of the value
+                if !is_variable_name(&name) {
+                    return Err(CalcError::InvalidVariableName(name));
+                }
+                let value = self.evaluate_node(&value, &ans)?;
+                self.store_traced(&name, value)
+            }
+            Statement::MemoryAdd(operand) => {
+                let value = self.evaluate_operand(operand.as_ref(), &ans)?;
+                self.memory_apply(OperationType::Addition, value)?
+            }
+            Statement::MemorySubtract(operand) => {
+                let value = self.evaluate_operand(operand.as_ref(), &ans)?;
+                self.memory_apply(OperationType::Subtraction, value)?
+            }
+            Statement::MemoryClear => {
+                self.memory_clear();
+                N::zero()
+            }
+        };
+        self.ans = Some(res.clone());
+        Ok(res)
+    }
+
+    // Evaluate the operand of a memory command, defaulting to ans
+    fn evaluate_operand(
+        &mut self,
+        operand: Option<&Expr<N>>,
+        ans: &Option<Traced<N>>,
+    ) -> Result<Traced<N>, CalcError> {
+        match operand {
+            Some(operand) => self.evaluate_node(operand, ans),
+            None => ans
+                .clone()
+                .ok_or_else(|| CalcError::UnknownVariable("ans".to_string())),
         }
-        add
     }
 
-    // Perform subtraction and store successful operations in history
-    // Return Some(result) on success, None on overflow
-    pub fn subtraction(&mut self, x: i64, y: i64) -> Option<i64> {
-        let sub = OperationType::Subtraction.perform(x, y);
-        if sub.is_some() {
-            self.history
-                .push(Operation::new(x, y, OperationType::Subtraction));
+    fn evaluate_node(
+        &mut self,
+        node: &Expr<N>,
+        ans: &Option<Traced<N>>,
+    ) -> Result<Traced<N>, CalcError> {
+        match node {
+            Expr::Number(value) => Ok((value.clone(), None)),
+            Expr::Variable(name) if name == "ans" => ans
+                .clone()
+                .ok_or_else(|| CalcError::UnknownVariable(name.clone())),
+            Expr::Variable(name) => Ok((self.lookup(name)?, None)),
+            Expr::Negate(operand) => {
+                let value = self.evaluate_node(operand, ans)?;
+                self.apply_traced(OperationType::Subtraction, (N::zero(), None), value)
+            }
+            Expr::Unary {
+                operation_type,
+                operand,
+            } => {
+                let value = self.evaluate_node(operand, ans)?;
+                self.apply_traced(operation_type.clone(), value, (N::zero(), None))
+            }
+            Expr::Binary {
+                operation_type,
+                lhs,
+                rhs,
+            } => {
+                let x = self.evaluate_node(lhs, ans)?;
+                let y = self.evaluate_node(rhs, ans)?;
+                self.apply_traced(operation_type.clone(), x, y)
+            }
         }
-        sub
     }
 
-    // Perform multiplication and store successful operations in history
-    // Return Some(result) on success, None on overflow
-    pub fn multiplication(&mut self, x: i64, y: i64) -> Option<i64> {
-        let mul = OperationType::Multiplication.perform(x, y);
-        if mul.is_some() {
-            self.history
-                .push(Operation::new(x, y, OperationType::Multiplication));
+    // Same as apply, recording where the operands came from
+    // Return the result with the sequence number of the operation
+    fn apply_traced(
+        &mut self,
+        operation_type: OperationType,
+        (x, x_from): Traced<N>,
+        (y, y_from): Traced<N>,
+    ) -> Result<Traced<N>, CalcError> {
+        let mut op = Operation::with_rounding(x, y, operation_type, self.policy, self.rounding);
+        op.first_source = self.source(x_from);
+        op.second_source = self.source(y_from);
+        let sequence = self.next_sequence;
+        self.record(op).map(|value| (value, Some(sequence)))
+    }
+
+    // An operand produced by the operation with sequence number from is Previous
+    // if no other operation succeeded since
+    fn source(&self, from: Option<u64>) -> Operand {
+        match from {
+            Some(_) if from == self.last_success() => Operand::Previous,
+            _ => Operand::Literal,
         }
-        mul
+    }
+
+    // Sequence number of the last successful operation in history
+    fn last_success(&self) -> Option<u64> {
+        self.history
+            .iter()
+            .rev()
+            .find(|op| op.is_success())
+            .map(|op| op.sequence)
+    }
+
+    // Perform the operation and store it in history, whether it succeeds or not
+    pub(crate) fn apply(
+        &mut self,
+        operation_type: OperationType,
+        x: N,
+        y: N,
+    ) -> Result<N, CalcError> {
+        self.record(Operation::with_rounding(
+            x,
+            y,
+            operation_type,
+            self.policy,
+            self.rounding,
+        ))
+    }
+
+    // Give the operation the next sequence number and append it to history
+    //
+    // Recording a new operation discards everything that could be redone.
+    pub(crate) fn record(&mut self, mut op: Operation<N>) -> Result<N, CalcError> {
+        op.sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let res = op.result.clone();
+        self.redo_stack.clear();
+        self.apply_effects(&mut op);
+        self.history.push_back(op);
+        self.evict();
+        res
+    }
+
+    // Drop the oldest operations until the history fits into max_history
+    fn evict(&mut self) {
+        if let Some(max_history) = self.max_history {
+            while self.history.len() > max_history {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    // Update ans and, for assignments, the variable with a newly recorded operation
+    // The value the variable had before is kept in the operation for undo
+    fn apply_effects(&mut self, op: &mut Operation<N>) {
+        if let Ok(value) = &op.result {
+            self.ans = Some(value.clone());
+            if let Some(variable) = &op.variable {
+                op.previous_value = self.variables.insert(variable.clone(), value.clone());
+            }
+        }
+    }
+
+    // Replace the history, continuing the sequence after its last operation
+    // Variables and ans are restored from the assignments and results in it
+    pub(crate) fn replace_history(&mut self, mut history: Vec<Operation<N>>) {
+        self.next_sequence = history.last().map_or(0, |op| op.sequence + 1);
+        self.variables.clear();
+        self.ans = None;
+        for op in &mut history {
+            self.apply_effects(op);
+        }
+        self.history = history.into();
+        self.redo_stack.clear();
+        self.evict();
+    }
+
+    // Sequence number the next recorded operation will get
+    pub(crate) fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    // Look up an operation in history by its sequence number
+    pub fn get(&self, sequence: u64) -> Option<&Operation<N>> {
+        self.history
+            .binary_search_by_key(&sequence, |op| op.sequence)
+            .ok()
+            .map(|position| &self.history[position])
     }
 
     // Generate a formatted string showing all operations in history
     // Format: "index: first_num operation_sign second_num = result\n"
+    // Unary operations are shown as "index: operation_sign(first_num) = result\n"
+    // Failed operations are shown as "index: first_num operation_sign second_num failed: reason\n"
+    // Operations not performed with the checked policy end with the policy, e.g. " (wrapping)"
+    // and those not rounded with truncate with the rounding, e.g. " (wrapping, half-even)"
+    // Assignments are shown as "index: variable = value\n"
+    // Steps of a macro replay end with the id of the replay, e.g. " [macro 4]"
     //
-    // Example: "0: 5 + 3 = 8\n1: 10 - 2 = 8\n"
+    // Example: "0: 5 + 3 = 8\n1: 10 - 2 = 8\n2: sqrt(16) = 4\n3: 1 / 0 failed: division by zero\n"
     pub fn show_history(&self) -> String {
-        let mut res = String::new();
-        for (index, item) in self.history.iter().enumerate() {
-            res.push_str(
-                format!(
-                    "{}: {} {} {} = {}\n",
-                    index,
-                    item.first_num,
-                    item.operation_type.get_sign(),
-                    item.second_num,
-                    item.operation_type
-                        .perform(item.first_num, item.second_num)
-                        .unwrap()
-                )
-                .as_str(),
-            )
-        }
-        res
+        self.show_history_view(HistoryView::All)
     }
 
-    // Repeat an operation from history by index
+    // Same as show_history, restricted to the operations selected by view
+    pub fn show_history_view(&self, view: HistoryView) -> String {
+        self.format_history(&DefaultFormatter, view)
+    }
+
+    // Repeat an operation from history by index (its sequence number, as shown by show_history)
+    // The operation is performed with the overflow policy and rounding it was recorded with
     // Add the repeated operation to history and return the result
-    // Return None if the index is invalid
-    pub fn repeat(&mut self, operation_index: usize) -> Option<i64> {
-        match self.history.get(operation_index) {
-            None => None,
+    // Return Err(CalcError::InvalidIndex) if the index is invalid or was evicted
+    pub fn repeat(&mut self, operation_index: u64) -> Result<N, CalcError> {
+        match self.get(operation_index) {
+            None => Err(CalcError::InvalidIndex(operation_index)),
             Some(op) => {
-                let op = op.clone();
-                let res = op.operation_type.perform(op.first_num, op.second_num);
-                self.history.push(op);
-                res
+                let mut repeated = Operation::with_rounding(
+                    op.first_num.clone(),
+                    op.second_num.clone(),
+                    op.operation_type.clone(),
+                    op.policy,
+                    op.rounding,
+                );
+                repeated.variable = op.variable.clone();
+                self.record(repeated)
             }
         }
     }
 
+    // Remove the most recent operation from history and return it
+    // The operation can be restored with redo until a new operation is recorded
+    // Return None if the history is empty
+    //
+    // Undoing an assignment gives the variable back the value it had before,
+    // even if the assignment of that value was evicted, or removes it if it had none.
+    pub fn undo(&mut self) -> Option<Operation<N>> {
+        let op = self.history.pop_back()?;
+        if let (Some(variable), true) = (&op.variable, op.is_success()) {
+            match &op.previous_value {
+                Some(value) => self.variables.insert(variable.clone(), value.clone()),
+                None => self.variables.remove(variable),
+            };
+        }
+        self.ans = self
+            .history
+            .iter()
+            .rev()
+            .find_map(|earlier| earlier.result.clone().ok());
+        self.next_sequence = op.sequence;
+        self.redo_stack.push(op.clone());
+        Some(op)
+    }
+
+    // Restore the most recently undone operation, keeping its sequence number
+    // Return None if there is nothing to redo
+    pub fn redo(&mut self) -> Option<&Operation<N>> {
+        let mut op = self.redo_stack.pop()?;
+        self.next_sequence = op.sequence + 1;
+        self.apply_effects(&mut op);
+        self.history.push_back(op);
+        self.evict();
+        self.history.back()
+    }
+
     //  Clear all operations from history
+    // Variables and the memory register keep their values
     pub fn clear_history(&mut self) {
         self.history.clear();
+        self.redo_stack.clear();
+        self.next_sequence = 0;
     }
 }
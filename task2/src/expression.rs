@@ -0,0 +1,632 @@
+//! Infix expression support for the calculator.
+//!
+//! Input such as `(-4 + 9) * 3 - 2` is split into tokens, parsed by a small
+//! recursive descent parser into an `Expr` tree and then evaluated node by
+//! node by `Calculator::evaluate`.
+//!
+//! A line of calculator input is a `Statement`: an expression, an assignment
+//! `let name = expression` or one of the memory commands `M+`, `M-` and `MC`.
+
+use std::fmt;
+
+use crate::calculator::OperationType;
+use crate::numeric::Number;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<N = i64> {
+    Number(N),
+    // A variable, ans or MR, looked up when the expression is evaluated
+    Variable(String),
+    Negate(Box<Expr<N>>),
+    Unary {
+        operation_type: OperationType,
+        operand: Box<Expr<N>>,
+    },
+    Binary {
+        operation_type: OperationType,
+        lhs: Box<Expr<N>>,
+        rhs: Box<Expr<N>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement<N = i64> {
+    Expression(Expr<N>),
+    Assignment { name: String, value: Expr<N> },
+    // M+ and M-, without an operand they use ans
+    MemoryAdd(Option<Expr<N>>),
+    MemorySubtract(Option<Expr<N>>),
+    MemoryClear,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorReason {
+    EmptyExpression,
+    UnexpectedCharacter(char),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UnclosedParenthesis,
+    NumberOutOfRange(String),
+    // A literal the number type cannot represent at all, such as 1.5 for an integer type
+    InvalidNumber(String),
+    UnknownFunction(String),
+}
+
+// A parse failure together with the byte offset in the input where it was detected
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub reason: ParseErrorReason,
+}
+
+impl ParseError {
+    pub(crate) fn new(position: usize, reason: ParseErrorReason) -> Self {
+        Self { position, reason }
+    }
+}
+
+impl fmt::Display for ParseErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorReason::EmptyExpression => write!(f, "empty expression"),
+            ParseErrorReason::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            ParseErrorReason::UnexpectedToken(t) => write!(f, "unexpected token '{}'", t),
+            ParseErrorReason::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ParseErrorReason::UnclosedParenthesis => write!(f, "unclosed parenthesis"),
+            ParseErrorReason::NumberOutOfRange(n) => write!(f, "number {} is out of range", n),
+            ParseErrorReason::InvalidNumber(n) => write!(f, "invalid number {}", n),
+            ParseErrorReason::UnknownFunction(name) => write!(f, "unknown function '{}'", name),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parse error at position {}: {}", self.position, self.reason)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Number(String),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Ampersand,
+    Pipe,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    LeftParen,
+    RightParen,
+    Equals,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Number(n) => write!(f, "{}", n),
+            TokenKind::Identifier(name) => write!(f, "{}", name),
+            TokenKind::Plus => write!(f, "+"),
+            TokenKind::Minus => write!(f, "-"),
+            TokenKind::Star => write!(f, "*"),
+            TokenKind::Slash => write!(f, "/"),
+            TokenKind::Percent => write!(f, "%"),
+            TokenKind::Caret => write!(f, "^"),
+            TokenKind::Ampersand => write!(f, "&"),
+            TokenKind::Pipe => write!(f, "|"),
+            TokenKind::Tilde => write!(f, "~"),
+            TokenKind::ShiftLeft => write!(f, "<<"),
+            TokenKind::ShiftRight => write!(f, ">>"),
+            TokenKind::LeftParen => write!(f, "("),
+            TokenKind::RightParen => write!(f, ")"),
+            TokenKind::Equals => write!(f, "="),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '0'..='9' => {
+                // A fraction is kept in the literal, the number type decides whether it is valid
+                // Hex and binary literals ("0xff", "0b101") keep their letters as well
+                let radix_prefix = c == '0' && input[position + 1..].starts_with(['x', 'b']);
+                let mut digits = String::new();
+                while let Some(&(_, d)) = chars.peek() {
+                    let hex_digit = radix_prefix && d.is_ascii_alphanumeric();
+                    if !(d.is_ascii_digit() || d == '.' || hex_digit) {
+                        break;
+                    }
+                    digits.push(d);
+                    chars.next();
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Number(digits),
+                    position,
+                });
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&(_, d)) = chars.peek() {
+                    if !(d.is_ascii_alphanumeric() || d == '_') {
+                        break;
+                    }
+                    name.push(d);
+                    chars.next();
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Identifier(name),
+                    position,
+                });
+                continue;
+            }
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '^' => TokenKind::Caret,
+            '&' => TokenKind::Ampersand,
+            '|' => TokenKind::Pipe,
+            '~' => TokenKind::Tilde,
+            '<' | '>' if input[position + 1..].starts_with(c) => {
+                chars.next();
+                chars.next();
+                let kind = if c == '<' { TokenKind::ShiftLeft } else { TokenKind::ShiftRight };
+                tokens.push(Token { kind, position });
+                continue;
+            }
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '=' => TokenKind::Equals,
+            other => {
+                return Err(ParseError::new(
+                    position,
+                    ParseErrorReason::UnexpectedCharacter(other),
+                ))
+            }
+        };
+        chars.next();
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(ParseError::new(0, ParseErrorReason::EmptyExpression));
+        }
+        Ok(Parser {
+            tokens,
+            current: 0,
+            end: input.len(),
+        })
+    }
+
+    // Return parsed, provided that all of the input was consumed
+    fn finish<T>(&self, parsed: T) -> Result<T, ParseError> {
+        match self.peek() {
+            Some(_) => Err(self.unexpected()),
+            None => Ok(parsed),
+        }
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.current).map(|t| &t.kind)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.current)
+            .map_or(self.end, |t| t.position)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.current).cloned();
+        self.current += 1;
+        token
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.tokens.get(self.current) {
+            Some(token) => ParseError::new(
+                token.position,
+                ParseErrorReason::UnexpectedToken(token.kind.to_string()),
+            ),
+            None => ParseError::new(self.end, ParseErrorReason::UnexpectedEnd),
+        }
+    }
+
+    // expression := xor ("|" xor)*
+    fn expression<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let mut lhs = self.xor()?;
+        while let Some(TokenKind::Pipe) = self.peek() {
+            self.advance();
+            let rhs = self.xor()?;
+            lhs = binary(OperationType::Or, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    // xor := and ("xor" and)*
+    fn xor<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let mut lhs = self.and()?;
+        while matches!(self.peek(), Some(TokenKind::Identifier(name)) if name == "xor") {
+            self.advance();
+            let rhs = self.and()?;
+            lhs = binary(OperationType::Xor, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    // and := shift ("&" shift)*
+    fn and<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let mut lhs = self.shift()?;
+        while let Some(TokenKind::Ampersand) = self.peek() {
+            self.advance();
+            let rhs = self.shift()?;
+            lhs = binary(OperationType::And, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    // shift := sum (("<<" | ">>" | "rotl" | "rotr") sum)*
+    fn shift<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let mut lhs = self.sum()?;
+        loop {
+            let operation_type = match self.peek() {
+                Some(TokenKind::ShiftLeft) => OperationType::ShiftLeft,
+                Some(TokenKind::ShiftRight) => OperationType::ShiftRight,
+                Some(TokenKind::Identifier(name)) if name == "rotl" => OperationType::RotateLeft,
+                Some(TokenKind::Identifier(name)) if name == "rotr" => OperationType::RotateRight,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.sum()?;
+            lhs = binary(operation_type, lhs, rhs);
+        }
+    }
+
+    // sum := term (("+" | "-") term)*
+    fn sum<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let mut lhs = self.term()?;
+        loop {
+            let operation_type = match self.peek() {
+                Some(TokenKind::Plus) => OperationType::Addition,
+                Some(TokenKind::Minus) => OperationType::Subtraction,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.term()?;
+            lhs = Expr::Binary {
+                operation_type,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+    }
+
+    // term := unary (("*" | "/" | "%") unary)*
+    fn term<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let operation_type = match self.peek() {
+                Some(TokenKind::Star) => OperationType::Multiplication,
+                Some(TokenKind::Slash) => OperationType::Division,
+                Some(TokenKind::Percent) => OperationType::Remainder,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.unary()?;
+            lhs = Expr::Binary {
+                operation_type,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+    }
+
+    // unary := "-" unary | "~" unary | power
+    //
+    // A minus directly in front of a literal is folded into the literal,
+    // so that i64::MIN can be written and `-4` is not recorded as `0 - 4`.
+    // The literal is not folded when it is the base of a power: -2^2 == -(2^2).
+    fn unary<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        if let Some(TokenKind::Tilde) = self.peek() {
+            self.advance();
+            let operand = self.unary()?;
+            return Ok(Expr::Unary {
+                operation_type: OperationType::Not,
+                operand: Box::new(operand),
+            });
+        }
+        if let Some(TokenKind::Minus) = self.peek() {
+            self.advance();
+            // A unit belongs to the literal, "-2 SOL ^ 2" is a power as well
+            let unit = self.unit::<N>(self.current + 1).is_some();
+            let after_literal = self.current + 1 + usize::from(unit);
+            let followed_by_power = matches!(
+                self.tokens.get(after_literal).map(|t| &t.kind),
+                Some(TokenKind::Caret)
+            );
+            if let Some(TokenKind::Number(digits)) = self.peek() {
+                if !followed_by_power {
+                    let literal = format!("-{}", digits);
+                    return self.number(&literal);
+                }
+            }
+            let operand = self.unary()?;
+            return Ok(Expr::Negate(Box::new(operand)));
+        }
+        self.power()
+    }
+
+    // power := primary ("^" unary)?
+    //
+    // The exponent is parsed through unary, which makes "^" right associative.
+    fn power<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let base = self.primary()?;
+        if let Some(TokenKind::Caret) = self.peek() {
+            self.advance();
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary {
+                operation_type: OperationType::Power,
+                lhs: Box::new(base),
+                rhs: Box::new(exponent),
+            });
+        }
+        Ok(base)
+    }
+
+    // Consume the number token, and the unit following it if there is one
+    // literal is the text of the number token, with a folded minus in front
+    fn number<N: Number>(&mut self, literal: &str) -> Result<Expr<N>, ParseError> {
+        let position = self.position();
+        self.advance();
+        match self.unit::<N>(self.current) {
+            Some(unit) => {
+                self.advance();
+                parse_number(literal, Some(&unit), position)
+            }
+            None => parse_number(literal, None, position),
+        }
+    }
+
+    // The token at index if it is a unit of N, as in "1.5 SOL"
+    fn unit<N: Number>(&self, index: usize) -> Option<String> {
+        match self.tokens.get(index).map(|t| &t.kind) {
+            Some(TokenKind::Identifier(name)) if N::has_unit(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    // primary := number unit? | variable | "(" expression ")" | function "(" expression ")"
+    fn primary<N: Number>(&mut self) -> Result<Expr<N>, ParseError> {
+        let position = self.position();
+        let followed_by_paren = matches!(
+            self.tokens.get(self.current + 1).map(|t| &t.kind),
+            Some(TokenKind::LeftParen)
+        );
+        match self.peek() {
+            Some(TokenKind::Number(digits)) => {
+                let digits = digits.clone();
+                self.number(&digits)
+            }
+            Some(TokenKind::LeftParen) => {
+                self.advance();
+                self.parenthesized(position)
+            }
+            Some(TokenKind::Identifier(name)) if name != "sqrt" && !followed_by_paren => {
+                let name = name.clone();
+                self.advance();
+                Ok(Expr::Variable(name))
+            }
+            Some(TokenKind::Identifier(name)) => {
+                let operation_type = match name.as_str() {
+                    "sqrt" => OperationType::SquareRoot,
+                    _ => {
+                        return Err(ParseError::new(
+                            position,
+                            ParseErrorReason::UnknownFunction(name.clone()),
+                        ))
+                    }
+                };
+                self.advance();
+                let paren_position = self.position();
+                match self.peek() {
+                    Some(TokenKind::LeftParen) => self.advance(),
+                    _ => return Err(self.unexpected()),
+                };
+                let operand = self.parenthesized(paren_position)?;
+                Ok(Expr::Unary {
+                    operation_type,
+                    operand: Box::new(operand),
+                })
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // Parse the rest of a parenthesized expression whose "(" at open_position was consumed
+    fn parenthesized<N: Number>(&mut self, open_position: usize) -> Result<Expr<N>, ParseError> {
+        let inner = self.expression()?;
+        match self.peek() {
+            Some(TokenKind::RightParen) => {
+                self.advance();
+                Ok(inner)
+            }
+            Some(_) => Err(self.unexpected()),
+            None => Err(ParseError::new(
+                open_position,
+                ParseErrorReason::UnclosedParenthesis,
+            )),
+        }
+    }
+}
+
+fn binary<N>(operation_type: OperationType, lhs: Expr<N>, rhs: Expr<N>) -> Expr<N> {
+    Expr::Binary {
+        operation_type,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+
+fn parse_number<N: Number>(
+    literal: &str,
+    unit: Option<&str>,
+    position: usize,
+) -> Result<Expr<N>, ParseError> {
+    let parsed = match unit {
+        Some(unit) => N::parse_with_unit(literal, unit),
+        None => N::parse_literal(literal),
+    };
+    parsed.map(Expr::Number).ok_or_else(|| {
+        let text = match unit {
+            Some(unit) => format!("{} {}", literal, unit),
+            None => literal.to_string(),
+        };
+        // When the digits are fine only the magnitude can be the problem
+        let reason = if well_formed::<N>(literal) {
+            ParseErrorReason::NumberOutOfRange(text)
+        } else {
+            ParseErrorReason::InvalidNumber(text)
+        };
+        ParseError::new(position, reason)
+    })
+}
+
+// Whether the literal is made of valid digits: decimal without a fraction, or
+// hex and binary for number types that accept them
+fn well_formed<N: Number>(literal: &str) -> bool {
+    let unsigned = literal.strip_prefix('-').unwrap_or(literal);
+    let (prefix, digits, valid): (&str, &str, fn(char) -> bool) =
+        match (unsigned.strip_prefix("0x"), unsigned.strip_prefix("0b")) {
+            (Some(digits), _) => ("0x0", digits, |c| c.is_ascii_hexdigit()),
+            (_, Some(digits)) => ("0b0", digits, |c| c == '0' || c == '1'),
+            _ => return !literal.contains('.'),
+        };
+    N::parse_literal(prefix).is_some() && !digits.is_empty() && digits.chars().all(valid)
+}
+
+// Parse an infix expression into an AST
+//
+// Supports number literals, parentheses, unary minus, `sqrt(...)` and the
+// binary operators `+`, `-`, `*`, `/`, `%` and `^` with the usual precedence.
+// `^` binds tightest and is right associative.
+//
+// The bitwise operators bind looser than `+` and `-`, as in C: shifts and
+// rotations (`<<`, `>>`, `rotl`, `rotr`) first, then `&`, `xor` and `|`.
+// `~` is a prefix operator like unary minus. Integer literals can be written
+// in hex and binary, e.g. `0xff` and `0b1010`.
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    parse_as::<i64>(input)
+}
+
+// Same as parse, with literals of the given number type (e.g. `1.5` for f64)
+// Number types with units accept a unit after a literal, e.g. `1.5 SOL`
+pub fn parse_as<N: Number>(input: &str) -> Result<Expr<N>, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let expr = parser.expression()?;
+    parser.finish(expr)
+}
+
+// Parse a line of calculator input, see Statement
+//
+// statement := "let" identifier "=" expression
+//            | "M+" expression? | "M-" expression? | "MC"
+//            | expression
+//
+// "M+" and "M-" are commands only when written as one word followed by
+// whitespace or nothing, otherwise the input is an expression reading the
+// memory register M, e.g. "M - 1".
+pub fn parse_statement_as<N: Number>(input: &str) -> Result<Statement<N>, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let first = match parser.peek() {
+        Some(TokenKind::Identifier(name)) => name.clone(),
+        _ => String::new(),
+    };
+    let second = parser.tokens.get(1).map(|t| t.kind.clone());
+
+    let statement = match (first.as_str(), second) {
+        ("let", _) => {
+            parser.advance();
+            let name = match parser.peek() {
+                Some(TokenKind::Identifier(name)) => name.clone(),
+                _ => return Err(parser.unexpected()),
+            };
+            parser.advance();
+            match parser.peek() {
+                Some(TokenKind::Equals) => parser.advance(),
+                _ => return Err(parser.unexpected()),
+            };
+            Statement::Assignment {
+                name,
+                value: parser.expression()?,
+            }
+        }
+        ("M", Some(sign @ (TokenKind::Plus | TokenKind::Minus)))
+            if memory_command(&parser.tokens) =>
+        {
+            parser.advance();
+            parser.advance();
+            let operand = match parser.peek() {
+                None => None,
+                Some(_) => Some(parser.expression()?),
+            };
+            match sign {
+                TokenKind::Plus => Statement::MemoryAdd(operand),
+                _ => Statement::MemorySubtract(operand),
+            }
+        }
+        ("MC", None) => {
+            parser.advance();
+            Statement::MemoryClear
+        }
+        _ => Statement::Expression(parser.expression()?),
+    };
+    parser.finish(statement)
+}
+
+// Whether the first two tokens are written as one word, with whitespace or nothing after it
+fn memory_command(tokens: &[Token]) -> bool {
+    match tokens {
+        [name, sign, rest @ ..] => {
+            sign.position == name.position + 1
+                && rest
+                    .first()
+                    .is_none_or(|next| next.position > sign.position + 1)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,313 @@
+//! Decimal fixed point numbers, such as SOL amounts.
+//!
+//! A `Fixed<D>` stores a whole number of base units (lamports for SOL) in an
+//! i64. The `Denomination` D gives the number of decimals and the unit names,
+//! so `Fixed<Sol>` holds 1.5 SOL as 1_500_000_000 lamports.
+//!
+//! Addition, subtraction and remainder are performed on the base units by the
+//! i64 backend, so they follow exactly the same overflow rules. Multiplication,
+//! division, power and square root round their exact result with the
+//! `Rounding` of the operation and then apply the overflow policy; Widening
+//! reports the exact base unit result of an overflow through OutOfRange.
+//! A power whose intermediate result does not even fit into an i128 has no
+//! exact result to wrap or report, so it fails with Overflow unless the
+//! policy is Saturating.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::calculator::{
+    CalcError, Calculator, HistoryView, Operation, OperationType, OverflowPolicy,
+};
+use crate::format::{default_entry, HistoryFormatter};
+use crate::numeric::{unsupported, Number, Rounding};
+
+// Describes a fixed point currency
+pub trait Denomination: Clone + Copy + PartialEq {
+    // Stored in history files, see Number::NAME
+    const NAME: &'static str;
+    // Number of decimals, at most 18
+    const SCALE: u32;
+    // Name of the whole unit, e.g. "SOL"
+    const UNIT: &'static str;
+    // Name of the base unit, e.g. "lamports"
+    const BASE_UNIT: &'static str;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sol;
+
+impl Denomination for Sol {
+    const NAME: &'static str = "sol";
+    const SCALE: u32 = 9;
+    const UNIT: &'static str = "SOL";
+    const BASE_UNIT: &'static str = "lamports";
+}
+
+// Unit a fixed point number is shown in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    // e.g. "1.5 SOL"
+    Whole,
+    // e.g. "1500000000 lamports"
+    Base,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Fixed<D> {
+    base_units: i64,
+    denomination: PhantomData<D>,
+}
+
+pub type SolAmount = Fixed<Sol>;
+
+impl<D: Denomination> Fixed<D> {
+    // Base units per whole unit; evaluating it fails to compile for a SCALE above 18
+    const FACTOR: i64 = 10i64.pow(D::SCALE);
+
+    pub fn from_base_units(base_units: i64) -> Self {
+        Self {
+            base_units,
+            denomination: PhantomData,
+        }
+    }
+
+    // Return None if the amount does not fit
+    pub fn from_whole_units(whole_units: i64) -> Option<Self> {
+        whole_units
+            .checked_mul(Self::FACTOR)
+            .map(Self::from_base_units)
+    }
+
+    pub fn base_units(&self) -> i64 {
+        self.base_units
+    }
+
+    // The number followed by the unit, e.g. "1.5 SOL" or "1500000000 lamports"
+    pub fn format_in(&self, unit: Unit) -> String {
+        match unit {
+            Unit::Whole => format!("{} {}", self, D::UNIT),
+            Unit::Base => format!("{} {}", self.base_units, D::BASE_UNIT),
+        }
+    }
+
+    // Multiply two amounts given in base units, keeping the scale
+    fn multiply(x: i128, y: i128, rounding: Rounding) -> Option<i128> {
+        rounding.divide(x.checked_mul(y)?, i128::from(Self::FACTOR))
+    }
+
+    // Square and multiply, rounding after every multiplication
+    // Return None if an intermediate result does not fit into an i128
+    fn power(x: i128, exponent: u64, rounding: Rounding) -> Option<i128> {
+        let (mut base, mut exponent, mut res) = (x, exponent, i128::from(Self::FACTOR));
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                res = Self::multiply(res, base, rounding)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = Self::multiply(base, base, rounding)?;
+            }
+        }
+        Some(res)
+    }
+
+    // Bring an exact result in base units back into an i64 according to the policy
+    fn narrow(
+        wide: Option<i128>,
+        policy: OverflowPolicy,
+        negative: bool,
+    ) -> Result<Self, CalcError> {
+        let res = match (wide, policy) {
+            (Some(wide), _) if i64::try_from(wide).is_ok() => wide as i64,
+            (Some(wide), OverflowPolicy::Wrapping) => wide as i64,
+            (Some(wide), OverflowPolicy::Widening) => return Err(CalcError::OutOfRange(wide)),
+            (_, OverflowPolicy::Saturating) if negative => i64::MIN,
+            (_, OverflowPolicy::Saturating) => i64::MAX,
+            _ => return Err(CalcError::Overflow),
+        };
+        Ok(Self::from_base_units(res))
+    }
+}
+
+// Derived ordering would require an ordered denomination
+impl<D: Denomination> PartialOrd for Fixed<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.base_units.cmp(&other.base_units))
+    }
+}
+
+impl<D: Denomination> fmt::Display for Fixed<D> {
+    // Whole units with as many decimals as needed, e.g. "1.5", "-0.000000001" or "3"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let factor = Self::FACTOR as u64;
+        let magnitude = self.base_units.unsigned_abs();
+        let sign = if self.base_units < 0 { "-" } else { "" };
+        let fraction = magnitude % factor;
+        let res = if fraction == 0 {
+            format!("{}{}", sign, magnitude / factor)
+        } else {
+            let digits = format!("{:0width$}", fraction, width = D::SCALE as usize);
+            format!(
+                "{}{}.{}",
+                sign,
+                magnitude / factor,
+                digits.trim_end_matches('0')
+            )
+        };
+        f.pad(&res)
+    }
+}
+
+impl<D: Denomination> fmt::Debug for Fixed<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format_in(Unit::Whole))
+    }
+}
+
+impl<D: Denomination> Number for Fixed<D> {
+    const NAME: &'static str = D::NAME;
+
+    fn zero() -> Self {
+        Self::from_base_units(0)
+    }
+
+    // Whole units with at most SCALE decimals, e.g. "1.5" or "-0.25"
+    fn parse_literal(literal: &str) -> Option<Self> {
+        let (negative, digits) = match literal.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, literal),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !all_digits(whole) || !all_digits(fraction) {
+            return None;
+        }
+        if fraction.len() > D::SCALE as usize || digits.ends_with('.') {
+            return None;
+        }
+        let padded = format!("{}{:0<width$}", whole, fraction, width = D::SCALE as usize);
+        let magnitude: i128 = padded.parse().ok()?;
+        let base_units = if negative { -magnitude } else { magnitude };
+        i64::try_from(base_units).ok().map(Self::from_base_units)
+    }
+
+    fn compute(
+        operation_type: &OperationType,
+        x: &Self,
+        y: &Self,
+        policy: OverflowPolicy,
+    ) -> Result<Self, CalcError> {
+        Self::compute_rounded(operation_type, x, y, policy, Rounding::default())
+    }
+
+    fn compute_rounded(
+        operation_type: &OperationType,
+        x: &Self,
+        y: &Self,
+        policy: OverflowPolicy,
+        rounding: Rounding,
+    ) -> Result<Self, CalcError> {
+        let (wide_x, wide_y) = (i128::from(x.base_units), i128::from(y.base_units));
+        let factor = i128::from(Self::FACTOR);
+        match operation_type {
+            OperationType::Addition
+            | OperationType::Subtraction
+            | OperationType::Remainder
+            | OperationType::Assignment => {
+                i64::compute(operation_type, &x.base_units, &y.base_units, policy)
+                    .map(Self::from_base_units)
+            }
+            OperationType::Multiplication => {
+                let negative = (wide_x < 0) != (wide_y < 0);
+                Self::narrow(Self::multiply(wide_x, wide_y, rounding), policy, negative)
+            }
+            OperationType::Division => {
+                if wide_y == 0 {
+                    return Err(CalcError::DivisionByZero);
+                }
+                let negative = (wide_x < 0) != (wide_y < 0);
+                Self::narrow(rounding.divide(wide_x * factor, wide_y), policy, negative)
+            }
+            OperationType::Power => {
+                if wide_y < 0 {
+                    return Err(CalcError::NegativeExponent);
+                }
+                if wide_y % factor != 0 {
+                    return Err(CalcError::NonIntegerExponent);
+                }
+                let exponent = (wide_y / factor) as u64;
+                let negative = wide_x < 0 && exponent % 2 == 1;
+                Self::narrow(Self::power(wide_x, exponent, rounding), policy, negative)
+            }
+            OperationType::SquareRoot => {
+                if wide_x < 0 {
+                    return Err(CalcError::NegativeSquareRoot);
+                }
+                // sqrt(x / factor) * factor == sqrt(x * factor)
+                let scaled = wide_x * factor;
+                let root = scaled.isqrt();
+                let remainder = scaled - root * root;
+                // The exact root is never halfway, so both half modes round up past root + 1/2
+                let rounded = match rounding {
+                    Rounding::Truncate | Rounding::Floor => root,
+                    Rounding::Ceil if remainder > 0 => root + 1,
+                    Rounding::HalfUp | Rounding::HalfEven if remainder > root => root + 1,
+                    _ => root,
+                };
+                Self::narrow(Some(rounded), policy, false)
+            }
+            // Bits of a scaled amount mean nothing
+            _ => Err(unsupported::<Self>(operation_type)),
+        }
+    }
+
+    // The whole unit and the base unit, case insensitive; "lamport" is accepted as well
+    fn has_unit(unit: &str) -> bool {
+        unit.eq_ignore_ascii_case(D::UNIT)
+            || unit.eq_ignore_ascii_case(D::BASE_UNIT)
+            || unit.eq_ignore_ascii_case(D::BASE_UNIT.trim_end_matches('s'))
+    }
+
+    fn parse_with_unit(literal: &str, unit: &str) -> Option<Self> {
+        if unit.eq_ignore_ascii_case(D::UNIT) {
+            return Self::parse_literal(literal);
+        }
+        // Base units are whole numbers
+        literal.parse().ok().map(Self::from_base_units)
+    }
+
+    fn formatters() -> Vec<Box<dyn HistoryFormatter<Self>>> {
+        vec![
+            Box::new(UnitFormatter(Unit::Whole)),
+            Box::new(UnitFormatter(Unit::Base)),
+        ]
+    }
+}
+
+// The default history layout with every number followed by the unit it is shown in
+// Every calculator of fixed point numbers has both, as "whole-units" and "base-units"
+pub struct UnitFormatter(pub Unit);
+
+impl<D: Denomination> HistoryFormatter<Fixed<D>> for UnitFormatter {
+    fn name(&self) -> &str {
+        match self.0 {
+            Unit::Whole => "whole-units",
+            Unit::Base => "base-units",
+        }
+    }
+
+    fn entry(&self, op: &Operation<Fixed<D>>) -> String {
+        default_entry(op, &|value: &Fixed<D>| value.format_in(self.0))
+    }
+}
+
+impl<D: Denomination> Calculator<Fixed<D>> {
+    // Same as show_history, with every number followed by the unit it is shown in
+    //
+    // Example in lamports: "0: 1500000000 lamports + 2 lamports = 1500000002 lamports\n"
+    pub fn show_history_in(&self, unit: Unit) -> String {
+        self.format_history(&UnitFormatter(unit), HistoryView::All)
+    }
+}
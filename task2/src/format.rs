@@ -0,0 +1,327 @@
+//! Pluggable rendering of the calculator history.
+//!
+//! A `HistoryFormatter` turns operations into text: an optional header, one
+//! entry per operation and an optional footer. Every calculator knows the
+//! built-in formatters below by name and accepts more through
+//! `register_formatter`, which replaces a formatter with the same name.
+//!
+//! - `default`: the layout of `show_history`, e.g. `0: 5 + 3 = 8`
+//! - `rpn`: Reverse Polish Notation, e.g. `0: 5 3 + = 8`
+//! - `markdown`: a Markdown table
+//! - `latex`: a LaTeX `tabular` environment
+//! - `hex`, `binary`: the default layout with integers in base 16 or 2, e.g.
+//!   `0: 0xff & 0xf = 0xf`
+//!
+//! Number types can add their own through `Number::formatters`; fixed point
+//! numbers add `whole-units` and `base-units` (see `crate::fixed`).
+
+use crate::calculator::{
+    CalcError, Calculator, HistoryView, Operation, OperationType, OverflowPolicy,
+};
+use crate::numeric::{Number, Radix, Rounding};
+
+pub trait HistoryFormatter<N: Number> {
+    // Name the formatter is registered and selected by
+    fn name(&self) -> &str;
+
+    // Text in front of the first entry, ending with a newline unless empty
+    fn header(&self) -> String {
+        String::new()
+    }
+
+    // One operation, without the trailing newline
+    fn entry(&self, op: &Operation<N>) -> String;
+
+    // Text after the last entry, ending with a newline unless empty
+    fn footer(&self) -> String {
+        String::new()
+    }
+}
+
+// The layout of show_history
+pub struct DefaultFormatter;
+
+// Operands before the operator, e.g. "5 3 +" and "16 sqrt"
+pub struct RpnFormatter;
+
+// A table with the columns #, Operation, Result and Notes
+pub struct MarkdownFormatter;
+
+// A tabular environment with the same columns as MarkdownFormatter
+pub struct LatexFormatter;
+
+// The layout of show_history with numbers in another base
+// Number types without bits (see Number::to_radix) are written in decimal
+pub struct RadixFormatter(pub Radix);
+
+// The entry of DefaultFormatter, with every number written by the given function
+//
+// Used by formatters that only change how numbers look, such as the unit
+// formatter of fixed point numbers (see crate::fixed).
+pub fn default_entry<N: Number>(op: &Operation<N>, number: &dyn Fn(&N) -> String) -> String {
+    let mut res = match (&op.variable, &op.result) {
+        (Some(variable), Ok(value)) => {
+            return format!(
+                "{}: {} = {}{}",
+                op.sequence,
+                variable,
+                number(value),
+                macro_note(op)
+            )
+        }
+        _ if op.operation_type.is_unary() => format!(
+            "{}: {}({})",
+            op.sequence,
+            op.operation_type.get_sign(),
+            number(&op.first_num)
+        ),
+        _ => format!(
+            "{}: {} {} {}",
+            op.sequence,
+            number(&op.first_num),
+            op.operation_type.get_sign(),
+            number(&op.second_num)
+        ),
+    };
+    match &op.result {
+        Ok(result) => res.push_str(format!(" = {}", number(result)).as_str()),
+        Err(err) => res.push_str(format!(" failed: {}", err).as_str()),
+    }
+    if let Some(settings) = settings(op) {
+        res.push_str(format!(" ({})", settings).as_str());
+    }
+    res.push_str(macro_note(op).as_str());
+    res
+}
+
+// The policy unless it is checked and the rounding unless it is truncate
+// e.g. "wrapping, half-even"
+fn settings<N: Number>(op: &Operation<N>) -> Option<String> {
+    let mut settings = Vec::new();
+    if op.policy != OverflowPolicy::Checked {
+        settings.push(op.policy.name());
+    }
+    if op.rounding != Rounding::default() {
+        settings.push(op.rounding.name());
+    }
+    (!settings.is_empty()).then(|| settings.join(", "))
+}
+
+// " [macro id]" for the steps of a macro replay, empty otherwise
+fn macro_note<N: Number>(op: &Operation<N>) -> String {
+    match op.macro_id {
+        Some(macro_id) => format!(" [macro {}]", macro_id),
+        None => String::new(),
+    }
+}
+
+// The settings and the macro id as a table cell, e.g. "wrapping, macro 4"
+fn notes<N: Number>(op: &Operation<N>) -> String {
+    let mut notes: Vec<String> = settings(op).into_iter().collect();
+    if let Some(macro_id) = op.macro_id {
+        notes.push(format!("macro {}", macro_id));
+    }
+    notes.join(", ")
+}
+
+impl<N: Number> HistoryFormatter<N> for DefaultFormatter {
+    fn name(&self) -> &str {
+        "default"
+    }
+
+    fn entry(&self, op: &Operation<N>) -> String {
+        default_entry(op, &N::to_string)
+    }
+}
+
+impl<N: Number> HistoryFormatter<N> for RadixFormatter {
+    fn name(&self) -> &str {
+        match self.0 {
+            Radix::Hex => "hex",
+            Radix::Binary => "binary",
+        }
+    }
+
+    fn entry(&self, op: &Operation<N>) -> String {
+        default_entry(op, &|value: &N| {
+            value.to_radix(self.0).unwrap_or_else(|| value.to_string())
+        })
+    }
+}
+
+impl<N: Number> HistoryFormatter<N> for RpnFormatter {
+    fn name(&self) -> &str {
+        "rpn"
+    }
+
+    // Assignments are shown as "index: value variable ="
+    fn entry(&self, op: &Operation<N>) -> String {
+        let operation = match &op.variable {
+            Some(variable) => format!("{} {} =", op.first_num, variable),
+            None if op.operation_type.is_unary() => {
+                format!("{} {}", op.first_num, op.operation_type.get_sign())
+            }
+            None => format!(
+                "{} {} {}",
+                op.first_num,
+                op.second_num,
+                op.operation_type.get_sign()
+            ),
+        };
+        let mut res = match &op.result {
+            Ok(result) => format!("{}: {} = {}", op.sequence, operation, result),
+            Err(err) => format!("{}: {} failed: {}", op.sequence, operation, err),
+        };
+        if let Some(settings) = settings(op) {
+            res.push_str(format!(" ({})", settings).as_str());
+        }
+        res.push_str(macro_note(op).as_str());
+        res
+    }
+}
+
+impl<N: Number> HistoryFormatter<N> for MarkdownFormatter {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn header(&self) -> String {
+        "| # | Operation | Result | Notes |\n| ---: | --- | --- | --- |\n".to_string()
+    }
+
+    fn entry(&self, op: &Operation<N>) -> String {
+        let operation = match &op.variable {
+            Some(variable) => format!("{} = {}", variable, op.first_num),
+            None if op.operation_type.is_unary() => {
+                format!("{}({})", op.operation_type.get_sign(), op.first_num)
+            }
+            None => format!(
+                "{} {} {}",
+                op.first_num,
+                op.operation_type.get_sign(),
+                op.second_num
+            ),
+        };
+        let result = match &op.result {
+            Ok(result) => result.to_string(),
+            Err(err) => format!("failed: {}", err),
+        };
+        // A pipe would end the cell early
+        let cell = |text: String| text.replace('|', "\\|");
+        format!(
+            "| {} | `{}` | {} | {} |",
+            op.sequence,
+            cell(operation),
+            cell(result),
+            cell(notes(op))
+        )
+    }
+}
+
+impl<N: Number> HistoryFormatter<N> for LatexFormatter {
+    fn name(&self) -> &str {
+        "latex"
+    }
+
+    fn header(&self) -> String {
+        "\\begin{tabular}{rlll}\n\\# & Operation & Result & Notes \\\\\n\\hline\n".to_string()
+    }
+
+    fn entry(&self, op: &Operation<N>) -> String {
+        let (x, y) = (op.first_num.to_string(), op.second_num.to_string());
+        let operation = match (&op.variable, &op.operation_type) {
+            (Some(variable), _) => format!("\\mathit{{{}}} = {}", latex_text(variable), x),
+            (None, OperationType::Addition) => format!("{} + {}", x, y),
+            (None, OperationType::Subtraction) => format!("{} - {}", x, y),
+            (None, OperationType::Multiplication) => format!("{} \\times {}", x, y),
+            (None, OperationType::Division) => format!("{} \\div {}", x, y),
+            (None, OperationType::Remainder) => format!("{} \\bmod {}", x, y),
+            (None, OperationType::Power) => format!("{{{}}}^{{{}}}", x, y),
+            (None, OperationType::SquareRoot) => format!("\\sqrt{{{}}}", x),
+            (None, OperationType::Assignment) => x,
+            (None, OperationType::And) => format!("{} \\mathbin{{\\&}} {}", x, y),
+            (None, OperationType::Or) => format!("{} \\mid {}", x, y),
+            (None, OperationType::Xor) => format!("{} \\oplus {}", x, y),
+            (None, OperationType::Not) => format!("\\lnot {}", x),
+            (None, OperationType::ShiftLeft) => format!("{} \\ll {}", x, y),
+            (None, OperationType::ShiftRight) => format!("{} \\gg {}", x, y),
+            (None, OperationType::RotateLeft) => {
+                format!("{} \\mathbin{{\\mathrm{{rotl}}}} {}", x, y)
+            }
+            (None, OperationType::RotateRight) => {
+                format!("{} \\mathbin{{\\mathrm{{rotr}}}} {}", x, y)
+            }
+        };
+        let result = match &op.result {
+            Ok(result) => format!("${}$", result),
+            Err(err) => format!("failed: {}", latex_text(&err.to_string())),
+        };
+        format!(
+            "{} & ${}$ & {} & {} \\\\",
+            op.sequence,
+            operation,
+            result,
+            latex_text(&notes(op))
+        )
+    }
+
+    fn footer(&self) -> String {
+        "\\end{tabular}\n".to_string()
+    }
+}
+
+// Escape the characters with a special meaning in LaTeX text that can show up in
+// variable names and error messages
+fn latex_text(text: &str) -> String {
+    text.replace('_', "\\_")
+        .replace('&', "\\&")
+        .replace('%', "\\%")
+}
+
+// The formatters every calculator starts with, followed by those of its number type
+pub(crate) fn built_in<N: Number>() -> Vec<Box<dyn HistoryFormatter<N>>> {
+    let mut formatters: Vec<Box<dyn HistoryFormatter<N>>> = vec![
+        Box::new(DefaultFormatter),
+        Box::new(RpnFormatter),
+        Box::new(MarkdownFormatter),
+        Box::new(LatexFormatter),
+        Box::new(RadixFormatter(Radix::Hex)),
+        Box::new(RadixFormatter(Radix::Binary)),
+    ];
+    formatters.extend(N::formatters());
+    formatters
+}
+
+impl<N: Number> Calculator<N> {
+    // Make a formatter available to show_history_as, replacing one with the same name
+    pub fn register_formatter(&mut self, formatter: Box<dyn HistoryFormatter<N>>) {
+        self.formatters
+            .insert(formatter.name().to_string(), formatter);
+    }
+
+    // Names of the registered formatters, built-in ones included, in alphabetical order
+    pub fn formatter_names(&self) -> impl Iterator<Item = &str> {
+        self.formatters.keys().map(String::as_str)
+    }
+
+    // Render the operations selected by view with the given formatter
+    pub fn format_history(&self, formatter: &dyn HistoryFormatter<N>, view: HistoryView) -> String {
+        let mut res = formatter.header();
+        for op in self.history.iter().filter(|op| view.includes(op)) {
+            res.push_str(formatter.entry(op).as_str());
+            res.push('\n');
+        }
+        res.push_str(formatter.footer().as_str());
+        res
+    }
+
+    // Render the whole history with the formatter registered under name
+    // Return Err(CalcError::UnknownFormatter) if there is none
+    pub fn show_history_as(&self, name: &str) -> Result<String, CalcError> {
+        let formatter = self
+            .formatters
+            .get(name)
+            .ok_or_else(|| CalcError::UnknownFormatter(name.to_string()))?;
+        Ok(self.format_history(formatter.as_ref(), HistoryView::All))
+    }
+}
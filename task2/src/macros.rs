@@ -0,0 +1,204 @@
+//! Named sequences of operations that can be replayed as a unit.
+//!
+//! A macro is taken from a contiguous range of history, either given by
+//! sequence numbers (`record_macro`) or recorded live between
+//! `start_recording` and `stop_recording`. Failed operations in the range are
+//! left out.
+//!
+//! The first operand of the first step becomes the parameter of the macro.
+//! An operand of a later step that was recorded as the result of the step
+//! before it (`Operand::Previous`, e.g. `ans` or a nested expression) is
+//! chained to that result, every other operand is kept as recorded, even if
+//! it happens to equal that result. For `5 + 3 = 8` followed by `ans * 2`,
+//! `run_macro(name, 10)` performs `10 + 3 = 13` and `13 * 2 = 26`. Operands
+//! taken from the RPN stack and given to methods such as `addition` are
+//! always kept.
+//!
+//! A replay is computed completely before anything is recorded. If a step
+//! fails, the replay is abandoned with `CalcError::MacroFailed` and neither
+//! history nor variables change; otherwise all steps are recorded with the
+//! same `macro_id`.
+
+use crate::calculator::{
+    CalcError, Calculator, Operand, Operation, OperationType, OverflowPolicy,
+};
+use crate::numeric::{Number, Rounding};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MacroOperand<N = i64> {
+    // The value the macro is run with
+    Parameter,
+    // The result of the previous step
+    Previous,
+    Value(N),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroStep<N = i64> {
+    pub operation_type: OperationType,
+    pub first: MacroOperand<N>,
+    pub second: MacroOperand<N>,
+    // Steps are replayed with the policy and rounding they were recorded with, like repeat
+    pub policy: OverflowPolicy,
+    pub rounding: Rounding,
+    // Set for assignments
+    pub variable: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Macro<N = i64> {
+    pub name: String,
+    pub steps: Vec<MacroStep<N>>,
+}
+
+impl<N: Number> Macro<N> {
+    // Build a macro from recorded operations, skipping the failed ones
+    // Return Err(CalcError::EmptyMacro) if no operation succeeded
+    pub fn from_operations<'a>(
+        name: &str,
+        operations: impl IntoIterator<Item = &'a Operation<N>>,
+    ) -> Result<Self, CalcError>
+    where
+        N: 'a,
+    {
+        let mut steps: Vec<MacroStep<N>> = Vec::new();
+        for op in operations.into_iter().filter(|op| op.is_success()) {
+            // The result of an operation before the first step is kept as a value
+            let operand = |value: &N, source: Operand| match source {
+                Operand::Previous if !steps.is_empty() => MacroOperand::Previous,
+                _ => MacroOperand::Value(value.clone()),
+            };
+            let first = match steps.is_empty() {
+                true => MacroOperand::Parameter,
+                false => operand(&op.first_num, op.first_source),
+            };
+            let second = operand(&op.second_num, op.second_source);
+            steps.push(MacroStep {
+                operation_type: op.operation_type.clone(),
+                first,
+                second,
+                policy: op.policy,
+                rounding: op.rounding,
+                variable: op.variable.clone(),
+            });
+        }
+        if steps.is_empty() {
+            return Err(CalcError::EmptyMacro);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            steps,
+        })
+    }
+
+    // Perform every step without recording anything
+    // Return the operations in order, or Err(CalcError::MacroFailed) for the first failing step
+    pub fn perform(&self, parameter: &N) -> Result<Vec<Operation<N>>, CalcError> {
+        let mut operations: Vec<Operation<N>> = Vec::new();
+        for (step, item) in self.steps.iter().enumerate() {
+            let previous = operations
+                .last()
+                .and_then(|op| op.result.as_ref().ok())
+                .unwrap_or(parameter);
+            let operand = |operand: &MacroOperand<N>| match operand {
+                MacroOperand::Parameter => parameter.clone(),
+                MacroOperand::Previous => previous.clone(),
+                MacroOperand::Value(value) => value.clone(),
+            };
+            let mut op = Operation::with_rounding(
+                operand(&item.first),
+                operand(&item.second),
+                item.operation_type.clone(),
+                item.policy,
+                item.rounding,
+            );
+            op.variable = item.variable.clone();
+            op.first_source = source(&item.first);
+            op.second_source = source(&item.second);
+            if let Err(err) = &op.result {
+                return Err(CalcError::MacroFailed {
+                    step,
+                    error: Box::new(err.clone()),
+                });
+            }
+            operations.push(op);
+        }
+        Ok(operations)
+    }
+}
+
+// Source of a replayed operand, so that a macro recorded from a replay chains the same way
+fn source<N>(operand: &MacroOperand<N>) -> Operand {
+    match operand {
+        MacroOperand::Previous => Operand::Previous,
+        _ => Operand::Literal,
+    }
+}
+
+impl<N: Number> Calculator<N> {
+    // Save the operations with sequence numbers from..=to as a macro, replacing one with the same name
+    // Return Err(CalcError::InvalidIndex) if one of them is not in history
+    pub fn record_macro(&mut self, name: &str, from: u64, to: u64) -> Result<&Macro<N>, CalcError> {
+        let mut operations = Vec::new();
+        for sequence in from..=to {
+            let op = self
+                .get(sequence)
+                .ok_or(CalcError::InvalidIndex(sequence))?;
+            operations.push(op);
+        }
+        let recorded = Macro::from_operations(name, operations)?;
+        Ok(self.save_macro(recorded))
+    }
+
+    // Start recording a macro from the next operation on
+    // A recording already in progress is discarded
+    pub fn start_recording(&mut self, name: &str) {
+        self.recording = Some((name.to_string(), self.next_sequence()));
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    // Save the operations recorded since start_recording (and not undone) as a macro
+    // Return Err(CalcError::NotRecording) if start_recording was not called
+    pub fn stop_recording(&mut self) -> Result<&Macro<N>, CalcError> {
+        let (name, start) = self.recording.take().ok_or(CalcError::NotRecording)?;
+        let operations = self.history.iter().filter(|op| op.sequence >= start);
+        let recorded = Macro::from_operations(&name, operations)?;
+        Ok(self.save_macro(recorded))
+    }
+
+    pub fn get_macro(&self, name: &str) -> Option<&Macro<N>> {
+        self.macros.get(name)
+    }
+
+    // Macros ordered by name
+    pub fn macros(&self) -> impl Iterator<Item = &Macro<N>> {
+        self.macros.values()
+    }
+
+    // Replay a macro with parameter as its first operand and return the result of the last step
+    // All steps are recorded with a shared macro_id, or none of them if one fails
+    // Return Err(CalcError::UnknownMacro) if there is no macro with that name
+    pub fn run_macro(&mut self, name: &str, parameter: N) -> Result<N, CalcError> {
+        let operations = self
+            .macros
+            .get(name)
+            .ok_or_else(|| CalcError::UnknownMacro(name.to_string()))?
+            .perform(&parameter)?;
+        let macro_id = self.next_sequence();
+        let mut res = Ok(parameter);
+        for mut op in operations {
+            op.macro_id = Some(macro_id);
+            res = self.record(op);
+        }
+        res
+    }
+
+    fn save_macro(&mut self, recorded: Macro<N>) -> &Macro<N> {
+        let name = recorded.name.clone();
+        self.macros.insert(name.clone(), recorded);
+        &self.macros[&name]
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 #![allow(unused_doc_comments)]
+#![allow(clippy::empty_line_after_doc_comments)]
 
 ///-------------------------------------------------------------------------------
 ///
@@ -11,8 +12,25 @@
 ///-------------------------------------------------------------------------------
 
 mod tests;
+mod bigint;
 mod calculator;
+mod expression;
+mod fixed;
+mod format;
+mod macros;
+mod numeric;
+mod persistence;
+mod placement;
+mod query;
+mod repl;
+mod rpn;
+mod scene;
+mod shape_dsl;
 mod shapes;
+mod solids;
+mod svg;
+mod transform;
+mod units;
 use calculator::*;
 use shapes::*;
 
@@ -36,7 +54,7 @@ fn rectangle_example() {
     
     assert_eq!(
         res.err(),
-        Some(Error::InvalidHeight)
+        Some(Error::InvalidHeight(-2.0))
     );
 }
 
@@ -53,7 +71,7 @@ fn circle_example() {
     
     assert_eq!(
         res.err(),
-        Some(Error::InvalidRadius)
+        Some(Error::InvalidRadius(-15.0))
     );
 }
 
@@ -66,12 +84,12 @@ fn calculator_example() {
     let subtraction = calculator.subtraction(x_in, y_in);
     let multiplication = calculator.multiplication(x_in, y_in);
     
-    assert_eq!(addition, Some(5));
-    assert_eq!(subtraction, Some(-13));
-    assert_eq!(multiplication, Some(-36));
+    assert_eq!(addition, Ok(5));
+    assert_eq!(subtraction, Ok(-13));
+    assert_eq!(multiplication, Ok(-36));
 
-    calculator.repeat(1);
-    calculator.repeat(0);
+    calculator.repeat(1).unwrap();
+    calculator.repeat(0).unwrap();
     
     let history = calculator.show_history();
     let expected = "0: -4 + 9 = 5\n1: -4 - 9 = -13\n2: -4 * 9 = -36\n3: -4 - 9 = -13\n4: -4 + 9 = 5\n";
@@ -88,11 +106,39 @@ fn calculator_example() {
     calculator.clear_history();
     assert_eq!(calculator.show_history(), "");
 
-    assert_eq!(calculator.repeat(1), None);
+    assert_eq!(calculator.repeat(1), Err(CalcError::InvalidIndex(1)));
     assert_eq!(calculator.show_history(), "");
 }
 
 fn main() {
+    // `cargo run -- calc [--sol] [script]` starts the calculator instead of the examples
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("calc") {
+        let sol = args.get(1).map(String::as_str) == Some("--sol");
+        let script = args.get(if sol { 2 } else { 1 }).map(String::as_str);
+        let res = if sol {
+            repl::start::<fixed::SolAmount>(script)
+        } else {
+            repl::start::<i64>(script)
+        };
+        if let Err(err) = res {
+            eprintln!("calc: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // `cargo run -- shapes [file]` reports the metrics of the shapes in a file or stdin
+    if args.first().map(String::as_str) == Some("shapes") {
+        match shape_dsl::start(args.get(1).map(String::as_str)) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("shapes: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
     calculator_example();
     rectangle_example();
     circle_example();
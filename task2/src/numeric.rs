@@ -0,0 +1,437 @@
+//! Number types the calculator can work with.
+//!
+//! `Calculator`, `Operation` and `OperationType::compute` are generic over
+//! `Number`. Every backend decides for itself what overflow and invalid
+//! results mean:
+//!
+//! - `i64`, `u64`, `i128`: the overflow policy is applied as described on
+//!   `OverflowPolicy`; Widening computes in i128. The only backends with
+//!   bitwise operators, which never overflow; a shift or rotation by at
+//!   least the bit width fails with `CalcError::InvalidShift` unless the
+//!   policy is Wrapping (the amount is taken modulo the width) or Saturating
+//!   (every bit is shifted out, rotations still take it modulo the width).
+//!   Literals can be written in hex and binary, e.g. `0xff` and `-0b101`
+//! - `f64`: results that are NaN fail with `CalcError::NotANumber`, finite
+//!   operands producing an infinity count as an overflow; Wrapping keeps the
+//!   plain IEEE 754 result, infinities and NaN included
+//! - `BigInt`: never overflows, see `crate::bigint`
+//! - `Fixed`: decimal fixed point, e.g. SOL with 9 decimals, see `crate::fixed`;
+//!   the only backend that uses the `Rounding` of an operation
+
+use std::fmt;
+
+use crate::calculator::{CalcError, OperationType, OverflowPolicy};
+use crate::format::HistoryFormatter;
+
+// Numbers are ordered for history statistics; f64 NaN is not comparable to anything
+pub trait Number: Clone + PartialEq + PartialOrd + fmt::Debug + fmt::Display {
+    // Short name of the type, stored in history files
+    const NAME: &'static str;
+
+    // Whether every value is a whole number, written as a JSON number in history files
+    const INTEGER: bool = false;
+
+    fn zero() -> Self;
+
+    // Parse a literal such as "42", "-7" or, for f64, "1.5"
+    fn parse_literal(literal: &str) -> Option<Self>;
+
+    // Write the number in base 16 or 2 with a "0x" or "0b" prefix, None if the type has no bits
+    // Negative numbers are written as a minus and the magnitude, e.g. "-0x4", which parse_literal
+    // reads back
+    fn to_radix(&self, radix: Radix) -> Option<String> {
+        let _ = radix;
+        None
+    }
+
+    // Perform the operation on x and y (y is ignored by unary operations)
+    fn compute(
+        operation_type: &OperationType,
+        x: &Self,
+        y: &Self,
+        policy: OverflowPolicy,
+    ) -> Result<Self, CalcError>;
+
+    // Same as compute, rounding inexact results as requested
+    // Types whose results are always exact (or rounded by the hardware) ignore the rounding
+    fn compute_rounded(
+        operation_type: &OperationType,
+        x: &Self,
+        y: &Self,
+        policy: OverflowPolicy,
+        rounding: Rounding,
+    ) -> Result<Self, CalcError> {
+        let _ = rounding;
+        Self::compute(operation_type, x, y, policy)
+    }
+
+    // Whether a literal can be followed by the unit name, as in "1.5 SOL"
+    fn has_unit(unit: &str) -> bool {
+        let _ = unit;
+        false
+    }
+
+    // Parse a literal followed by a unit for which has_unit is true
+    fn parse_with_unit(literal: &str, unit: &str) -> Option<Self> {
+        let _ = (literal, unit);
+        None
+    }
+
+    // History formatters every calculator of the type starts with, next to the built-in ones
+    fn formatters() -> Vec<Box<dyn HistoryFormatter<Self>>> {
+        Vec::new()
+    }
+}
+
+// Error of the backends without bits for a bitwise operator
+pub(crate) fn unsupported<N: Number>(operation_type: &OperationType) -> CalcError {
+    CalcError::UnsupportedOperation {
+        operator: operation_type.get_sign().to_string(),
+        number: N::NAME,
+    }
+}
+
+// Base of Number::to_radix
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Radix {
+    Hex,
+    Binary,
+}
+
+// How a result that falls between two representable numbers is rounded
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Rounding {
+    // Toward zero, like integer division
+    #[default]
+    Truncate,
+    // Toward negative infinity
+    Floor,
+    // Toward positive infinity
+    Ceil,
+    // To the nearest number, ties away from zero
+    HalfUp,
+    // To the nearest number, ties to the even one
+    HalfEven,
+}
+
+impl Rounding {
+    pub fn name(&self) -> &str {
+        match self {
+            Rounding::Truncate => "truncate",
+            Rounding::Floor => "floor",
+            Rounding::Ceil => "ceil",
+            Rounding::HalfUp => "half-up",
+            Rounding::HalfEven => "half-even",
+        }
+    }
+
+    // Inverse of name
+    pub fn from_name(name: &str) -> Option<Rounding> {
+        match name {
+            "truncate" => Some(Rounding::Truncate),
+            "floor" => Some(Rounding::Floor),
+            "ceil" => Some(Rounding::Ceil),
+            "half-up" => Some(Rounding::HalfUp),
+            "half-even" => Some(Rounding::HalfEven),
+            _ => None,
+        }
+    }
+
+    // Divide n by d, rounding the quotient
+    // Return None when d is 0 or the quotient does not fit into an i128
+    pub fn divide(&self, n: i128, d: i128) -> Option<i128> {
+        let quotient = n.checked_div(d)?;
+        let remainder = n % d;
+        if remainder == 0 {
+            return Some(quotient);
+        }
+        // The exact quotient lies between quotient and the next number away from zero
+        let negative = (n < 0) != (d < 0);
+        let away = if negative { quotient - 1 } else { quotient + 1 };
+        let twice_remainder = remainder.unsigned_abs() * 2;
+        let rounded = match self {
+            Rounding::Truncate => quotient,
+            Rounding::Floor if negative => away,
+            Rounding::Ceil if !negative => away,
+            Rounding::Floor | Rounding::Ceil => quotient,
+            Rounding::HalfUp if twice_remainder >= d.unsigned_abs() => away,
+            Rounding::HalfEven if twice_remainder > d.unsigned_abs() => away,
+            Rounding::HalfEven if twice_remainder == d.unsigned_abs() && quotient % 2 != 0 => away,
+            Rounding::HalfUp | Rounding::HalfEven => quotient,
+        };
+        Some(rounded)
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+macro_rules! integer_number {
+    ($($t:ty => $name:literal),* $(,)?) => {$(
+        // The same code serves signed and unsigned types
+        #[allow(unused_comparisons)]
+        impl Number for $t {
+            const NAME: &'static str = $name;
+            const INTEGER: bool = true;
+
+            fn zero() -> Self {
+                0
+            }
+
+            // Decimal, or hex and binary with a "0x" or "0b" prefix after an optional minus
+            fn parse_literal(literal: &str) -> Option<Self> {
+                let (negative, digits) = match literal.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, literal),
+                };
+                let (radix, digits) = if let Some(digits) = digits.strip_prefix("0x") {
+                    (16, digits)
+                } else if let Some(digits) = digits.strip_prefix("0b") {
+                    (2, digits)
+                } else {
+                    return literal.parse().ok();
+                };
+                // from_str_radix accepts a sign of its own
+                if digits.starts_with(['+', '-']) {
+                    return None;
+                }
+                let magnitude = u128::from_str_radix(digits, radix).ok()?;
+                if negative {
+                    let value = 0i128.checked_sub_unsigned(magnitude)?;
+                    Self::try_from(value).ok()
+                } else {
+                    Self::try_from(magnitude).ok()
+                }
+            }
+
+            fn to_radix(&self, radix: Radix) -> Option<String> {
+                let sign = if *self < 0 { "-" } else { "" };
+                let magnitude = (*self as i128).unsigned_abs();
+                Some(match radix {
+                    Radix::Hex => format!("{}{:#x}", sign, magnitude),
+                    Radix::Binary => format!("{}{:#b}", sign, magnitude),
+                })
+            }
+
+            fn compute(
+                operation_type: &OperationType,
+                x: &Self,
+                y: &Self,
+                policy: OverflowPolicy,
+            ) -> Result<Self, CalcError> {
+                let (x, y) = (*x, *y);
+                match operation_type {
+                    OperationType::Division | OperationType::Remainder if y == 0 => {
+                        return Err(CalcError::DivisionByZero)
+                    }
+                    OperationType::Power if y < 0 => return Err(CalcError::NegativeExponent),
+                    OperationType::SquareRoot if x < 0 => {
+                        return Err(CalcError::NegativeSquareRoot)
+                    }
+                    OperationType::ShiftLeft
+                    | OperationType::ShiftRight
+                    | OperationType::RotateLeft
+                    | OperationType::RotateRight => {
+                        let too_far = match policy {
+                            OverflowPolicy::Checked | OverflowPolicy::Widening => {
+                                y as u128 >= u128::from(Self::BITS)
+                            }
+                            OverflowPolicy::Saturating | OverflowPolicy::Wrapping => false,
+                        };
+                        if y < 0 || too_far {
+                            return Err(CalcError::InvalidShift {
+                                amount: i128::try_from(y).unwrap_or(i128::MAX),
+                                bits: Self::BITS,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+                if operation_type.is_bitwise() && policy == OverflowPolicy::Widening {
+                    // Nothing to widen, bits are the same in every width
+                    return Ok(Self::wrapping(operation_type, x, y));
+                }
+
+                match policy {
+                    OverflowPolicy::Checked => {
+                        Self::checked(operation_type, x, y).ok_or(CalcError::Overflow)
+                    }
+                    OverflowPolicy::Saturating => Ok(Self::saturating(operation_type, x, y)),
+                    OverflowPolicy::Wrapping => Ok(Self::wrapping(operation_type, x, y)),
+                    OverflowPolicy::Widening => {
+                        let wide = i128::compute(
+                            operation_type,
+                            &i128::try_from(x).map_err(|_| CalcError::Overflow)?,
+                            &i128::try_from(y).map_err(|_| CalcError::Overflow)?,
+                            OverflowPolicy::Checked,
+                        )?;
+                        Self::try_from(wide).map_err(|_| CalcError::OutOfRange(wide))
+                    }
+                }
+            }
+        }
+
+        // The helpers below expect operands that already passed the domain checks in compute
+        impl IntegerOps for $t {
+            fn checked(operation_type: &OperationType, x: Self, y: Self) -> Option<Self> {
+                match operation_type {
+                    OperationType::Addition => x.checked_add(y),
+                    OperationType::Subtraction => x.checked_sub(y),
+                    OperationType::Multiplication => x.checked_mul(y),
+                    OperationType::Division => x.checked_div(y),
+                    OperationType::Remainder => x.checked_rem(y),
+                    OperationType::Power => match u32::try_from(y) {
+                        Ok(exponent) => x.checked_pow(exponent),
+                        // Only 0, 1 and -1 survive exponents this large
+                        Err(_) if x == 0 || x == 1 => Some(x),
+                        Err(_) if Self::MIN != 0 && x.wrapping_add(1) == 0 => {
+                            Some(if y % 2 == 0 { 1 } else { x })
+                        }
+                        Err(_) => None,
+                    },
+                    OperationType::SquareRoot => Some(x.isqrt()),
+                    OperationType::Assignment => Some(x),
+                    // The amount is known to be below the bit width
+                    _ => Some(Self::wrapping(operation_type, x, y)),
+                }
+            }
+
+            #[allow(unused_comparisons)]
+            fn saturating(operation_type: &OperationType, x: Self, y: Self) -> Self {
+                match operation_type {
+                    OperationType::Addition => x.saturating_add(y),
+                    OperationType::Subtraction => x.saturating_sub(y),
+                    OperationType::Multiplication => x.saturating_mul(y),
+                    OperationType::Division => x.saturating_div(y),
+                    // Only MIN % -1 overflows, and its exact result is 0
+                    OperationType::Remainder => x.checked_rem(y).unwrap_or(0),
+                    OperationType::Power => match u32::try_from(y) {
+                        Ok(exponent) => x.saturating_pow(exponent),
+                        Err(_) => Self::checked(operation_type, x, y).unwrap_or(
+                            if x < 0 && y % 2 == 1 { Self::MIN } else { Self::MAX },
+                        ),
+                    },
+                    OperationType::SquareRoot => x.isqrt(),
+                    OperationType::Assignment => x,
+                    // Every bit is shifted out; right shifts of negative numbers keep the sign
+                    OperationType::ShiftLeft if y as u128 >= u128::from(Self::BITS) => 0,
+                    OperationType::ShiftRight if y as u128 >= u128::from(Self::BITS) => {
+                        if x < 0 { !0 } else { 0 }
+                    }
+                    _ => Self::wrapping(operation_type, x, y),
+                }
+            }
+
+            fn wrapping(operation_type: &OperationType, x: Self, y: Self) -> Self {
+                match operation_type {
+                    OperationType::Addition => x.wrapping_add(y),
+                    OperationType::Subtraction => x.wrapping_sub(y),
+                    OperationType::Multiplication => x.wrapping_mul(y),
+                    OperationType::Division => x.wrapping_div(y),
+                    OperationType::Remainder => x.wrapping_rem(y),
+                    // Square and multiply, so exponents beyond u32 wrap as well
+                    OperationType::Power => {
+                        let (mut base, mut exponent, mut res) = (x, y as u128, 1);
+                        while exponent > 0 {
+                            if exponent & 1 == 1 {
+                                res = Self::wrapping_mul(res, base);
+                            }
+                            base = base.wrapping_mul(base);
+                            exponent >>= 1;
+                        }
+                        res
+                    }
+                    OperationType::SquareRoot => x.isqrt(),
+                    OperationType::Assignment => x,
+                    OperationType::And => x & y,
+                    OperationType::Or => x | y,
+                    OperationType::Xor => x ^ y,
+                    OperationType::Not => !x,
+                    // Amounts are taken modulo the bit width
+                    OperationType::ShiftLeft => x.wrapping_shl(Self::shift_amount(y)),
+                    OperationType::ShiftRight => x.wrapping_shr(Self::shift_amount(y)),
+                    OperationType::RotateLeft => x.rotate_left(Self::shift_amount(y)),
+                    OperationType::RotateRight => x.rotate_right(Self::shift_amount(y)),
+                }
+            }
+
+            // A non-negative shift amount modulo the bit width
+            fn shift_amount(y: Self) -> u32 {
+                (y as u128 % u128::from(Self::BITS)) as u32
+            }
+        }
+    )*};
+}
+
+// Per policy arithmetic shared by the primitive integer backends
+trait IntegerOps: Sized {
+    fn checked(operation_type: &OperationType, x: Self, y: Self) -> Option<Self>;
+    fn saturating(operation_type: &OperationType, x: Self, y: Self) -> Self;
+    fn wrapping(operation_type: &OperationType, x: Self, y: Self) -> Self;
+    fn shift_amount(y: Self) -> u32;
+}
+
+integer_number! {
+    i64 => "i64",
+    u64 => "u64",
+    i128 => "i128",
+}
+
+impl Number for f64 {
+    const NAME: &'static str = "f64";
+
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn parse_literal(literal: &str) -> Option<Self> {
+        literal.parse().ok()
+    }
+
+    fn compute(
+        operation_type: &OperationType,
+        x: &Self,
+        y: &Self,
+        policy: OverflowPolicy,
+    ) -> Result<Self, CalcError> {
+        let (x, y) = (*x, *y);
+        if policy != OverflowPolicy::Wrapping {
+            match operation_type {
+                OperationType::Division | OperationType::Remainder if y == 0.0 => {
+                    return Err(CalcError::DivisionByZero)
+                }
+                OperationType::SquareRoot if x < 0.0 => return Err(CalcError::NegativeSquareRoot),
+                _ => {}
+            }
+        }
+
+        let res = match operation_type {
+            OperationType::Addition => x + y,
+            OperationType::Subtraction => x - y,
+            OperationType::Multiplication => x * y,
+            OperationType::Division => x / y,
+            OperationType::Remainder => x % y,
+            OperationType::Power => x.powf(y),
+            OperationType::SquareRoot => x.sqrt(),
+            OperationType::Assignment => x,
+            _ => return Err(unsupported::<Self>(operation_type)),
+        };
+        let overflowed = res.is_infinite() && x.is_finite() && y.is_finite();
+
+        match policy {
+            // There is nothing to wrap, the IEEE 754 result is kept as it is
+            OverflowPolicy::Wrapping => Ok(res),
+            _ if res.is_nan() => Err(CalcError::NotANumber),
+            OverflowPolicy::Saturating if overflowed => {
+                Ok(if res > 0.0 { f64::MAX } else { f64::MIN })
+            }
+            OverflowPolicy::Checked | OverflowPolicy::Widening if overflowed => {
+                Err(CalcError::Overflow)
+            }
+            _ => Ok(res),
+        }
+    }
+}
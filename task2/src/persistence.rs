@@ -0,0 +1,546 @@
+//! Saving and loading of the calculator history.
+//!
+//! The history can be written as JSON or CSV. Both formats carry a version
+//! number so that files in another layout are rejected with a clear error
+//! instead of being misread.
+//!
+//! JSON:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "number": "i64",
+//!   "operations": [
+//!     {
+//!       "sequence": 0,
+//!       "first_num": 5,
+//!       "operator": "+",
+//!       "second_num": 3,
+//!       "policy": "checked",
+//!       "rounding": "truncate",
+//!       "result": 8,
+//!       "error": null,
+//!       "timestamp": 1700000000,
+//!       "variable": null,
+//!       "macro_id": null,
+//!       "first_source": "literal",
+//!       "second_source": "literal"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! CSV:
+//!
+//! ```text
+//! # calc-history v1 i64
+//! sequence,first_num,operator,second_num,policy,result,error,timestamp,variable,macro_id,rounding,first_source,second_source
+//! 0,5,+,3,checked,8,,1700000000,,,truncate,literal,literal
+//! 1,1,/,0,checked,,division_by_zero,1700000000,,,truncate,literal,literal
+//! 2,5000,=,0,checked,5000,,1700000000,fee,,truncate,literal,literal
+//! 3,8,*,2,checked,16,,1700000000,,3,truncate,previous,literal
+//! ```
+//!
+//! Assignments to variables are stored as operations with the operator `=`
+//! and the name of the variable, so variables are restored on load. Steps of
+//! a macro replay carry the id of the replay, and every operation whether its
+//! operands were the result of the operation before (see `Operand`), so that
+//! macros can be recorded from loaded history; macros themselves are not saved.
+//!
+//! The number type of the calculator is stored next to the version and has
+//! to match the calculator loading the file. Integers that fit into an i64 or
+//! u64 are written as JSON numbers, all other numbers (huge integers, f64 and
+//! fixed point) as strings so that no precision or sign of zero is lost.
+//! Fixed point numbers are written in whole units, e.g. "1.5" for 1.5 SOL.
+//!
+//! Every field is required, except those that are empty (CSV) or null (JSON)
+//! by nature: the result of a failed operation, the error of a successful
+//! one, the variable of anything but an assignment and the macro id of an
+//! operation outside a replay.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::calculator::{
+    is_variable_name, CalcError, Calculator, Operand, Operation, OperationType, OverflowPolicy,
+    MEMORY,
+};
+use crate::numeric::{Number, Rounding};
+
+// Format version written and read by this calculator
+pub const FORMAT_VERSION: u32 = 1;
+
+const CSV_VERSION_PREFIX: &str = "# calc-history v";
+const CSV_COLUMNS: [&str; 13] = [
+    "sequence",
+    "first_num",
+    "operator",
+    "second_num",
+    "policy",
+    "result",
+    "error",
+    "timestamp",
+    "variable",
+    "macro_id",
+    "rounding",
+    "first_source",
+    "second_source",
+];
+
+#[derive(Debug)]
+pub enum HistoryFileError {
+    Io(io::Error),
+    // The file could not be understood; line is set for line based formats
+    Corrupt { line: Option<usize>, reason: String },
+    UnsupportedVersion { found: u32, supported: u32 },
+    // The file holds numbers of another type than the calculator loading it
+    NumberTypeMismatch { found: String, expected: &'static str },
+}
+
+impl HistoryFileError {
+    fn corrupt(line: Option<usize>, reason: impl Into<String>) -> Self {
+        HistoryFileError::Corrupt {
+            line,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for HistoryFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryFileError::Io(err) => write!(f, "{}", err),
+            HistoryFileError::Corrupt {
+                line: Some(line),
+                reason,
+            } => write!(f, "corrupt history file at line {}: {}", line, reason),
+            HistoryFileError::Corrupt { line: None, reason } => {
+                write!(f, "corrupt history file: {}", reason)
+            }
+            HistoryFileError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "history file version {} is not supported, expected version {}",
+                found, supported
+            ),
+            HistoryFileError::NumberTypeMismatch { found, expected } => write!(
+                f,
+                "history file holds {} numbers, the calculator uses {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HistoryFileError {}
+
+impl From<io::Error> for HistoryFileError {
+    fn from(err: io::Error) -> Self {
+        HistoryFileError::Io(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryDocument {
+    version: u32,
+    number: String,
+    operations: Vec<OperationRecord>,
+}
+
+// Numbers are kept as JSON values, see number_value and parse_value
+#[derive(Serialize, Deserialize)]
+struct OperationRecord {
+    sequence: u64,
+    first_num: Value,
+    operator: String,
+    second_num: Value,
+    policy: String,
+    rounding: String,
+    result: Option<Value>,
+    // Set instead of result for failed operations
+    error: Option<String>,
+    timestamp: u64,
+    // Only set for assignments
+    variable: Option<String>,
+    // Only set for steps of a macro replay
+    macro_id: Option<u64>,
+    first_source: String,
+    second_source: String,
+}
+
+fn error_code(err: &CalcError) -> &'static str {
+    match err {
+        CalcError::Overflow => "overflow",
+        CalcError::OutOfRange(_) => "out_of_range",
+        CalcError::DivisionByZero => "division_by_zero",
+        CalcError::NegativeExponent => "negative_exponent",
+        CalcError::NonIntegerExponent => "non_integer_exponent",
+        CalcError::NegativeSquareRoot => "negative_square_root",
+        CalcError::NotANumber => "not_a_number",
+        // Never produced by OperationType::perform, so never part of an Operation
+        CalcError::InvalidIndex(_) => "invalid_index",
+        CalcError::UnknownVariable(_) => "unknown_variable",
+        CalcError::InvalidVariableName(_) => "invalid_variable_name",
+        CalcError::UnknownMacro(_) => "unknown_macro",
+        CalcError::UnknownFormatter(_) => "unknown_formatter",
+        CalcError::EmptyMacro => "empty_macro",
+        CalcError::NotRecording => "not_recording",
+        CalcError::MacroFailed { .. } => "macro_failed",
+        CalcError::InvalidShift { .. } => "invalid_shift",
+        CalcError::UnsupportedOperation { .. } => "unsupported_operation",
+        CalcError::StackUnderflow { .. } => "stack_underflow",
+        CalcError::Parse(_) => "parse",
+    }
+}
+
+// A JSON number when the type is an integer one and the value fits into an i64 or u64,
+// its text otherwise
+//
+// The text of other types is kept as is: f64 -0 would be read back as 0 from a JSON number.
+fn number_value<N: Number>(value: &N) -> Value {
+    let text = value.to_string();
+    if N::INTEGER {
+        if let Ok(integer) = text.parse::<i64>() {
+            return Value::from(integer);
+        }
+        if let Ok(integer) = text.parse::<u64>() {
+            return Value::from(integer);
+        }
+    }
+    Value::String(text)
+}
+
+// Inverse of number_value, also accepting a number written as a string
+fn parse_value<N: Number>(value: &Value) -> Option<N> {
+    match value {
+        Value::Number(number) => N::parse_literal(&number.to_string()),
+        Value::String(text) => N::parse_literal(text),
+        _ => None,
+    }
+}
+
+// Text of a value for error messages
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl OperationRecord {
+    fn from_operation<N: Number>(op: &Operation<N>) -> Self {
+        Self {
+            sequence: op.sequence,
+            first_num: number_value(&op.first_num),
+            operator: op.operation_type.get_sign().to_string(),
+            second_num: number_value(&op.second_num),
+            policy: op.policy.name().to_string(),
+            rounding: op.rounding.name().to_string(),
+            result: op.result.as_ref().ok().map(number_value),
+            error: op.result.as_ref().err().map(|err| error_code(err).to_string()),
+            timestamp: op.timestamp,
+            variable: op.variable.clone(),
+            macro_id: op.macro_id,
+            first_source: op.first_source.name().to_string(),
+            second_source: op.second_source.name().to_string(),
+        }
+    }
+
+    // Rebuild the operation, checking that the stored outcome is the one it produces
+    //
+    // The outcome is recomputed with the recorded policy and rounding, which also restores
+    // error details that are not part of the file, such as an out of range value.
+    //
+    // Results are compared by their text, so that a NaN stored by a wrapping
+    // f64 operation matches the NaN it is recomputed as.
+    fn into_operation<N: Number>(
+        self,
+        line: Option<usize>,
+    ) -> Result<Operation<N>, HistoryFileError> {
+        let number = |value: &Value| {
+            parse_value::<N>(value).ok_or_else(|| {
+                HistoryFileError::corrupt(
+                    line,
+                    format!("invalid {} number '{}'", N::NAME, value_text(value)),
+                )
+            })
+        };
+        let first_num = number(&self.first_num)?;
+        let second_num = number(&self.second_num)?;
+        let stored = self.result.as_ref().map(number).transpose()?;
+        let operation_type = OperationType::from_sign(&self.operator).ok_or_else(|| {
+            HistoryFileError::corrupt(line, format!("unknown operator '{}'", self.operator))
+        })?;
+        let policy = OverflowPolicy::from_name(&self.policy).ok_or_else(|| {
+            HistoryFileError::corrupt(line, format!("unknown overflow policy '{}'", self.policy))
+        })?;
+        let rounding = Rounding::from_name(&self.rounding).ok_or_else(|| {
+            HistoryFileError::corrupt(line, format!("unknown rounding '{}'", self.rounding))
+        })?;
+        let source = |name: &str| {
+            Operand::from_name(name).ok_or_else(|| {
+                HistoryFileError::corrupt(line, format!("unknown operand source '{}'", name))
+            })
+        };
+        let first_source = source(&self.first_source)?;
+        let second_source = source(&self.second_source)?;
+        let is_assignment = operation_type == OperationType::Assignment;
+        match &self.variable {
+            Some(name) if !is_assignment || (!is_variable_name(name) && name != MEMORY) => {
+                return Err(HistoryFileError::corrupt(
+                    line,
+                    format!("unexpected variable '{}'", name),
+                ))
+            }
+            None if is_assignment => {
+                return Err(HistoryFileError::corrupt(line, "assignment without a variable"))
+            }
+            _ => {}
+        }
+        let result = operation_type.compute_rounded(&first_num, &second_num, policy, rounding);
+        let matches = match (&result, stored, &self.error) {
+            (Ok(value), Some(stored), None) => value.to_string() == stored.to_string(),
+            (Err(err), None, Some(code)) => error_code(err) == code,
+            _ => false,
+        };
+        if !matches {
+            return Err(HistoryFileError::corrupt(
+                line,
+                format!(
+                    "recorded outcome does not match {} {} {}",
+                    first_num, self.operator, second_num
+                ),
+            ));
+        }
+        Ok(Operation {
+            first_num,
+            second_num,
+            operation_type,
+            policy,
+            rounding,
+            result,
+            sequence: self.sequence,
+            timestamp: self.timestamp,
+            variable: self.variable,
+            // Set again when the history is replaced
+            previous_value: None,
+            macro_id: self.macro_id,
+            first_source,
+            second_source,
+        })
+    }
+}
+
+// Rebuild the history, requiring strictly increasing sequence numbers
+fn build_history<N: Number>(
+    records: impl Iterator<Item = (Option<usize>, OperationRecord)>,
+) -> Result<Vec<Operation<N>>, HistoryFileError> {
+    let mut history: Vec<Operation<N>> = Vec::new();
+    for (line, record) in records {
+        let op = record.into_operation(line)?;
+        if let Some(previous) = history.last() {
+            if op.sequence <= previous.sequence {
+                return Err(HistoryFileError::corrupt(
+                    line,
+                    format!("sequence {} does not follow {}", op.sequence, previous.sequence),
+                ));
+            }
+        }
+        history.push(op);
+    }
+    Ok(history)
+}
+
+fn check_version(found: u32) -> Result<(), HistoryFileError> {
+    if found != FORMAT_VERSION {
+        return Err(HistoryFileError::UnsupportedVersion {
+            found,
+            supported: FORMAT_VERSION,
+        });
+    }
+    Ok(())
+}
+
+fn check_number_type<N: Number>(found: &str) -> Result<(), HistoryFileError> {
+    if found != N::NAME {
+        return Err(HistoryFileError::NumberTypeMismatch {
+            found: found.to_string(),
+            expected: N::NAME,
+        });
+    }
+    Ok(())
+}
+
+// Serialize operations into the versioned JSON format
+pub fn to_json<'a, N: Number + 'a>(history: impl IntoIterator<Item = &'a Operation<N>>) -> String {
+    let document = HistoryDocument {
+        version: FORMAT_VERSION,
+        number: N::NAME.to_string(),
+        operations: history.into_iter().map(OperationRecord::from_operation).collect(),
+    };
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
+// Parse i64 operations from the versioned JSON format
+pub fn from_json(input: &str) -> Result<Vec<Operation>, HistoryFileError> {
+    from_json_as::<i64>(input)
+}
+
+// Same as from_json, for a calculator using the given number type
+pub fn from_json_as<N: Number>(input: &str) -> Result<Vec<Operation<N>>, HistoryFileError> {
+    let value: serde_json::Value = serde_json::from_str(input)
+        .map_err(|err| HistoryFileError::corrupt(Some(err.line()), err.to_string()))?;
+
+    // Look at the version first so another layout is reported as such
+    let version = value
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or_else(|| HistoryFileError::corrupt(None, "missing format version"))?;
+    check_version(u32::try_from(version).unwrap_or(u32::MAX))?;
+
+    let document: HistoryDocument = serde_json::from_value(value)
+        .map_err(|err| HistoryFileError::corrupt(None, err.to_string()))?;
+    check_number_type::<N>(&document.number)?;
+    build_history(document.operations.into_iter().map(|record| (None, record)))
+}
+
+// Serialize operations into the versioned CSV format
+pub fn to_csv<'a, N: Number + 'a>(history: impl IntoIterator<Item = &'a Operation<N>>) -> String {
+    let mut res = format!(
+        "{}{} {}\n{}\n",
+        CSV_VERSION_PREFIX,
+        FORMAT_VERSION,
+        N::NAME,
+        CSV_COLUMNS.join(",")
+    );
+    for op in history {
+        let record = OperationRecord::from_operation(op);
+        res.push_str(
+            format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                op.sequence,
+                op.first_num,
+                record.operator,
+                op.second_num,
+                op.policy,
+                op.result.as_ref().map(|value| value.to_string()).unwrap_or_default(),
+                record.error.unwrap_or_default(),
+                record.timestamp,
+                record.variable.unwrap_or_default(),
+                record.macro_id.map(|id| id.to_string()).unwrap_or_default(),
+                op.rounding,
+                op.first_source.name(),
+                op.second_source.name()
+            )
+            .as_str(),
+        );
+    }
+    res
+}
+
+// Parse i64 operations from the versioned CSV format
+pub fn from_csv(input: &str) -> Result<Vec<Operation>, HistoryFileError> {
+    from_csv_as::<i64>(input)
+}
+
+// Same as from_csv, for a calculator using the given number type
+pub fn from_csv_as<N: Number>(input: &str) -> Result<Vec<Operation<N>>, HistoryFileError> {
+    let mut lines = input.lines().enumerate().map(|(index, line)| (index + 1, line));
+
+    // The first line holds the version, followed by the number type
+    let mut first_line = lines
+        .next()
+        .and_then(|(_, line)| line.strip_prefix(CSV_VERSION_PREFIX))
+        .map(str::split_whitespace)
+        .ok_or_else(|| HistoryFileError::corrupt(Some(1), "missing format version"))?;
+    let version = first_line
+        .next()
+        .and_then(|version| version.parse::<u32>().ok())
+        .ok_or_else(|| HistoryFileError::corrupt(Some(1), "missing format version"))?;
+    check_version(version)?;
+    let number = first_line
+        .next()
+        .ok_or_else(|| HistoryFileError::corrupt(Some(1), "missing number type"))?;
+    check_number_type::<N>(number)?;
+
+    match lines.next() {
+        Some((_, header)) if header.split(',').map(str::trim).eq(CSV_COLUMNS) => {}
+        Some(_) => return Err(HistoryFileError::corrupt(Some(2), "unexpected columns")),
+        None => return Err(HistoryFileError::corrupt(Some(2), "missing header")),
+    }
+
+    let mut records = Vec::new();
+    for (line_number, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = parse_csv_record(line)
+            .ok_or_else(|| HistoryFileError::corrupt(Some(line_number), "malformed record"))?;
+        records.push((Some(line_number), record));
+    }
+    build_history(records.into_iter())
+}
+
+// A record in the order of CSV_COLUMNS, None if a field is missing or malformed
+fn parse_csv_record(line: &str) -> Option<OperationRecord> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() != CSV_COLUMNS.len() {
+        return None;
+    }
+    let field = |name: &str| {
+        CSV_COLUMNS
+            .iter()
+            .position(|column| *column == name)
+            .map(|index| fields[index])
+            .filter(|value| !value.is_empty())
+    };
+    Some(OperationRecord {
+        sequence: field("sequence")?.parse().ok()?,
+        first_num: Value::from(field("first_num")?),
+        operator: field("operator")?.to_string(),
+        second_num: Value::from(field("second_num")?),
+        policy: field("policy")?.to_string(),
+        rounding: field("rounding")?.to_string(),
+        result: field("result").map(Value::from),
+        error: field("error").map(str::to_string),
+        timestamp: field("timestamp")?.parse().ok()?,
+        variable: field("variable").map(str::to_string),
+        macro_id: field("macro_id").map(str::parse).transpose().ok()?,
+        first_source: field("first_source")?.to_string(),
+        second_source: field("second_source")?.to_string(),
+    })
+}
+
+impl<N: Number> Calculator<N> {
+    // Write the history to a JSON file
+    pub fn save_history_json(&self, path: impl AsRef<Path>) -> Result<(), HistoryFileError> {
+        fs::write(path, to_json(&self.history))?;
+        Ok(())
+    }
+
+    // Replace the history with the one stored in a JSON file
+    // The current history is left untouched if the file cannot be read
+    pub fn load_history_json(&mut self, path: impl AsRef<Path>) -> Result<(), HistoryFileError> {
+        let history = from_json_as(&fs::read_to_string(path)?)?;
+        self.replace_history(history);
+        Ok(())
+    }
+
+    // Write the history to a CSV file
+    pub fn save_history_csv(&self, path: impl AsRef<Path>) -> Result<(), HistoryFileError> {
+        fs::write(path, to_csv(&self.history))?;
+        Ok(())
+    }
+
+    // Replace the history with the one stored in a CSV file
+    // The current history is left untouched if the file cannot be read
+    pub fn load_history_csv(&mut self, path: impl AsRef<Path>) -> Result<(), HistoryFileError> {
+        let history = from_csv_as(&fs::read_to_string(path)?)?;
+        self.replace_history(history);
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+//! Shapes placed on a plane, for layout and hit-testing.
+//!
+//! The shapes of `crate::shapes` only know their dimensions. `Positioned`
+//! gives one a `Placement`: the point it sits at and a rotation around that
+//! point, in radians counterclockwise. Where that point lies on the shape:
+//!
+//! - rectangles, regular polygons, circles and ellipses: their center
+//! - triangles: their centroid, with side c lying along the x axis before rotation
+//! - polygons: the origin of their vertex coordinates
+//!
+//! Every `Positioned` shape implements `Placed`, which answers the geometric
+//! questions: bounding box, whether a point lies on the shape, and whether two
+//! placed shapes of any kind overlap. Boundaries count as part of the shape,
+//! so shapes that only touch intersect.
+//!
+//! Positions and points are in the unit of the shape (see `Shape::unit`).
+//! Two shapes in different units are never tested against each other:
+//! `intersects` returns `Error::MixedUnits` instead.
+
+use std::f64::consts::PI;
+
+use crate::shapes::*;
+
+// The boundary of a shape
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outline {
+    // Vertices in order, the last one connected back to the first; may be concave
+    // A degenerate shape has 1 vertex (a point) or 2 (a segment)
+    Polygon(Vec<(f64, f64)>),
+    // Rotation is in radians, from the x axis to semi_axis_x; both semi-axes are positive
+    Ellipse {
+        center: (f64, f64),
+        semi_axis_x: f64,
+        semi_axis_y: f64,
+        rotation: f64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Placement {
+    pub position: (f64, f64),
+    // Radians, counterclockwise around position
+    pub rotation: f64,
+}
+
+pub struct Positioned<S> {
+    pub shape: S,
+    pub placement: Placement,
+}
+
+// Shapes that can be positioned
+pub trait Outlined: Shape {
+    // The outline when placed at the origin without rotation
+    fn local_outline(&self) -> Outline;
+}
+
+pub trait Placed {
+    // The shape being placed, for its dimensions
+    fn shape(&self) -> &dyn Shape;
+
+    fn outline(&self) -> Outline;
+
+    // Move by dx along the x axis and dy along the y axis
+    fn translate(&mut self, dx: f64, dy: f64);
+
+    // Rotate by angle radians counterclockwise around the position
+    fn rotate(&mut self, angle: f64);
+
+    // The smallest axis-aligned box containing the shape
+    fn bounding_box(&self) -> BoundingBox {
+        match self.outline() {
+            Outline::Polygon(vertices) => vertices.iter().fold(
+                BoundingBox {
+                    min_x: f64::INFINITY,
+                    min_y: f64::INFINITY,
+                    max_x: f64::NEG_INFINITY,
+                    max_y: f64::NEG_INFINITY,
+                },
+                |bounds, &(x, y)| BoundingBox {
+                    min_x: bounds.min_x.min(x),
+                    min_y: bounds.min_y.min(y),
+                    max_x: bounds.max_x.max(x),
+                    max_y: bounds.max_y.max(y),
+                },
+            ),
+            Outline::Ellipse {
+                center,
+                semi_axis_x: a,
+                semi_axis_y: b,
+                rotation,
+            } => {
+                let (sin, cos) = rotation.sin_cos();
+                let half_width = ((a * cos).powi(2) + (b * sin).powi(2)).sqrt();
+                let half_height = ((a * sin).powi(2) + (b * cos).powi(2)).sqrt();
+                BoundingBox {
+                    min_x: center.0 - half_width,
+                    min_y: center.1 - half_height,
+                    max_x: center.0 + half_width,
+                    max_y: center.1 + half_height,
+                }
+            }
+        }
+    }
+
+    // Whether the point lies inside the shape or on its boundary
+    fn contains_point(&self, point: (f64, f64)) -> bool {
+        match self.outline() {
+            Outline::Polygon(vertices) => polygon_contains(&vertices, point),
+            Outline::Ellipse {
+                center,
+                semi_axis_x,
+                semi_axis_y,
+                rotation,
+            } => {
+                let (u, v) = rotate_point(sub(point, center), -rotation);
+                (u / semi_axis_x).powi(2) + (v / semi_axis_y).powi(2) <= 1.0
+            }
+        }
+    }
+
+    // Whether the two shapes have a point in common
+    // Return Err(Error::MixedUnits) if the shapes are in different units
+    fn intersects(&self, other: &dyn Placed) -> Result<bool, Error> {
+        let (unit, other_unit) = (self.shape().unit(), other.shape().unit());
+        if unit != other_unit {
+            return Err(Error::MixedUnits(unit, other_unit));
+        }
+        Ok(match (self.outline(), other.outline()) {
+            (Outline::Polygon(first), Outline::Polygon(second)) => {
+                polygons_intersect(&first, &second)
+            }
+            (Outline::Polygon(vertices), ellipse @ Outline::Ellipse { .. })
+            | (ellipse @ Outline::Ellipse { .. }, Outline::Polygon(vertices)) => {
+                ellipse_polygon_intersect(&ellipse, &vertices)
+            }
+            (first, second) => ellipses_intersect(&first, &second),
+        })
+    }
+}
+
+impl<S: Outlined> Positioned<S> {
+    pub fn new(shape: S, placement: Placement) -> Self {
+        Self { shape, placement }
+    }
+
+    // The shape at (x, y) without rotation
+    pub fn at(shape: S, x: f64, y: f64) -> Self {
+        Self::new(
+            shape,
+            Placement {
+                position: (x, y),
+                rotation: 0.0,
+            },
+        )
+    }
+}
+
+impl<S: Outlined> Placed for Positioned<S> {
+    fn shape(&self) -> &dyn Shape {
+        &self.shape
+    }
+
+    fn outline(&self) -> Outline {
+        let Placement { position, rotation } = self.placement;
+        match self.shape.local_outline() {
+            Outline::Polygon(vertices) => Outline::Polygon(
+                vertices
+                    .into_iter()
+                    .map(|vertex| add(rotate_point(vertex, rotation), position))
+                    .collect(),
+            ),
+            Outline::Ellipse {
+                center,
+                semi_axis_x,
+                semi_axis_y,
+                rotation: local_rotation,
+            } => Outline::Ellipse {
+                center: add(rotate_point(center, rotation), position),
+                semi_axis_x,
+                semi_axis_y,
+                rotation: local_rotation + rotation,
+            },
+        }
+    }
+
+    fn translate(&mut self, dx: f64, dy: f64) {
+        self.placement.position = add(self.placement.position, (dx, dy));
+    }
+
+    fn rotate(&mut self, angle: f64) {
+        self.placement.rotation += angle;
+    }
+}
+
+impl Outlined for Rectangle {
+    fn local_outline(&self) -> Outline {
+        let (x, y) = (self.get_width() / 2.0, self.get_height() / 2.0);
+        Outline::Polygon(vec![(-x, -y), (x, -y), (x, y), (-x, y)])
+    }
+}
+
+impl Outlined for Circle {
+    fn local_outline(&self) -> Outline {
+        ellipse_outline(self.get_radius(), self.get_radius())
+    }
+}
+
+impl Outlined for Ellipse {
+    fn local_outline(&self) -> Outline {
+        ellipse_outline(self.get_semi_axis_x(), self.get_semi_axis_y())
+    }
+}
+
+impl Outlined for Triangle {
+    // Side c from (0, 0) to (c, 0), then shifted so the centroid is at the origin
+    fn local_outline(&self) -> Outline {
+        let (a, b, c) = self.get_sides();
+        let x = if c == 0.0 {
+            0.0
+        } else {
+            (b * b + c * c - a * a) / (2.0 * c)
+        };
+        // Rounding can make x slightly longer than b for flat triangles
+        let y = (b * b - x * x).max(0.0).sqrt();
+        let centroid = ((c + x) / 3.0, y / 3.0);
+        Outline::Polygon(
+            [(0.0, 0.0), (c, 0.0), (x, y)]
+                .into_iter()
+                .map(|vertex| sub(vertex, centroid))
+                .collect(),
+        )
+    }
+}
+
+impl Outlined for RegularPolygon {
+    // Centered at the origin with the bottom side horizontal
+    fn local_outline(&self) -> Outline {
+        let n = self.get_sides() as f64;
+        let circumradius = self.get_side_length() / (2.0 * (PI / n).sin());
+        Outline::Polygon(
+            (0..self.get_sides())
+                .map(|i| {
+                    let angle = -PI / 2.0 + PI / n + 2.0 * PI * i as f64 / n;
+                    (circumradius * angle.cos(), circumradius * angle.sin())
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Outlined for Polygon {
+    fn local_outline(&self) -> Outline {
+        Outline::Polygon(self.get_vertices().to_vec())
+    }
+}
+
+// An ellipse around the origin; a segment or a point if a semi-axis is zero
+fn ellipse_outline(semi_axis_x: f64, semi_axis_y: f64) -> Outline {
+    match (semi_axis_x == 0.0, semi_axis_y == 0.0) {
+        (true, true) => Outline::Polygon(vec![(0.0, 0.0)]),
+        (true, false) => Outline::Polygon(vec![(0.0, -semi_axis_y), (0.0, semi_axis_y)]),
+        (false, true) => Outline::Polygon(vec![(-semi_axis_x, 0.0), (semi_axis_x, 0.0)]),
+        (false, false) => Outline::Ellipse {
+            center: (0.0, 0.0),
+            semi_axis_x,
+            semi_axis_y,
+            rotation: 0.0,
+        },
+    }
+}
+
+fn add(p: (f64, f64), q: (f64, f64)) -> (f64, f64) {
+    (p.0 + q.0, p.1 + q.1)
+}
+
+fn sub(p: (f64, f64), q: (f64, f64)) -> (f64, f64) {
+    (p.0 - q.0, p.1 - q.1)
+}
+
+// Rotate p by angle radians counterclockwise around the origin
+fn rotate_point(p: (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (p.0 * cos - p.1 * sin, p.0 * sin + p.1 * cos)
+}
+
+// Boundary first, then the even-odd rule for the interior
+fn polygon_contains(vertices: &[(f64, f64)], point: (f64, f64)) -> bool {
+    let on_boundary =
+        edges(vertices).any(|(p, q)| orientation(p, q, point) == 0.0 && on_segment(p, q, point));
+    if on_boundary {
+        return true;
+    }
+    let crossings = edges(vertices)
+        .filter(|(p, q)| {
+            (p.1 > point.1) != (q.1 > point.1)
+                && point.0 < p.0 + (point.1 - p.1) * (q.0 - p.0) / (q.1 - p.1)
+        })
+        .count();
+    crossings % 2 == 1
+}
+
+// Either the boundaries cross, or one polygon lies completely inside the other
+fn polygons_intersect(first: &[(f64, f64)], second: &[(f64, f64)]) -> bool {
+    edges(first).any(|(p, q)| edges(second).any(|(r, s)| segments_intersect(p, q, r, s)))
+        || polygon_contains(first, second[0])
+        || polygon_contains(second, first[0])
+}
+
+// Mapping the ellipse onto the unit circle keeps intersections, so this checks
+// the unit circle against the polygon mapped the same way
+fn ellipse_polygon_intersect(ellipse: &Outline, vertices: &[(f64, f64)]) -> bool {
+    let Outline::Ellipse {
+        center,
+        semi_axis_x,
+        semi_axis_y,
+        rotation,
+    } = *ellipse
+    else {
+        unreachable!("not an ellipse");
+    };
+    let mapped: Vec<(f64, f64)> = vertices
+        .iter()
+        .map(|&vertex| {
+            let (u, v) = rotate_point(sub(vertex, center), -rotation);
+            (u / semi_axis_x, v / semi_axis_y)
+        })
+        .collect();
+    polygon_contains(&mapped, (0.0, 0.0))
+        || edges(&mapped).any(|(p, q)| distance_to_origin(p, q) <= 1.0)
+}
+
+// Distance from the origin to the segment p-q
+fn distance_to_origin(p: (f64, f64), q: (f64, f64)) -> f64 {
+    let d = sub(q, p);
+    let length_squared = d.0 * d.0 + d.1 * d.1;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (-(p.0 * d.0 + p.1 * d.1) / length_squared).clamp(0.0, 1.0)
+    };
+    (p.0 + t * d.0).hypot(p.1 + t * d.1)
+}
+
+// Two convex shapes are disjoint exactly when a line separates them. With the
+// support function h of each ellipse, a direction d gives a separating line when
+// h_first(d) + h_second(-d) < 0, so the ellipses intersect when that sum is never
+// negative. The sum is smooth in the angle of d: its minimum is found by sampling
+// and then narrowed down by golden-section search.
+fn ellipses_intersect(first: &Outline, second: &Outline) -> bool {
+    let sum = |angle: f64| {
+        let d = (angle.cos(), angle.sin());
+        let d_reversed = (-d.0, -d.1);
+        support(first, d) + support(second, d_reversed)
+    };
+    const SAMPLES: usize = 360;
+    let step = 2.0 * PI / SAMPLES as f64;
+    let best = (0..SAMPLES)
+        .map(|i| i as f64 * step)
+        .min_by(|x, y| sum(*x).total_cmp(&sum(*y)))
+        .unwrap();
+
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (best - step, best + step);
+    for _ in 0..60 {
+        let x = high - ratio * (high - low);
+        let y = low + ratio * (high - low);
+        if sum(x) < sum(y) {
+            high = y;
+        } else {
+            low = x;
+        }
+    }
+    sum(best).min(sum((low + high) / 2.0)) >= 0.0
+}
+
+// Largest projection of the ellipse onto the direction d
+fn support(ellipse: &Outline, d: (f64, f64)) -> f64 {
+    let Outline::Ellipse {
+        center,
+        semi_axis_x,
+        semi_axis_y,
+        rotation,
+    } = *ellipse
+    else {
+        unreachable!("not an ellipse");
+    };
+    let (u, v) = rotate_point(d, -rotation);
+    center.0 * d.0 + center.1 * d.1 + (semi_axis_x * u).hypot(semi_axis_y * v)
+}
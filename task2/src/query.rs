@@ -0,0 +1,193 @@
+//! Structured read access to the calculator history.
+//!
+//! `Calculator::query` starts a `HistoryQuery` matching every operation in
+//! history. Each filter narrows it down further, and the matching operations
+//! are returned as they are, or summarized by `stats`:
+//!
+//! ```text
+//! let stats = calculator
+//!     .query()
+//!     .operation_type(OperationType::Division)
+//!     .sequences(10..20)
+//!     .result(|value| *value > 100)
+//!     .stats();
+//! ```
+//!
+//! Failed operations only match filters on their operands, never a filter on
+//! the result.
+
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+
+use crate::calculator::{
+    CalcError, Calculator, HistoryView, Operation, OperationType, OverflowPolicy,
+};
+use crate::numeric::Number;
+
+type Filter<'a, N> = Box<dyn Fn(&Operation<N>) -> bool + 'a>;
+
+pub struct HistoryQuery<'a, N = i64> {
+    calculator: &'a Calculator<N>,
+    filters: Vec<Filter<'a, N>>,
+}
+
+// An operation performed more than once with the same operands
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repetition<N = i64> {
+    pub operation_type: OperationType,
+    pub first_num: N,
+    pub second_num: N,
+    // Number of times it was performed, counting the first one
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryStats<N = i64> {
+    // Matching operations, failed ones included
+    pub count: usize,
+    pub failed: usize,
+    // Matching operations per operator; operators that were not used are left out
+    pub per_operator: BTreeMap<OperationType, usize>,
+    // Sum of the successful results, computed with the checked policy
+    // Ok(0) when there are none, Err if the sum does not fit or is not a number
+    pub sum: Result<N, CalcError>,
+    // Smallest and largest successful result, skipping results that cannot be compared (NaN)
+    pub min: Option<N>,
+    pub max: Option<N>,
+    // The operation performed most often with the same operands, the earliest one on a tie
+    // None unless some operation was performed at least twice
+    pub most_repeated: Option<Repetition<N>>,
+}
+
+impl<'a, N: Number> HistoryQuery<'a, N> {
+    fn filter(mut self, filter: impl Fn(&Operation<N>) -> bool + 'a) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn operation_type(self, operation_type: OperationType) -> Self {
+        self.filter(move |op| op.operation_type == operation_type)
+    }
+
+    pub fn view(self, view: HistoryView) -> Self {
+        self.filter(move |op| view.includes(op))
+    }
+
+    // Operations whose sequence number (the index shown by show_history) is in range
+    pub fn sequences(self, range: impl RangeBounds<u64> + 'a) -> Self {
+        self.filter(move |op| range.contains(&op.sequence))
+    }
+
+    // Operations whose timestamp (seconds since the Unix epoch) is in range
+    pub fn timestamps(self, range: impl RangeBounds<u64> + 'a) -> Self {
+        self.filter(move |op| range.contains(&op.timestamp))
+    }
+
+    // Operations whose operands satisfy predicate(first_num, second_num)
+    pub fn operands(self, predicate: impl Fn(&N, &N) -> bool + 'a) -> Self {
+        self.filter(move |op| predicate(&op.first_num, &op.second_num))
+    }
+
+    // Successful operations whose result satisfies predicate
+    pub fn result(self, predicate: impl Fn(&N) -> bool + 'a) -> Self {
+        self.filter(move |op| op.result.as_ref().is_ok_and(&predicate))
+    }
+
+    // Operations matching every filter, oldest first
+    pub fn operations(&self) -> Vec<&'a Operation<N>> {
+        self.calculator
+            .history
+            .iter()
+            .filter(|op| self.filters.iter().all(|filter| filter(op)))
+            .collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.operations().len()
+    }
+
+    pub fn stats(&self) -> HistoryStats<N> {
+        let operations = self.operations();
+        let mut per_operator = BTreeMap::new();
+        for op in &operations {
+            *per_operator.entry(op.operation_type.clone()).or_insert(0) += 1;
+        }
+
+        let results: Vec<&N> = operations
+            .iter()
+            .filter_map(|op| op.result.as_ref().ok())
+            .collect();
+        let sum = results.iter().try_fold(N::zero(), |sum, value| {
+            OperationType::Addition.compute(&sum, value, OverflowPolicy::Checked)
+        });
+        // A value that cannot be compared to itself (NaN) cannot be ordered
+        let comparable = || {
+            results
+                .iter()
+                .copied()
+                .filter(|value| value.partial_cmp(value).is_some())
+        };
+        let min = comparable().fold(None, |min: Option<&N>, value| match min {
+            Some(min) if min <= value => Some(min),
+            _ => Some(value),
+        });
+        let max = comparable().fold(None, |max: Option<&N>, value| match max {
+            Some(max) if max >= value => Some(max),
+            _ => Some(value),
+        });
+
+        HistoryStats {
+            count: operations.len(),
+            failed: operations.len() - results.len(),
+            per_operator,
+            sum,
+            min: min.cloned(),
+            max: max.cloned(),
+            most_repeated: most_repeated(&operations),
+        }
+    }
+}
+
+// Numbers are only PartialEq, so operations are grouped by comparing them one by one
+fn most_repeated<N: Number>(operations: &[&Operation<N>]) -> Option<Repetition<N>> {
+    let mut groups: Vec<Repetition<N>> = Vec::new();
+    for op in operations {
+        let group = groups.iter_mut().find(|group| {
+            group.operation_type == op.operation_type
+                && group.first_num == op.first_num
+                && group.second_num == op.second_num
+        });
+        match group {
+            Some(group) => group.count += 1,
+            None => groups.push(Repetition {
+                operation_type: op.operation_type.clone(),
+                first_num: op.first_num.clone(),
+                second_num: op.second_num.clone(),
+                count: 1,
+            }),
+        }
+    }
+    // Keep the earliest group on a tie
+    groups.into_iter().filter(|group| group.count > 1).fold(
+        None,
+        |best: Option<Repetition<N>>, group| match best {
+            Some(best) if best.count >= group.count => Some(best),
+            _ => Some(group),
+        },
+    )
+}
+
+impl<N: Number> Calculator<N> {
+    // Start a query matching every operation in history
+    pub fn query(&self) -> HistoryQuery<'_, N> {
+        HistoryQuery {
+            calculator: self,
+            filters: Vec::new(),
+        }
+    }
+
+    // Statistics of the whole history, same as query().stats()
+    pub fn stats(&self) -> HistoryStats<N> {
+        self.query().stats()
+    }
+}
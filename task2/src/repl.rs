@@ -0,0 +1,276 @@
+//! Line based front end for the calculator.
+//!
+//! Started with `cargo run -- calc [--sol] [script]`. Without a script the
+//! commands are read from stdin, so a session can be typed interactively or
+//! piped in. A single `Calculator` lives for the whole session; it computes
+//! with i64 numbers, or with SOL amounts (see `crate::fixed`) given `--sol`.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+
+use crate::calculator::{CalcError, Calculator, OverflowPolicy};
+use crate::macros::Macro;
+use crate::numeric::{Number, Rounding};
+
+const HELP: &str = "\
+commands:
+  <expression>   evaluate an expression, e.g. (-4 + 9) * 3 - 2, ans is the last result
+                 bitwise: & | xor ~ << >> rotl rotr, literals such as 0xff and 0b101
+  let x = <expr> store the result of an expression in the variable x, which cannot be
+                 named after a command
+  M+ / M- [expr] add to or subtract from memory (ans by default), MR recalls, MC clears
+  vars           show the variables and the memory register
+  rpn <tokens>   evaluate Reverse Polish Notation on the stack, e.g. rpn 5 3 + 2 *
+                 dup, swap and drop manipulate the stack
+  stack          show the RPN stack, bottom first
+  history [format]
+                 show the operation history (default, rpn, markdown, latex, hex, binary,
+                 and whole-units or base-units for SOL amounts)
+  repeat <index> repeat the operation at the given history index
+  undo           remove the last operation from history
+  redo           restore the last undone operation
+  clear          clear the history
+  record <name>  start recording a macro from the next operation on
+  stop           stop recording and save the macro
+  macro <name> <from> <to>
+                 save the history entries from..to as a macro
+  run <name> <value>
+                 replay a macro with value as its first operand
+  policy [name]  show or set the overflow policy (checked, saturating, wrapping, widening)
+  rounding [name]
+                 show or set the rounding of SOL amounts (truncate, floor, ceil, half-up,
+                 half-even)
+  save <file>    save the history (CSV for *.csv, JSON otherwise)
+  load <file>    replace the history with the one saved in a file
+  help           show this help
+  quit           leave the calculator
+";
+
+// Operations kept by a session before the oldest ones are evicted
+const HISTORY_LIMIT: usize = 1000;
+
+// Words taking the place of an expression at the start of a line
+// A variable with one of these names could be assigned but never read back
+const COMMANDS: [&str; 19] = [
+    "quit", "exit", "help", "history", "vars", "rpn", "stack", "clear", "undo", "redo",
+    "policy", "rounding", "save", "load", "record", "stop", "macro", "run", "repeat",
+];
+
+// Outcome of a single input line
+enum Flow {
+    Continue,
+    Quit,
+}
+
+// Run the calculator on the script at the given path, or on stdin when there is none
+pub fn start<N: Number>(script: Option<&str>) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match script {
+        Some(path) => {
+            let file = BufReader::new(File::open(path)?);
+            run_as::<N, _, _>(file, &mut stdout, false)
+        }
+        None => {
+            let stdin = io::stdin();
+            let interactive = stdin.is_terminal();
+            run_as::<N, _, _>(stdin.lock(), &mut stdout, interactive)
+        }
+    }
+}
+
+// Read commands line by line from input and write the responses to output
+//
+// A prompt is only written when interactive is set, so that piped input
+// produces output which is easy to compare in tests.
+pub fn run<R: BufRead, W: Write>(input: R, output: &mut W, interactive: bool) -> io::Result<()> {
+    run_as::<i64, R, W>(input, output, interactive)
+}
+
+// Same as run, with a calculator using the given number type
+pub fn run_as<N: Number, R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    interactive: bool,
+) -> io::Result<()> {
+    let mut calculator = Calculator::<N>::default();
+    calculator.set_max_history(Some(HISTORY_LIMIT));
+    let mut lines = input.lines();
+
+    loop {
+        if interactive {
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(()),
+        };
+        if let Flow::Quit = execute(&mut calculator, line.trim(), output)? {
+            return Ok(());
+        }
+    }
+}
+
+fn execute<N: Number, W: Write>(
+    calculator: &mut Calculator<N>,
+    line: &str,
+    output: &mut W,
+) -> io::Result<Flow> {
+    let mut words = line.split_whitespace();
+    let command = match words.next() {
+        // Blank lines and comments are allowed in scripts
+        None => return Ok(Flow::Continue),
+        Some(word) if word.starts_with('#') => return Ok(Flow::Continue),
+        Some(word) => word,
+    };
+
+    match command {
+        "quit" | "exit" => return Ok(Flow::Quit),
+        "help" => write!(output, "{}", HELP)?,
+        "history" => match (words.next(), words.next()) {
+            (None, _) => write!(output, "{}", calculator.show_history())?,
+            (Some(name), None) => match calculator.show_history_as(name) {
+                Ok(history) => write!(output, "{}", history)?,
+                Err(err) => writeln!(output, "error: {}", err)?,
+            },
+            _ => writeln!(output, "error: usage: history [format]")?,
+        },
+        "vars" => {
+            for (name, value) in calculator.variables() {
+                writeln!(output, "{} = {}", name, value)?;
+            }
+            writeln!(output, "memory = {}", calculator.memory_recall())?;
+        }
+        "rpn" => match calculator.evaluate_rpn(&line[command.len()..]) {
+            Ok(Some(top)) => writeln!(output, "{}", top)?,
+            Ok(None) => writeln!(output, "stack is empty")?,
+            Err(err) => writeln!(output, "error: {}", err)?,
+        },
+        "stack" => match calculator.stack() {
+            [] => writeln!(output, "stack is empty")?,
+            values => {
+                let values: Vec<String> = values.iter().map(N::to_string).collect();
+                writeln!(output, "{}", values.join(" "))?
+            }
+        },
+        "clear" => {
+            calculator.clear_history();
+            writeln!(output, "history cleared")?;
+        }
+        "undo" => match calculator.undo() {
+            Some(_) => writeln!(output, "undone")?,
+            None => writeln!(output, "error: nothing to undo")?,
+        },
+        "redo" => match calculator.redo() {
+            Some(_) => writeln!(output, "redone")?,
+            None => writeln!(output, "error: nothing to redo")?,
+        },
+        "policy" => match (words.next(), words.next()) {
+            (None, _) => writeln!(output, "{}", calculator.overflow_policy())?,
+            (Some(name), None) => match OverflowPolicy::from_name(name) {
+                Some(policy) => {
+                    calculator.set_overflow_policy(policy);
+                    writeln!(output, "overflow policy: {}", policy)?;
+                }
+                None => writeln!(output, "error: unknown overflow policy '{}'", name)?,
+            },
+            _ => writeln!(output, "error: usage: policy [name]")?,
+        },
+        "rounding" => match (words.next(), words.next()) {
+            (None, _) => writeln!(output, "{}", calculator.rounding())?,
+            (Some(name), None) => match Rounding::from_name(name) {
+                Some(rounding) => {
+                    calculator.set_rounding(rounding);
+                    writeln!(output, "rounding: {}", rounding)?;
+                }
+                None => writeln!(output, "error: unknown rounding '{}'", name)?,
+            },
+            _ => writeln!(output, "error: usage: rounding [name]")?,
+        },
+        "save" | "load" => match (words.next(), words.next()) {
+            (Some(path), None) => {
+                let csv = path.ends_with(".csv");
+                let res = match (command, csv) {
+                    ("save", true) => calculator.save_history_csv(path),
+                    ("save", false) => calculator.save_history_json(path),
+                    (_, true) => calculator.load_history_csv(path),
+                    (_, false) => calculator.load_history_json(path),
+                };
+                match res {
+                    Ok(()) if command == "save" => writeln!(output, "history saved")?,
+                    Ok(()) => writeln!(output, "history loaded")?,
+                    Err(err) => writeln!(output, "error: {}", err)?,
+                }
+            }
+            _ => writeln!(output, "error: usage: {} <file>", command)?,
+        },
+        "record" => match (words.next(), words.next()) {
+            (Some(name), None) => {
+                calculator.start_recording(name);
+                writeln!(output, "recording macro '{}'", name)?;
+            }
+            _ => writeln!(output, "error: usage: record <name>")?,
+        },
+        "stop" => report_macro(calculator.stop_recording(), output)?,
+        "macro" => {
+            let name = words.next();
+            let from = words.next().map(str::parse::<u64>);
+            let to = words.next().map(str::parse::<u64>);
+            match (name, from, to, words.next()) {
+                (Some(name), Some(Ok(from)), Some(Ok(to)), None) => {
+                    report_macro(calculator.record_macro(name, from, to), output)?
+                }
+                _ => writeln!(output, "error: usage: macro <name> <from> <to>")?,
+            }
+        }
+        "run" => match (words.next(), words.next().map(N::parse_literal), words.next()) {
+            (Some(name), Some(Some(value)), None) => match calculator.run_macro(name, value) {
+                Ok(result) => writeln!(output, "{}", result)?,
+                Err(err) => writeln!(output, "error: {}", err)?,
+            },
+            _ => writeln!(output, "error: usage: run <name> <value>")?,
+        },
+        "repeat" => match (words.next().map(str::parse::<u64>), words.next()) {
+            (Some(Ok(index)), None) => match calculator.repeat(index) {
+                Ok(result) => writeln!(output, "{}", result)?,
+                Err(err) => writeln!(output, "error: {}", err)?,
+            },
+            _ => writeln!(output, "error: usage: repeat <index>")?,
+        },
+        "let" => match words.next().and_then(|name| name.split('=').next()) {
+            Some(name) if COMMANDS.contains(&name) => {
+                let err = CalcError::InvalidVariableName(name.to_string());
+                writeln!(output, "error: {}, it is a command", err)?
+            }
+            _ => evaluate(calculator, line, output)?,
+        },
+        _ => evaluate(calculator, line, output)?,
+    }
+    Ok(Flow::Continue)
+}
+
+fn evaluate<N: Number, W: Write>(
+    calculator: &mut Calculator<N>,
+    line: &str,
+    output: &mut W,
+) -> io::Result<()> {
+    match calculator.evaluate(line) {
+        Ok(result) => writeln!(output, "{}", result),
+        Err(err) => writeln!(output, "error: {}", err),
+    }
+}
+
+fn report_macro<N: Number, W: Write>(
+    saved: Result<&Macro<N>, CalcError>,
+    output: &mut W,
+) -> io::Result<()> {
+    match saved {
+        Ok(saved) => writeln!(
+            output,
+            "macro '{}' saved with {} steps",
+            saved.name,
+            saved.steps.len()
+        ),
+        Err(err) => writeln!(output, "error: {}", err),
+    }
+}
@@ -0,0 +1,149 @@
+//! Reverse Polish Notation input on top of the calculator.
+//!
+//! Operands are pushed onto a stack and every operator takes its operands
+//! from the top of it: `5 3 + 2 *` computes `(5 + 3) * 2`. The stack lives in
+//! the `Calculator`, so it persists across calls to `evaluate_rpn`.
+//!
+//! Applied operators are recorded in history like any other operation, so
+//! `repeat`, `undo` and `show_history` work the same. Undo only changes the
+//! history, never the stack.
+//!
+//! Tokens are separated by whitespace:
+//!
+//! - number literals, and variables (including `ans` and `MR`), which push
+//!   their value
+//! - `+ - * / % ^` take two operands, the top of the stack being the second
+//! - `sqrt` takes one
+//! - `dup` copies the top of the stack, `swap` exchanges the two topmost
+//!   values and `drop` removes the top one
+//!
+//! An operator that fails is recorded as failed and leaves its operands on
+//! the stack; the rest of the input is then skipped.
+
+use crate::calculator::{CalcError, Calculator, OperationType};
+use crate::expression::{ParseError, ParseErrorReason};
+use crate::numeric::Number;
+
+impl<N: Number> Calculator<N> {
+    // The stack, bottom first
+    pub fn stack(&self) -> &[N] {
+        &self.stack
+    }
+
+    pub fn push(&mut self, value: N) {
+        self.stack.push(value);
+    }
+
+    // Remove the top of the stack and return it
+    // Return Err(CalcError::StackUnderflow) if the stack is empty
+    pub fn pop(&mut self) -> Result<N, CalcError> {
+        self.require(1)?;
+        Ok(self.stack.pop().unwrap())
+    }
+
+    // Push a copy of the top of the stack
+    pub fn dup(&mut self) -> Result<(), CalcError> {
+        self.require(1)?;
+        self.stack.push(self.stack[self.stack.len() - 1].clone());
+        Ok(())
+    }
+
+    // Exchange the two topmost values
+    pub fn swap(&mut self) -> Result<(), CalcError> {
+        self.require(2)?;
+        let len = self.stack.len();
+        self.stack.swap(len - 1, len - 2);
+        Ok(())
+    }
+
+    pub fn clear_stack(&mut self) {
+        self.stack.clear();
+    }
+
+    // Apply the operation to the top of the stack, replacing its operands with the result
+    // The operation is recorded in history, whether it succeeds or not
+    // Return Err(CalcError::StackUnderflow) without recording anything if operands are missing
+    pub fn apply_rpn(&mut self, operation_type: OperationType) -> Result<N, CalcError> {
+        let arity = if operation_type.is_unary() { 1 } else { 2 };
+        self.require(arity)?;
+        let mut operands = self.stack.split_off(self.stack.len() - arity);
+        let y = if arity == 2 {
+            operands.pop().unwrap()
+        } else {
+            N::zero()
+        };
+        let x = operands.pop().unwrap();
+        match self.apply(operation_type, x.clone(), y.clone()) {
+            Ok(result) => {
+                self.stack.push(result.clone());
+                Ok(result)
+            }
+            Err(err) => {
+                self.stack.push(x);
+                if arity == 2 {
+                    self.stack.push(y);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    // Process whitespace separated RPN tokens and return the top of the stack afterwards
+    // Return the first error; the tokens before it keep their effect
+    pub fn evaluate_rpn(&mut self, input: &str) -> Result<Option<N>, CalcError> {
+        for (position, token) in tokens(input) {
+            match token {
+                "dup" => self.dup()?,
+                "swap" => self.swap()?,
+                "drop" => {
+                    self.pop()?;
+                }
+                _ => match OperationType::from_sign(token) {
+                    // Assignments need a name, which RPN has no place for
+                    Some(OperationType::Assignment) | None => {
+                        let value = self.rpn_operand(token, position)?;
+                        self.stack.push(value);
+                    }
+                    Some(operation_type) => {
+                        self.apply_rpn(operation_type)?;
+                    }
+                },
+            }
+        }
+        Ok(self.stack.last().cloned())
+    }
+
+    // A literal or the value of a variable
+    fn rpn_operand(&self, token: &str, position: usize) -> Result<N, CalcError> {
+        if let Some(value) = N::parse_literal(token) {
+            return Ok(value);
+        }
+        let starts_like_name = token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+        if starts_like_name && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return self.lookup(token);
+        }
+        let reason = if token.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            ParseErrorReason::InvalidNumber(token.to_string())
+        } else {
+            ParseErrorReason::UnexpectedToken(token.to_string())
+        };
+        Err(CalcError::Parse(ParseError::new(position, reason)))
+    }
+
+    fn require(&self, needed: usize) -> Result<(), CalcError> {
+        if self.stack.len() < needed {
+            return Err(CalcError::StackUnderflow {
+                needed,
+                found: self.stack.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+// Whitespace separated tokens with their byte offset
+fn tokens(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .split_whitespace()
+        .map(move |token| (token.as_ptr() as usize - input.as_ptr() as usize, token))
+}
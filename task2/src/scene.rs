@@ -0,0 +1,291 @@
+//! A collection of shapes of any kind.
+//!
+//! `Scene` holds boxed shapes in insertion order and answers questions about
+//! all of them at once: totals, the shapes within an area range, the largest
+//! and smallest one, and the shapes of each kind. Shapes may be in different
+//! units: the plain totals, the area range and the ordering by area are in
+//! metres, every shape being converted first. The measured totals stay in
+//! the unit of the shapes instead, and check that all of them use the same.
+//!
+//! A scene serializes as an object with a list of shapes, each tagged with its
+//! kind next to its dimensions and, for rectangles and circles, their unit:
+//!
+//! ```text
+//! {"shapes":[{"kind":"rectangle","width":7.0,"height":3.0,"unit":"m"},{"kind":"circle",...}]}
+//! ```
+//!
+//! Deserializing goes through the constructors of the shapes, so a file with a
+//! negative width is rejected with the same error as `Rectangle::new`. The
+//! other way round, `ToRecord` turns a shape into its record; a scene holds any
+//! shape that implements both, so `Shape` itself knows nothing of serde.
+
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::shapes::*;
+use crate::units::{Area, Length, LengthUnit};
+
+#[derive(Debug, Default)]
+pub struct Scene {
+    shapes: Vec<Box<dyn SceneShape>>,
+}
+
+// The record a shape is serialized as
+pub trait ToRecord {
+    fn to_record(&self) -> ShapeRecord;
+}
+
+// A shape a scene can hold and serialize
+pub trait SceneShape: Shape + ToRecord {}
+
+impl<T: Shape + ToRecord> SceneShape for T {}
+
+// The serialized form of a shape, tagged with the name of its kind
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShapeRecord {
+    Rectangle {
+        width: f64,
+        height: f64,
+        unit: LengthUnit,
+    },
+    Circle {
+        radius: f64,
+        unit: LengthUnit,
+    },
+    Triangle {
+        a: f64,
+        b: f64,
+        c: f64,
+    },
+    Ellipse {
+        semi_axis_x: f64,
+        semi_axis_y: f64,
+    },
+    RegularPolygon {
+        sides: usize,
+        side_length: f64,
+    },
+    Polygon {
+        vertices: Vec<(f64, f64)>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneRecord {
+    shapes: Vec<ShapeRecord>,
+}
+
+impl ShapeRecord {
+    // Build the shape with its constructor, returning the constructor's error
+    pub fn build(self) -> Result<Box<dyn SceneShape>, Error> {
+        Ok(match self {
+            ShapeRecord::Rectangle {
+                width,
+                height,
+                unit,
+            } => Box::new(Rectangle::from_lengths(
+                Length::new(width, unit),
+                Length::new(height, unit),
+            )?),
+            ShapeRecord::Circle { radius, unit } => {
+                Box::new(Circle::from_length(Length::new(radius, unit))?)
+            }
+            ShapeRecord::Triangle { a, b, c } => Box::new(Triangle::new(a, b, c)?),
+            ShapeRecord::Ellipse {
+                semi_axis_x,
+                semi_axis_y,
+            } => Box::new(Ellipse::new(semi_axis_x, semi_axis_y)?),
+            ShapeRecord::RegularPolygon { sides, side_length } => {
+                Box::new(RegularPolygon::new(sides, side_length)?)
+            }
+            ShapeRecord::Polygon { vertices } => Box::new(Polygon::new(vertices)?),
+        })
+    }
+}
+
+impl ToRecord for Rectangle {
+    fn to_record(&self) -> ShapeRecord {
+        ShapeRecord::Rectangle {
+            width: self.get_width(),
+            height: self.get_height(),
+            unit: self.get_unit(),
+        }
+    }
+}
+
+impl ToRecord for Circle {
+    fn to_record(&self) -> ShapeRecord {
+        ShapeRecord::Circle {
+            radius: self.get_radius(),
+            unit: self.get_unit(),
+        }
+    }
+}
+
+impl ToRecord for Triangle {
+    fn to_record(&self) -> ShapeRecord {
+        let (a, b, c) = self.get_sides();
+        ShapeRecord::Triangle { a, b, c }
+    }
+}
+
+impl ToRecord for Ellipse {
+    fn to_record(&self) -> ShapeRecord {
+        ShapeRecord::Ellipse {
+            semi_axis_x: self.get_semi_axis_x(),
+            semi_axis_y: self.get_semi_axis_y(),
+        }
+    }
+}
+
+impl ToRecord for RegularPolygon {
+    fn to_record(&self) -> ShapeRecord {
+        ShapeRecord::RegularPolygon {
+            sides: self.get_sides(),
+            side_length: self.get_side_length(),
+        }
+    }
+}
+
+impl ToRecord for Polygon {
+    fn to_record(&self) -> ShapeRecord {
+        ShapeRecord::Polygon {
+            vertices: self.get_vertices().to_vec(),
+        }
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, shape: Box<dyn SceneShape>) {
+        self.shapes.push(shape);
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn SceneShape>] {
+        &self.shapes
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    // In square metres
+    pub fn total_area(&self) -> f64 {
+        self.iter().map(area_in_metres).sum()
+    }
+
+    // In metres
+    pub fn total_perimeter(&self) -> f64 {
+        self.iter()
+            .map(|shape| shape.measured_perimeter().to(LengthUnit::Metre).value())
+            .sum()
+    }
+
+    // Same as total_area, in the unit all shapes are in
+    // Return Err(Error::MixedUnits) if two shapes have different units
+    pub fn total_measured_area(&self) -> Result<Area, Error> {
+        self.iter()
+            .map(|shape| shape.measured_area())
+            .try_fold(Area::new(0.0, self.unit()), |sum, area| {
+                sum.checked_add(area)
+            })
+    }
+
+    // Same as total_perimeter, in the unit all shapes are in
+    // Return Err(Error::MixedUnits) if two shapes have different units
+    pub fn total_measured_perimeter(&self) -> Result<Length, Error> {
+        self.iter()
+            .map(|shape| shape.measured_perimeter())
+            .try_fold(Length::new(0.0, self.unit()), |sum, length| {
+                sum.checked_add(length)
+            })
+    }
+
+    // Smallest area first; shapes with the same area keep their order
+    pub fn sort_by_area(&mut self) {
+        self.shapes.sort_by(|first, second| {
+            area_in_metres(first.as_ref()).total_cmp(&area_in_metres(second.as_ref()))
+        });
+    }
+
+    // Shapes whose area in square metres is in range, in scene order
+    pub fn filter_by_area(&self, range: impl RangeBounds<f64>) -> Vec<&dyn SceneShape> {
+        self.iter()
+            .filter(|shape| range.contains(&area_in_metres(*shape)))
+            .collect()
+    }
+
+    // The shape with the largest area, the first one on a tie
+    pub fn largest(&self) -> Option<&dyn SceneShape> {
+        self.iter().fold(None, |largest, shape| match largest {
+            Some(largest) if area_in_metres(largest) >= area_in_metres(shape) => Some(largest),
+            _ => Some(shape),
+        })
+    }
+
+    // The shape with the smallest area, the first one on a tie
+    pub fn smallest(&self) -> Option<&dyn SceneShape> {
+        self.iter().fold(None, |smallest, shape| match smallest {
+            Some(smallest) if area_in_metres(smallest) <= area_in_metres(shape) => Some(smallest),
+            _ => Some(shape),
+        })
+    }
+
+    // Shapes of each kind in scene order; kinds without shapes are left out
+    pub fn group_by_kind(&self) -> BTreeMap<ShapeKind, Vec<&dyn SceneShape>> {
+        let mut groups: BTreeMap<ShapeKind, Vec<&dyn SceneShape>> = BTreeMap::new();
+        for shape in self.iter() {
+            groups.entry(shape.kind()).or_default().push(shape);
+        }
+        groups
+    }
+
+    // Unit of the first shape, metres for an empty scene
+    fn unit(&self) -> LengthUnit {
+        self.iter()
+            .next()
+            .map_or(LengthUnit::Metre, |shape| shape.unit())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &dyn SceneShape> {
+        self.shapes.iter().map(|shape| shape.as_ref())
+    }
+}
+
+// Areas in different units are compared once converted to square metres
+fn area_in_metres(shape: &dyn SceneShape) -> f64 {
+    shape.measured_area().to(LengthUnit::Metre).value()
+}
+
+impl Serialize for Scene {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SceneRecord {
+            shapes: self.shapes.iter().map(|shape| shape.to_record()).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Scene {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let record = SceneRecord::deserialize(deserializer)?;
+        let shapes = record
+            .shapes
+            .into_iter()
+            .map(ShapeRecord::build)
+            .collect::<Result<_, _>>()
+            .map_err(D::Error::custom)?;
+        Ok(Scene { shapes })
+    }
+}
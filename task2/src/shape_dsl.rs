@@ -0,0 +1,233 @@
+//! A small text format for shapes, and the command computing their metrics.
+//!
+//! Every line describes one shape by its kind followed by `key=value` pairs,
+//! in any order:
+//!
+//! ```text
+//! rect w=7 h=3
+//! circle r=17 unit=cm
+//! ```
+//!
+//! - `rect` (or `rectangle`) takes `w` and `h` (or `width` and `height`)
+//! - `circle` takes `r` (or `radius`)
+//! - `unit` is optional for both: mm, cm, m, in or ft, metres by default
+//!
+//! Blank lines and lines starting with `#` are skipped. The values go through
+//! the constructors of the shapes, so a negative width is reported with the
+//! same `shapes::Error` as `Rectangle::new`.
+//!
+//! `cargo run -- shapes [file]` reads such a file, or stdin when there is none,
+//! and prints the area and perimeter of every shape followed by the totals.
+//! Invalid lines are reported with their line number and do not stop the
+//! others from being computed.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+
+use crate::scene::{Scene, SceneShape};
+use crate::shapes::{self, Circle, Rectangle, ShapeKind};
+use crate::units::{Length, LengthUnit};
+
+#[derive(Debug, PartialEq)]
+pub enum ShapeLineError {
+    UnknownShape(String),
+    // A word that is not of the form key=value
+    Malformed(String),
+    // A key the shape does not take
+    UnknownKey(String),
+    DuplicateKey(String),
+    MissingKey(&'static str),
+    InvalidNumber(String),
+    UnknownUnit(String),
+    // Rejected by the constructor of the shape
+    Shape(shapes::Error),
+}
+
+// A shape with the number of its line, or the reason the line is invalid
+pub type ParsedLine = Result<(usize, Box<dyn SceneShape>), LineError>;
+
+// An invalid line, numbered from 1
+#[derive(Debug, PartialEq)]
+pub struct LineError {
+    pub line: usize,
+    pub error: ShapeLineError,
+}
+
+impl fmt::Display for ShapeLineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeLineError::UnknownShape(kind) => {
+                write!(f, "unknown shape '{}', expected rect or circle", kind)
+            }
+            ShapeLineError::Malformed(word) => write!(f, "expected key=value, found '{}'", word),
+            ShapeLineError::UnknownKey(key) => write!(f, "unknown key '{}'", key),
+            ShapeLineError::DuplicateKey(key) => write!(f, "'{}' is given twice", key),
+            ShapeLineError::MissingKey(key) => write!(f, "missing '{}'", key),
+            ShapeLineError::InvalidNumber(value) => write!(f, "invalid number '{}'", value),
+            ShapeLineError::UnknownUnit(unit) => {
+                write!(f, "unknown unit '{}', expected mm, cm, m, in or ft", unit)
+            }
+            ShapeLineError::Shape(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ShapeLineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShapeLineError::Shape(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<shapes::Error> for ShapeLineError {
+    fn from(err: shapes::Error) -> Self {
+        ShapeLineError::Shape(err)
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for LineError {}
+
+// Parse a single line
+// Return Ok(None) for blank lines and comments
+pub fn parse_line(line: &str) -> Result<Option<Box<dyn SceneShape>>, ShapeLineError> {
+    let mut words = line.split_whitespace();
+    let kind = match words.next() {
+        None => return Ok(None),
+        Some(word) if word.starts_with('#') => return Ok(None),
+        Some(word) => word,
+    };
+    // Key names per shape, with their short forms
+    let (kind, keys): (ShapeKind, &[(&'static str, &str)]) = match kind {
+        "rect" | "rectangle" => (ShapeKind::Rectangle, &[("width", "w"), ("height", "h")]),
+        "circle" => (ShapeKind::Circle, &[("radius", "r")]),
+        _ => return Err(ShapeLineError::UnknownShape(kind.to_string())),
+    };
+
+    let mut values: Vec<(&'static str, f64)> = Vec::new();
+    let mut unit = None;
+    for word in words {
+        let (key, value) = word
+            .split_once('=')
+            .ok_or_else(|| ShapeLineError::Malformed(word.to_string()))?;
+        if key == "unit" {
+            if unit.is_some() {
+                return Err(ShapeLineError::DuplicateKey(key.to_string()));
+            }
+            let parsed = LengthUnit::from_name(value)
+                .ok_or_else(|| ShapeLineError::UnknownUnit(value.to_string()))?;
+            unit = Some(parsed);
+            continue;
+        }
+        let name = keys
+            .iter()
+            .find(|(name, short)| key == *name || key == *short)
+            .map(|(name, _)| *name)
+            .ok_or_else(|| ShapeLineError::UnknownKey(key.to_string()))?;
+        if values.iter().any(|(given, _)| *given == name) {
+            return Err(ShapeLineError::DuplicateKey(name.to_string()));
+        }
+        let number = value
+            .parse::<f64>()
+            .map_err(|_| ShapeLineError::InvalidNumber(value.to_string()))?;
+        values.push((name, number));
+    }
+
+    let unit = unit.unwrap_or_default();
+    let length = |name: &'static str| {
+        values
+            .iter()
+            .find(|(given, _)| *given == name)
+            .map(|(_, value)| Length::new(*value, unit))
+            .ok_or(ShapeLineError::MissingKey(name))
+    };
+    let shape: Box<dyn SceneShape> = match kind {
+        ShapeKind::Rectangle => Box::new(Rectangle::from_lengths(
+            length("width")?,
+            length("height")?,
+        )?),
+        _ => Box::new(Circle::from_length(length("radius")?)?),
+    };
+    Ok(Some(shape))
+}
+
+// Parse every line of input, numbering lines from 1
+// Blank lines and comments are left out
+pub fn parse(input: &str) -> Vec<ParsedLine> {
+    input
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| match parse_line(line) {
+            Ok(shape) => shape.map(|shape| Ok((index + 1, shape))),
+            Err(error) => Some(Err(LineError {
+                line: index + 1,
+                error,
+            })),
+        })
+        .collect()
+}
+
+// Report the metrics of the shapes in the file at the given path, or in stdin without one
+// Return Ok(false) if some line was invalid
+pub fn start(path: Option<&str>) -> io::Result<bool> {
+    let mut stdout = io::stdout().lock();
+    match path {
+        Some(path) => run(BufReader::new(File::open(path)?), &mut stdout),
+        None => run(io::stdin().lock(), &mut stdout),
+    }
+}
+
+// Write one line per shape or invalid line, then the totals of the valid shapes
+// Return Ok(false) if some line was invalid
+pub fn run<R: BufRead, W: Write>(mut input: R, output: &mut W) -> io::Result<bool> {
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+
+    let mut scene = Scene::new();
+    let mut valid = true;
+    for entry in parse(&text) {
+        match entry {
+            Ok((line, shape)) => {
+                writeln!(
+                    output,
+                    "line {}: {} area {} perimeter {}",
+                    line,
+                    shape.name(),
+                    shape.measured_area(),
+                    shape.measured_perimeter()
+                )?;
+                scene.push(shape);
+            }
+            Err(err) => {
+                valid = false;
+                writeln!(output, "error: {}", err)?;
+            }
+        }
+    }
+
+    match (
+        scene.total_measured_area(),
+        scene.total_measured_perimeter(),
+    ) {
+        (Ok(area), Ok(perimeter)) => writeln!(
+            output,
+            "total: {} shapes, area {} perimeter {}",
+            scene.len(),
+            area,
+            perimeter
+        )?,
+        // Shapes in different units cannot be added up
+        (Err(err), _) | (_, Err(err)) => {
+            writeln!(output, "total: {} shapes, {}", scene.len(), err)?
+        }
+    }
+    Ok(valid)
+}
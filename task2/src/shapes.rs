@@ -13,51 +13,201 @@
 ///
 ///-------------------------------------------------------------------------------
 
-pub trait Shape {
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::units::{Area, Length, LengthUnit};
+
+pub trait Shape: fmt::Debug {
     fn area(&self) -> f64;
     fn perimeter(&self) -> f64;
+    fn kind(&self) -> ShapeKind;
+
+    // The unit of the dimensions; shapes built from plain numbers are in metres
+    fn unit(&self) -> LengthUnit {
+        LengthUnit::Metre
+    }
+
+    // The area in the square of unit()
+    fn measured_area(&self) -> Area {
+        Area::new(self.area(), self.unit())
+    }
+
+    fn measured_perimeter(&self) -> Length {
+        Length::new(self.perimeter(), self.unit())
+    }
+
+    fn name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    // Order by area, like PartialOrd; shapes in different units are compared in square metres
+    fn partial_cmp_area(&self, other: &dyn Shape) -> Option<Ordering> {
+        let area = self.measured_area().to(LengthUnit::Metre);
+        area.partial_cmp(&other.measured_area().to(LengthUnit::Metre))
+    }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ShapeKind {
+    Rectangle,
+    Circle,
+    Triangle,
+    Ellipse,
+    RegularPolygon,
+    Polygon,
+}
+
+impl ShapeKind {
+    // Same as the tag of the serialized shape
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShapeKind::Rectangle => "rectangle",
+            ShapeKind::Circle => "circle",
+            ShapeKind::Triangle => "triangle",
+            ShapeKind::Ellipse => "ellipse",
+            ShapeKind::RegularPolygon => "regular_polygon",
+            ShapeKind::Polygon => "polygon",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Rectangle {
     width: f64,
     height: f64,
+    unit: LengthUnit,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Circle {
     radius: f64,
+    unit: LengthUnit,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triangle {
+    a: f64,
+    b: f64,
+    c: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ellipse {
+    semi_axis_x: f64,
+    semi_axis_y: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegularPolygon {
+    sides: usize,
+    side_length: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<(f64, f64)>,
+}
+
+// Every variant carries the rejected value
 #[derive(Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
-    InvalidWidth,
-    InvalidHeight,
-    InvalidRadius,
+    InvalidWidth(f64),
+    InvalidHeight(f64),
+    InvalidRadius(f64),
+    // The third dimension of a box, see crate::solids
+    InvalidDepth(f64),
+    // A negative or non-finite side of a triangle
+    InvalidSide(f64),
+    // Sides breaking the triangle inequality
+    InvalidTriangle(f64, f64, f64),
+    InvalidSemiAxis(f64),
+    // Fewer than 3 sides
+    InvalidSideCount(usize),
+    InvalidSideLength(f64),
+    // A vertex with a non-finite coordinate
+    InvalidVertex(f64, f64),
+    // Fewer than 3 vertices
+    TooFewVertices(usize),
+    // Edges crossing, touching or overlapping each other
+    SelfIntersecting,
+    // A scale factor that would flip or collapse the shape, or a non-finite one
+    InvalidScaleFactor(f64),
+    // Lengths in different units used together
+    MixedUnits(LengthUnit, LengthUnit),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidWidth(width) => write!(f, "invalid width {}", width),
+            Error::InvalidHeight(height) => write!(f, "invalid height {}", height),
+            Error::InvalidRadius(radius) => write!(f, "invalid radius {}", radius),
+            Error::InvalidDepth(depth) => write!(f, "invalid depth {}", depth),
+            Error::InvalidSide(side) => write!(f, "invalid side {}", side),
+            Error::InvalidTriangle(a, b, c) => {
+                write!(f, "sides {}, {} and {} do not form a triangle", a, b, c)
+            }
+            Error::InvalidSemiAxis(semi_axis) => write!(f, "invalid semi-axis {}", semi_axis),
+            Error::InvalidSideCount(sides) => {
+                write!(f, "a polygon needs at least 3 sides, got {}", sides)
+            }
+            Error::InvalidSideLength(length) => write!(f, "invalid side length {}", length),
+            Error::InvalidVertex(x, y) => write!(f, "invalid vertex ({}, {})", x, y),
+            Error::TooFewVertices(count) => {
+                write!(f, "a polygon needs at least 3 vertices, got {}", count)
+            }
+            Error::SelfIntersecting => write!(f, "polygon edges intersect"),
+            Error::InvalidScaleFactor(factor) => write!(f, "invalid scale factor {}", factor),
+            Error::MixedUnits(first, second) => {
+                write!(f, "cannot mix lengths in {} and {}", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Lengths must be finite and non-negative; error builds the error for a rejected one
+pub(crate) fn check_length(length: f64, error: fn(f64) -> Error) -> Result<(), Error> {
+    if length.is_finite() && length >= 0.0 {
+        Ok(())
+    } else {
+        Err(error(length))
+    }
 }
 
 // Implement constructor with setters and getters.
 //
-// Width and height are considered invalid if they are negative.
+// Width and height are considered invalid if they are negative, NaN or infinite.
 // All methods should return the corresponding error when invalid values are provided.
 impl Rectangle {
     pub fn new(width: f64, height: f64) -> Result<Self, Error> {
-        if width < 0.0 {
-            return Err(Error::InvalidWidth);
-        } else if height < 0.0 {
-            return Err(Error::InvalidHeight);
+        check_length(width, Error::InvalidWidth)?;
+        check_length(height, Error::InvalidHeight)?;
+        Ok(Self {
+            width,
+            height,
+            unit: LengthUnit::Metre,
+        })
+    }
+    // Return Err(Error::MixedUnits) unless width and height are in the same unit
+    pub fn from_lengths(width: Length, height: Length) -> Result<Self, Error> {
+        if width.unit() != height.unit() {
+            return Err(Error::MixedUnits(width.unit(), height.unit()));
         }
-        Ok(Self { width, height })
+        let mut rectangle = Self::new(width.value(), height.value())?;
+        rectangle.unit = width.unit();
+        Ok(rectangle)
     }
     pub fn set_width(&mut self, width: f64) -> Result<(), Error> {
-        if width < 0.0 {
-            return Err(Error::InvalidWidth);
-        }
+        check_length(width, Error::InvalidWidth)?;
         self.width = width;
         Ok(())
     }
     pub fn set_height(&mut self, height: f64) -> Result<(), Error> {
-        if height < 0.0 {
-            return Err(Error::InvalidHeight);
-        }
+        check_length(height, Error::InvalidHeight)?;
         self.height = height;
         Ok(())
     }
@@ -67,29 +217,238 @@ impl Rectangle {
     pub fn get_height(&self) -> f64 {
         self.height
     }
+    pub fn get_unit(&self) -> LengthUnit {
+        self.unit
+    }
+    pub fn width(&self) -> Length {
+        Length::new(self.width, self.unit)
+    }
+    pub fn height(&self) -> Length {
+        Length::new(self.height, self.unit)
+    }
+    // The same rectangle measured in another unit
+    // Return the error of the constructor if a dimension no longer fits in an f64
+    pub fn to_unit(&self, unit: LengthUnit) -> Result<Self, Error> {
+        Self::from_lengths(self.width().to(unit), self.height().to(unit))
+    }
 }
 
 // Implement constructor with setter and getter.
 //
-// The radius is considered invalid if it is negative.
+// The radius is considered invalid if it is negative, NaN or infinite.
 // All methods should return the corresponding error when invalid values are provided.
 impl Circle {
     pub fn new(radius: f64) -> Result<Self, Error> {
-        if radius < 0.0 {
-            return Err(Error::InvalidRadius);
-        }
-        Ok(Self { radius })
+        check_length(radius, Error::InvalidRadius)?;
+        Ok(Self {
+            radius,
+            unit: LengthUnit::Metre,
+        })
+    }
+    pub fn from_length(radius: Length) -> Result<Self, Error> {
+        let mut circle = Self::new(radius.value())?;
+        circle.unit = radius.unit();
+        Ok(circle)
     }
     pub fn set_radius(&mut self, radius: f64) -> Result<(), Error> {
-        if radius < 0.0 {
-            return Err(Error::InvalidRadius);
-        }
+        check_length(radius, Error::InvalidRadius)?;
         self.radius = radius;
         Ok(())
     }
     pub fn get_radius(&self) -> f64 {
         self.radius
     }
+    pub fn get_unit(&self) -> LengthUnit {
+        self.unit
+    }
+    pub fn radius(&self) -> Length {
+        Length::new(self.radius, self.unit)
+    }
+    // The same circle measured in another unit
+    // Return the error of the constructor if the radius no longer fits in an f64
+    pub fn to_unit(&self, unit: LengthUnit) -> Result<Self, Error> {
+        Self::from_length(self.radius().to(unit))
+    }
+}
+
+// The sides are invalid if one of them is negative or not finite, or if one is longer than
+// the other two together. Degenerate (flat) triangles are accepted.
+impl Triangle {
+    pub fn new(a: f64, b: f64, c: f64) -> Result<Self, Error> {
+        check_triangle(a, b, c)?;
+        Ok(Self { a, b, c })
+    }
+    pub fn set_sides(&mut self, a: f64, b: f64, c: f64) -> Result<(), Error> {
+        check_triangle(a, b, c)?;
+        (self.a, self.b, self.c) = (a, b, c);
+        Ok(())
+    }
+    pub fn get_sides(&self) -> (f64, f64, f64) {
+        (self.a, self.b, self.c)
+    }
+}
+
+fn check_triangle(a: f64, b: f64, c: f64) -> Result<(), Error> {
+    for side in [a, b, c] {
+        check_length(side, Error::InvalidSide)?;
+    }
+    if a > b + c || b > a + c || c > a + b {
+        return Err(Error::InvalidTriangle(a, b, c));
+    }
+    Ok(())
+}
+
+// The semi-axes are invalid if they are negative or not finite.
+impl Ellipse {
+    pub fn new(semi_axis_x: f64, semi_axis_y: f64) -> Result<Self, Error> {
+        check_length(semi_axis_x, Error::InvalidSemiAxis)?;
+        check_length(semi_axis_y, Error::InvalidSemiAxis)?;
+        Ok(Self {
+            semi_axis_x,
+            semi_axis_y,
+        })
+    }
+    pub fn set_semi_axis_x(&mut self, semi_axis_x: f64) -> Result<(), Error> {
+        check_length(semi_axis_x, Error::InvalidSemiAxis)?;
+        self.semi_axis_x = semi_axis_x;
+        Ok(())
+    }
+    pub fn set_semi_axis_y(&mut self, semi_axis_y: f64) -> Result<(), Error> {
+        check_length(semi_axis_y, Error::InvalidSemiAxis)?;
+        self.semi_axis_y = semi_axis_y;
+        Ok(())
+    }
+    pub fn get_semi_axis_x(&self) -> f64 {
+        self.semi_axis_x
+    }
+    pub fn get_semi_axis_y(&self) -> f64 {
+        self.semi_axis_y
+    }
+}
+
+// At least 3 sides are needed, and the side length must be finite and non-negative.
+impl RegularPolygon {
+    pub fn new(sides: usize, side_length: f64) -> Result<Self, Error> {
+        if sides < 3 {
+            return Err(Error::InvalidSideCount(sides));
+        }
+        check_length(side_length, Error::InvalidSideLength)?;
+        Ok(Self { sides, side_length })
+    }
+    pub fn set_sides(&mut self, sides: usize) -> Result<(), Error> {
+        if sides < 3 {
+            return Err(Error::InvalidSideCount(sides));
+        }
+        self.sides = sides;
+        Ok(())
+    }
+    pub fn set_side_length(&mut self, side_length: f64) -> Result<(), Error> {
+        check_length(side_length, Error::InvalidSideLength)?;
+        self.side_length = side_length;
+        Ok(())
+    }
+    pub fn get_sides(&self) -> usize {
+        self.sides
+    }
+    pub fn get_side_length(&self) -> f64 {
+        self.side_length
+    }
+}
+
+// The vertices are (x, y) points in order, the last one connected back to the first.
+// At least 3 are needed, their coordinates must be finite and no two edges may
+// cross, touch or overlap, except for neighbouring edges meeting at their shared vertex.
+impl Polygon {
+    pub fn new(vertices: Vec<(f64, f64)>) -> Result<Self, Error> {
+        check_polygon(&vertices)?;
+        Ok(Self { vertices })
+    }
+    pub fn set_vertices(&mut self, vertices: Vec<(f64, f64)>) -> Result<(), Error> {
+        check_polygon(&vertices)?;
+        self.vertices = vertices;
+        Ok(())
+    }
+    pub fn get_vertices(&self) -> &[(f64, f64)] {
+        &self.vertices
+    }
+
+    // Edges as (start, end), the last one closing the polygon
+    fn edges(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        edges(&self.vertices)
+    }
+}
+
+// Edges of the closed outline through vertices, as (start, end)
+pub(crate) fn edges(
+    vertices: &[(f64, f64)],
+) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+    let n = vertices.len();
+    (0..n).map(move |i| (vertices[i], vertices[(i + 1) % n]))
+}
+
+fn check_polygon(vertices: &[(f64, f64)]) -> Result<(), Error> {
+    let n = vertices.len();
+    if n < 3 {
+        return Err(Error::TooFewVertices(n));
+    }
+    if let Some(&(x, y)) = vertices.iter().find(|(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(Error::InvalidVertex(x, y));
+    }
+    let edges: Vec<_> = edges(vertices).collect();
+    for i in 0..n {
+        for j in i + 1..n {
+            let (p, q) = edges[i];
+            let (r, s) = edges[j];
+            let intersecting = if j == i + 1 {
+                // Edge i ends where edge j starts
+                folds_back(p, q, s)
+            } else if i == 0 && j == n - 1 {
+                // Edge j ends where edge i starts
+                folds_back(r, p, q)
+            } else {
+                segments_intersect(p, q, r, s)
+            };
+            if intersecting {
+                return Err(Error::SelfIntersecting);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Positive if a, b, c turn counterclockwise, negative if clockwise, zero if collinear
+pub(crate) fn orientation(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+// Whether the edges a-b and b-c overlap, i.e. c lies back along a-b
+// A zero length edge also counts, as it overlaps its neighbour
+fn folds_back(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    let towards_a = (a.0 - b.0) * (c.0 - b.0) + (a.1 - b.1) * (c.1 - b.1);
+    a == b || b == c || (orientation(a, b, c) == 0.0 && towards_a > 0.0)
+}
+
+// Whether c lies within the bounding box of a-b; used for collinear points only
+pub(crate) fn on_segment(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> bool {
+    c.0 >= a.0.min(b.0) && c.0 <= a.0.max(b.0) && c.1 >= a.1.min(b.1) && c.1 <= a.1.max(b.1)
+}
+
+// Whether the segments p-q and r-s have a point in common, end points included
+pub(crate) fn segments_intersect(
+    p: (f64, f64),
+    q: (f64, f64),
+    r: (f64, f64),
+    s: (f64, f64),
+) -> bool {
+    let (o1, o2) = (orientation(p, q, r), orientation(p, q, s));
+    let (o3, o4) = (orientation(r, s, p), orientation(r, s, q));
+    if o1 * o2 < 0.0 && o3 * o4 < 0.0 {
+        return true;
+    }
+    (o1 == 0.0 && on_segment(p, q, r))
+        || (o2 == 0.0 && on_segment(p, q, s))
+        || (o3 == 0.0 && on_segment(r, s, p))
+        || (o4 == 0.0 && on_segment(r, s, q))
 }
 
 // Implement the Shape trait for both Rectangle and Circle structs.
@@ -104,6 +463,14 @@ impl Shape for Rectangle {
     fn perimeter(&self) -> f64 {
         2.0 * (self.width + self.height)
     }
+
+    fn kind(&self) -> ShapeKind {
+        ShapeKind::Rectangle
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.unit
+    }
 }
 
 impl Shape for Circle {
@@ -114,4 +481,88 @@ impl Shape for Circle {
     fn perimeter(&self) -> f64 {
         2.0 * self.radius * std::f64::consts::PI
     }
+
+    fn kind(&self) -> ShapeKind {
+        ShapeKind::Circle
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+}
+
+impl Shape for Triangle {
+    // Heron's formula, in the form that stays accurate for needle-like triangles
+    fn area(&self) -> f64 {
+        let mut sides = [self.a, self.b, self.c];
+        sides.sort_by(|x, y| y.total_cmp(x));
+        let [a, b, c] = sides;
+        let product = (a + (b + c)) * (c - (a - b)) * (c + (a - b)) * (a + (b - c));
+        // Rounding can push a flat triangle slightly below zero
+        product.max(0.0).sqrt() / 4.0
+    }
+
+    fn perimeter(&self) -> f64 {
+        self.a + self.b + self.c
+    }
+
+    fn kind(&self) -> ShapeKind {
+        ShapeKind::Triangle
+    }
+}
+
+impl Shape for Ellipse {
+    fn area(&self) -> f64 {
+        self.semi_axis_x * self.semi_axis_y * std::f64::consts::PI
+    }
+
+    // Ramanujan's second approximation, exact for circles
+    fn perimeter(&self) -> f64 {
+        let (a, b) = (self.semi_axis_x, self.semi_axis_y);
+        if a + b == 0.0 {
+            return 0.0;
+        }
+        let h = ((a - b) / (a + b)).powi(2);
+        std::f64::consts::PI * (a + b) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()))
+    }
+
+    fn kind(&self) -> ShapeKind {
+        ShapeKind::Ellipse
+    }
+}
+
+impl Shape for RegularPolygon {
+    fn area(&self) -> f64 {
+        let n = self.sides as f64;
+        n * self.side_length * self.side_length / (4.0 * (std::f64::consts::PI / n).tan())
+    }
+
+    fn perimeter(&self) -> f64 {
+        self.sides as f64 * self.side_length
+    }
+
+    fn kind(&self) -> ShapeKind {
+        ShapeKind::RegularPolygon
+    }
+}
+
+impl Shape for Polygon {
+    // Shoelace formula
+    fn area(&self) -> f64 {
+        let twice_area: f64 = self
+            .edges()
+            .map(|(p, q)| p.0 * q.1 - q.0 * p.1)
+            .sum();
+        twice_area.abs() / 2.0
+    }
+
+    fn perimeter(&self) -> f64 {
+        self.edges()
+            .map(|(p, q)| (q.0 - p.0).hypot(q.1 - p.1))
+            .sum()
+    }
+
+    fn kind(&self) -> ShapeKind {
+        ShapeKind::Polygon
+    }
 }
@@ -0,0 +1,312 @@
+//! Three-dimensional solids next to the planar shapes.
+//!
+//! Every solid implements `Solid`, with its volume and surface area. Solids
+//! are built from their dimensions, or from a planar shape:
+//!
+//! - `Cuboid::extrude`: a rectangle pushed out by a depth
+//! - `Cylinder::extrude`: a circle pushed out by a height
+//! - `Cylinder::revolve`: a rectangle turned around its height side, its width
+//!   becoming the radius
+//! - `Sphere::revolve`: a circle turned around a diameter
+//! - `Cone::from_base`: a circle and the height of the apex above it
+//! - `Prism::extrude`: any shape pushed out by a height
+//!
+//! Dimensions are validated like those of shapes and rejected with the same
+//! `shapes::Error`, e.g. `Error::InvalidHeight` for a negative height. Solids
+//! built from a shape keep its unit; solids built from plain numbers are in
+//! metres.
+
+use std::f64::consts::PI;
+
+use crate::shapes::*;
+use crate::units::LengthUnit;
+
+pub trait Solid: std::fmt::Debug {
+    fn volume(&self) -> f64;
+    fn surface_area(&self) -> f64;
+
+    // The unit of the dimensions; volume and surface area are in its cube and square
+    fn unit(&self) -> LengthUnit {
+        LengthUnit::Metre
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cuboid {
+    width: f64,
+    height: f64,
+    depth: f64,
+    unit: LengthUnit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sphere {
+    radius: f64,
+    unit: LengthUnit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cylinder {
+    radius: f64,
+    height: f64,
+    unit: LengthUnit,
+}
+
+// A right circular cone
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cone {
+    radius: f64,
+    height: f64,
+    unit: LengthUnit,
+}
+
+// A right prism over any base shape
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prism<S> {
+    base: S,
+    height: f64,
+}
+
+// Width, height and depth are considered invalid if they are negative, NaN or infinite.
+impl Cuboid {
+    pub fn new(width: f64, height: f64, depth: f64) -> Result<Self, Error> {
+        check_length(width, Error::InvalidWidth)?;
+        check_length(height, Error::InvalidHeight)?;
+        check_length(depth, Error::InvalidDepth)?;
+        Ok(Self {
+            width,
+            height,
+            depth,
+            unit: LengthUnit::Metre,
+        })
+    }
+    // The depth is in the unit of the rectangle
+    pub fn extrude(base: &Rectangle, depth: f64) -> Result<Self, Error> {
+        let mut cuboid = Self::new(base.get_width(), base.get_height(), depth)?;
+        cuboid.unit = base.get_unit();
+        Ok(cuboid)
+    }
+    pub fn set_width(&mut self, width: f64) -> Result<(), Error> {
+        check_length(width, Error::InvalidWidth)?;
+        self.width = width;
+        Ok(())
+    }
+    pub fn set_height(&mut self, height: f64) -> Result<(), Error> {
+        check_length(height, Error::InvalidHeight)?;
+        self.height = height;
+        Ok(())
+    }
+    pub fn set_depth(&mut self, depth: f64) -> Result<(), Error> {
+        check_length(depth, Error::InvalidDepth)?;
+        self.depth = depth;
+        Ok(())
+    }
+    pub fn get_width(&self) -> f64 {
+        self.width
+    }
+    pub fn get_height(&self) -> f64 {
+        self.height
+    }
+    pub fn get_depth(&self) -> f64 {
+        self.depth
+    }
+}
+
+// The radius is considered invalid if it is negative, NaN or infinite.
+impl Sphere {
+    pub fn new(radius: f64) -> Result<Self, Error> {
+        check_length(radius, Error::InvalidRadius)?;
+        Ok(Self {
+            radius,
+            unit: LengthUnit::Metre,
+        })
+    }
+    pub fn revolve(circle: &Circle) -> Self {
+        Self {
+            radius: circle.get_radius(),
+            unit: circle.get_unit(),
+        }
+    }
+    pub fn set_radius(&mut self, radius: f64) -> Result<(), Error> {
+        check_length(radius, Error::InvalidRadius)?;
+        self.radius = radius;
+        Ok(())
+    }
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
+}
+
+// Radius and height are considered invalid if they are negative, NaN or infinite.
+impl Cylinder {
+    pub fn new(radius: f64, height: f64) -> Result<Self, Error> {
+        check_length(radius, Error::InvalidRadius)?;
+        check_length(height, Error::InvalidHeight)?;
+        Ok(Self {
+            radius,
+            height,
+            unit: LengthUnit::Metre,
+        })
+    }
+    // The height is in the unit of the circle
+    pub fn extrude(base: &Circle, height: f64) -> Result<Self, Error> {
+        let mut cylinder = Self::new(base.get_radius(), height)?;
+        cylinder.unit = base.get_unit();
+        Ok(cylinder)
+    }
+    pub fn revolve(rectangle: &Rectangle) -> Self {
+        Self {
+            radius: rectangle.get_width(),
+            height: rectangle.get_height(),
+            unit: rectangle.get_unit(),
+        }
+    }
+    pub fn set_radius(&mut self, radius: f64) -> Result<(), Error> {
+        check_length(radius, Error::InvalidRadius)?;
+        self.radius = radius;
+        Ok(())
+    }
+    pub fn set_height(&mut self, height: f64) -> Result<(), Error> {
+        check_length(height, Error::InvalidHeight)?;
+        self.height = height;
+        Ok(())
+    }
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
+    pub fn get_height(&self) -> f64 {
+        self.height
+    }
+}
+
+// Radius and height are considered invalid if they are negative, NaN or infinite.
+impl Cone {
+    pub fn new(radius: f64, height: f64) -> Result<Self, Error> {
+        check_length(radius, Error::InvalidRadius)?;
+        check_length(height, Error::InvalidHeight)?;
+        Ok(Self {
+            radius,
+            height,
+            unit: LengthUnit::Metre,
+        })
+    }
+    // The height is in the unit of the circle
+    pub fn from_base(base: &Circle, height: f64) -> Result<Self, Error> {
+        let mut cone = Self::new(base.get_radius(), height)?;
+        cone.unit = base.get_unit();
+        Ok(cone)
+    }
+    pub fn set_radius(&mut self, radius: f64) -> Result<(), Error> {
+        check_length(radius, Error::InvalidRadius)?;
+        self.radius = radius;
+        Ok(())
+    }
+    pub fn set_height(&mut self, height: f64) -> Result<(), Error> {
+        check_length(height, Error::InvalidHeight)?;
+        self.height = height;
+        Ok(())
+    }
+    pub fn get_radius(&self) -> f64 {
+        self.radius
+    }
+    pub fn get_height(&self) -> f64 {
+        self.height
+    }
+    // Distance from the apex to the edge of the base
+    pub fn slant_height(&self) -> f64 {
+        self.radius.hypot(self.height)
+    }
+}
+
+// The height is considered invalid if it is negative, NaN or infinite.
+impl<S: Shape> Prism<S> {
+    // The height is in the unit of the base
+    pub fn extrude(base: S, height: f64) -> Result<Self, Error> {
+        check_length(height, Error::InvalidHeight)?;
+        Ok(Self { base, height })
+    }
+    pub fn set_height(&mut self, height: f64) -> Result<(), Error> {
+        check_length(height, Error::InvalidHeight)?;
+        self.height = height;
+        Ok(())
+    }
+    pub fn get_base(&self) -> &S {
+        &self.base
+    }
+    pub fn get_height(&self) -> f64 {
+        self.height
+    }
+}
+
+impl Solid for Cuboid {
+    fn volume(&self) -> f64 {
+        self.width * self.height * self.depth
+    }
+
+    fn surface_area(&self) -> f64 {
+        2.0 * (self.width * self.height + self.width * self.depth + self.height * self.depth)
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+}
+
+impl Solid for Sphere {
+    fn volume(&self) -> f64 {
+        4.0 / 3.0 * PI * self.radius.powi(3)
+    }
+
+    fn surface_area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+}
+
+impl Solid for Cylinder {
+    fn volume(&self) -> f64 {
+        PI * self.radius * self.radius * self.height
+    }
+
+    // Both caps and the side
+    fn surface_area(&self) -> f64 {
+        2.0 * PI * self.radius * (self.radius + self.height)
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+}
+
+impl Solid for Cone {
+    fn volume(&self) -> f64 {
+        PI * self.radius * self.radius * self.height / 3.0
+    }
+
+    // The base and the side
+    fn surface_area(&self) -> f64 {
+        PI * self.radius * (self.radius + self.slant_height())
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+}
+
+impl<S: Shape> Solid for Prism<S> {
+    fn volume(&self) -> f64 {
+        self.base.area() * self.height
+    }
+
+    // Both ends and one rectangle per side
+    fn surface_area(&self) -> f64 {
+        2.0 * self.base.area() + self.base.perimeter() * self.height
+    }
+
+    fn unit(&self) -> LengthUnit {
+        self.base.unit()
+    }
+}
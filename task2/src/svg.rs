@@ -0,0 +1,278 @@
+//! SVG export of placed shapes.
+//!
+//! An `SvgDocument` collects shapes, each with a `Style` for its outline, its
+//! fill and an optional label with its area and perimeter, and renders them
+//! into a standalone SVG document. The viewBox is computed from the bounding
+//! boxes of the shapes, plus a margin, so every shape is in view.
+//!
+//! Shapes are drawn where they are placed (see `crate::placement`); shapes
+//! added without a placement sit at the origin. The y axis points up as in
+//! the rest of the geometry code, so coordinates are mirrored on the way out,
+//! SVG's y axis pointing down. Numbers are written with at most 3 decimals.
+//!
+//! All shapes of a document are in one unit, which is also the unit of the
+//! coordinates, the margin and the stroke widths. Adding a shape in another
+//! unit is an `Error::MixedUnits`; convert it first, e.g. with
+//! `Rectangle::to_unit`.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::placement::{BoundingBox, Outline, Outlined, Placed, Placement, Positioned};
+use crate::shapes::Error;
+use crate::units::LengthUnit;
+
+// Space around the shapes, in the units of the shapes
+const DEFAULT_MARGIN: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Label {
+    #[default]
+    Hidden,
+    Area,
+    Perimeter,
+    AreaAndPerimeter,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    // Any SVG paint, e.g. "black" or "#ff8800"; "none" disables it
+    pub stroke: String,
+    pub stroke_width: f64,
+    pub fill: String,
+    // Text written at the center of the bounding box
+    pub label: Label,
+}
+
+impl Default for Style {
+    // A thin black outline without fill or label
+    fn default() -> Self {
+        Style {
+            stroke: "black".to_string(),
+            stroke_width: 0.1,
+            fill: "none".to_string(),
+            label: Label::Hidden,
+        }
+    }
+}
+
+pub struct SvgDocument {
+    items: Vec<(Box<dyn Placed>, Style)>,
+    margin: f64,
+}
+
+impl Default for SvgDocument {
+    fn default() -> Self {
+        SvgDocument {
+            items: Vec::new(),
+            margin: DEFAULT_MARGIN,
+        }
+    }
+}
+
+impl SvgDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Space left around the shapes when computing the viewBox
+    pub fn set_margin(&mut self, margin: f64) {
+        self.margin = margin;
+    }
+
+    // The unit of the shapes, None for an empty document
+    pub fn unit(&self) -> Option<LengthUnit> {
+        self.items.first().map(|(shape, _)| shape.shape().unit())
+    }
+
+    // Add a placed shape; shapes are drawn in the order they are added
+    // Return Err(Error::MixedUnits) if its unit is not that of the shapes already added
+    pub fn add(&mut self, shape: impl Placed + 'static, style: Style) -> Result<(), Error> {
+        let unit = shape.shape().unit();
+        match self.unit() {
+            Some(document_unit) if document_unit != unit => {
+                Err(Error::MixedUnits(document_unit, unit))
+            }
+            _ => {
+                self.items.push((Box::new(shape), style));
+                Ok(())
+            }
+        }
+    }
+
+    // Add a shape at the origin without rotation
+    pub fn add_shape<S: Outlined + 'static>(
+        &mut self,
+        shape: S,
+        style: Style,
+    ) -> Result<(), Error> {
+        self.add(Positioned::new(shape, Placement::default()), style)
+    }
+
+    // The viewBox, in the coordinates of the shapes (y pointing up)
+    // An empty document gets a box of the margin around the origin
+    pub fn view_box(&self) -> BoundingBox {
+        let bounds = self
+            .items
+            .iter()
+            .map(|(shape, _)| shape.bounding_box())
+            .reduce(|first, second| BoundingBox {
+                min_x: first.min_x.min(second.min_x),
+                min_y: first.min_y.min(second.min_y),
+                max_x: first.max_x.max(second.max_x),
+                max_y: first.max_y.max(second.max_y),
+            })
+            .unwrap_or(BoundingBox {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: 0.0,
+                max_y: 0.0,
+            });
+        BoundingBox {
+            min_x: bounds.min_x - self.margin,
+            min_y: bounds.min_y - self.margin,
+            max_x: bounds.max_x + self.margin,
+            max_y: bounds.max_y + self.margin,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let view_box = self.view_box();
+        let (width, height) = (
+            view_box.max_x - view_box.min_x,
+            view_box.max_y - view_box.min_y,
+        );
+        // Labels scale with the drawing
+        let font_size = width.max(height) / 30.0;
+
+        let mut res = String::new();
+        res.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        writeln!(
+            res,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+            number(view_box.min_x),
+            number(-view_box.max_y),
+            number(width),
+            number(height)
+        )
+        .unwrap();
+        for (shape, style) in &self.items {
+            writeln!(res, "  {}", element(&shape.outline(), style)).unwrap();
+            if let Some(text) = label(shape.as_ref(), style.label) {
+                let bounds = shape.bounding_box();
+                writeln!(
+                    res,
+                    "  <text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+                     dominant-baseline=\"middle\">{}</text>",
+                    number((bounds.min_x + bounds.max_x) / 2.0),
+                    number(-(bounds.min_y + bounds.max_y) / 2.0),
+                    number(font_size),
+                    escape(&text)
+                )
+                .unwrap();
+            }
+        }
+        res.push_str("</svg>\n");
+        res
+    }
+
+    pub fn write_to<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        output.write_all(self.render().as_bytes())
+    }
+}
+
+// The SVG element drawing an outline, with y mirrored
+fn element(outline: &Outline, style: &Style) -> String {
+    let paint = format!(
+        "stroke=\"{}\" stroke-width=\"{}\" fill=\"{}\"",
+        escape(&style.stroke),
+        number(style.stroke_width),
+        escape(&style.fill)
+    );
+    match *outline {
+        Outline::Polygon(ref vertices) => {
+            let points: Vec<String> = vertices
+                .iter()
+                .map(|(x, y)| format!("{},{}", number(*x), number(-y)))
+                .collect();
+            format!("<polygon points=\"{}\" {}/>", points.join(" "), paint)
+        }
+        Outline::Ellipse {
+            center: (x, y),
+            semi_axis_x,
+            semi_axis_y,
+            ..
+        } if semi_axis_x == semi_axis_y => format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+            number(x),
+            number(-y),
+            number(semi_axis_x),
+            paint
+        ),
+        Outline::Ellipse {
+            center: (x, y),
+            semi_axis_x,
+            semi_axis_y,
+            rotation,
+        } => {
+            let mut res = format!(
+                "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" ",
+                number(x),
+                number(-y),
+                number(semi_axis_x),
+                number(semi_axis_y)
+            );
+            // Counterclockwise with y up is clockwise with y down
+            let degrees = -rotation.to_degrees();
+            if number(degrees) != "0" {
+                write!(
+                    res,
+                    "transform=\"rotate({} {} {})\" ",
+                    number(degrees),
+                    number(x),
+                    number(-y)
+                )
+                .unwrap();
+            }
+            res.push_str(&paint);
+            res.push_str("/>");
+            res
+        }
+    }
+}
+
+fn label(shape: &dyn Placed, label: Label) -> Option<String> {
+    let shape = shape.shape();
+    let area = || {
+        let area = shape.measured_area();
+        format!("A = {} {}²", number(area.value()), area.unit())
+    };
+    let perimeter = || {
+        let perimeter = shape.measured_perimeter();
+        format!("P = {} {}", number(perimeter.value()), perimeter.unit())
+    };
+    match label {
+        Label::Hidden => None,
+        Label::Area => Some(area()),
+        Label::Perimeter => Some(perimeter()),
+        Label::AreaAndPerimeter => Some(format!("{}, {}", area(), perimeter())),
+    }
+}
+
+// At most 3 decimals without trailing zeros, and no negative zero
+fn number(value: f64) -> String {
+    let res = format!("{:.3}", value);
+    let res = res.trim_end_matches('0').trim_end_matches('.');
+    match res {
+        "-0" => "0".to_string(),
+        _ => res.to_string(),
+    }
+}
+
+// Escape the characters with a special meaning in XML text and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
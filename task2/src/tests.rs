@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod calculator_tests {
+    use crate::bigint::BigInt;
     use crate::calculator::*;
     use rand::Rng;
 
@@ -9,12 +10,12 @@ mod calculator_tests {
         let y_in: i64 = 5;
         let mut calculator = Calculator::new();
 
-        assert_eq!(calculator.addition(x_in, y_in), x_in.checked_add(y_in));
+        assert_eq!(calculator.addition(x_in, y_in).ok(), x_in.checked_add(y_in));
 
         let new_x_in: i64 = 8;
         let new_y_in: i64 = 57;
 
-        assert_eq!(calculator.addition(new_x_in, new_y_in), new_x_in.checked_add(new_y_in));
+        assert_eq!(calculator.addition(new_x_in, new_y_in).ok(), new_x_in.checked_add(new_y_in));
     }
 
     #[test]
@@ -23,12 +24,12 @@ mod calculator_tests {
         let y_in: i64 = 5;
         let mut calculator = Calculator::new();
 
-        assert_eq!(calculator.subtraction(x_in, y_in), x_in.checked_sub(y_in));
+        assert_eq!(calculator.subtraction(x_in, y_in).ok(), x_in.checked_sub(y_in));
 
         let new_x_in: i64 = 13;
         let new_y_in: i64 = 21;
 
-        assert_eq!(calculator.subtraction(new_x_in, new_y_in), new_x_in.checked_sub(new_y_in));
+        assert_eq!(calculator.subtraction(new_x_in, new_y_in).ok(), new_x_in.checked_sub(new_y_in));
     }
 
     #[test]
@@ -37,12 +38,12 @@ mod calculator_tests {
         let y_in: i64 = 5;
         let mut calculator = Calculator::new();
 
-        assert_eq!(calculator.multiplication(x_in, y_in), x_in.checked_mul(y_in));
+        assert_eq!(calculator.multiplication(x_in, y_in).ok(), x_in.checked_mul(y_in));
 
         let new_x_in: i64 = 2;
         let new_y_in: i64 = 473;
 
-        assert_eq!(calculator.multiplication(new_x_in, new_y_in), new_x_in.checked_mul(new_y_in));
+        assert_eq!(calculator.multiplication(new_x_in, new_y_in).ok(), new_x_in.checked_mul(new_y_in));
     }
 
     #[test]
@@ -51,7 +52,7 @@ mod calculator_tests {
         let y_in: i64 = 1;
         let mut calculator = Calculator::new();
 
-        assert_eq!(calculator.addition(x_in, y_in), None);
+        assert_eq!(calculator.addition(x_in, y_in), Err(CalcError::Overflow));
     }
 
     #[test]
@@ -60,7 +61,7 @@ mod calculator_tests {
         let y_in: i64 = 1;
         let mut calculator = Calculator::new();
 
-        assert_eq!(calculator.subtraction(x_in, y_in), None);
+        assert_eq!(calculator.subtraction(x_in, y_in), Err(CalcError::Overflow));
     }
 
     #[test]
@@ -69,7 +70,7 @@ mod calculator_tests {
         let y_in: i64 = 2;
         let mut calculator = Calculator::new();
 
-        assert_eq!(calculator.multiplication(x_in, y_in), None);
+        assert_eq!(calculator.multiplication(x_in, y_in), Err(CalcError::Overflow));
     }
 
     #[test]
@@ -80,9 +81,9 @@ mod calculator_tests {
             let y_in = rng.gen::<i64>();
 
             let mut calculator = Calculator::new();
-            assert_eq!(calculator.addition(x_in, y_in), x_in.checked_add(y_in));
-            assert_eq!(calculator.subtraction(x_in, y_in), x_in.checked_sub(y_in));
-            assert_eq!(calculator.multiplication(x_in, y_in), x_in.checked_mul(y_in));
+            assert_eq!(calculator.addition(x_in, y_in).ok(), x_in.checked_add(y_in));
+            assert_eq!(calculator.subtraction(x_in, y_in).ok(), x_in.checked_sub(y_in));
+            assert_eq!(calculator.multiplication(x_in, y_in).ok(), x_in.checked_mul(y_in));
         }
     }
 
@@ -96,24 +97,202 @@ mod calculator_tests {
     #[test]
     fn operation_type_perform() {
         // Test normal operations
-        assert_eq!(OperationType::Addition.perform(5, 3), Some(8));
-        assert_eq!(OperationType::Subtraction.perform(5, 3), Some(2));
-        assert_eq!(OperationType::Multiplication.perform(5, 3), Some(15));
+        assert_eq!(OperationType::Addition.perform(5, 3), Ok(8));
+        assert_eq!(OperationType::Subtraction.perform(5, 3), Ok(2));
+        assert_eq!(OperationType::Multiplication.perform(5, 3), Ok(15));
 
         // Test with negative numbers
-        assert_eq!(OperationType::Addition.perform(-5, 3), Some(-2));
-        assert_eq!(OperationType::Subtraction.perform(-5, 3), Some(-8));
-        assert_eq!(OperationType::Multiplication.perform(-5, 3), Some(-15));
+        assert_eq!(OperationType::Addition.perform(-5, 3), Ok(-2));
+        assert_eq!(OperationType::Subtraction.perform(-5, 3), Ok(-8));
+        assert_eq!(OperationType::Multiplication.perform(-5, 3), Ok(-15));
 
         // Test with zero
-        assert_eq!(OperationType::Addition.perform(0, 5), Some(5));
-        assert_eq!(OperationType::Subtraction.perform(0, 5), Some(-5));
-        assert_eq!(OperationType::Multiplication.perform(0, 5), Some(0));
+        assert_eq!(OperationType::Addition.perform(0, 5), Ok(5));
+        assert_eq!(OperationType::Subtraction.perform(0, 5), Ok(-5));
+        assert_eq!(OperationType::Multiplication.perform(0, 5), Ok(0));
 
         // Test overflow cases
-        assert_eq!(OperationType::Addition.perform(i64::MAX, 1), None);
-        assert_eq!(OperationType::Subtraction.perform(i64::MIN, 1), None);
-        assert_eq!(OperationType::Multiplication.perform(i64::MAX / 2 + 1, 2), None);
+        assert_eq!(OperationType::Addition.perform(i64::MAX, 1), Err(CalcError::Overflow));
+        assert_eq!(OperationType::Subtraction.perform(i64::MIN, 1), Err(CalcError::Overflow));
+        assert_eq!(OperationType::Multiplication.perform(i64::MAX / 2 + 1, 2), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn operation_type_perform_extended() {
+        assert_eq!(OperationType::Division.perform(17, 5), Ok(3));
+        assert_eq!(OperationType::Division.perform(-17, 5), Ok(-3));
+        assert_eq!(OperationType::Remainder.perform(17, 5), Ok(2));
+        assert_eq!(OperationType::Remainder.perform(-17, 5), Ok(-2));
+        assert_eq!(OperationType::Power.perform(-3, 3), Ok(-27));
+        assert_eq!(OperationType::Power.perform(7, 0), Ok(1));
+        assert_eq!(OperationType::SquareRoot.perform(17, 0), Ok(4));
+
+        assert_eq!(OperationType::Division.perform(1, 0), Err(CalcError::DivisionByZero));
+        assert_eq!(OperationType::Remainder.perform(1, 0), Err(CalcError::DivisionByZero));
+        assert_eq!(OperationType::Division.perform(i64::MIN, -1), Err(CalcError::Overflow));
+        assert_eq!(OperationType::Remainder.perform(i64::MIN, -1), Err(CalcError::Overflow));
+        assert_eq!(OperationType::Power.perform(2, 63), Err(CalcError::Overflow));
+        assert_eq!(OperationType::Power.perform(2, -1), Err(CalcError::NegativeExponent));
+        assert_eq!(OperationType::Power.perform(-1, i64::MAX), Ok(-1));
+        assert_eq!(OperationType::Power.perform(2, i64::MAX), Err(CalcError::Overflow));
+        assert_eq!(OperationType::SquareRoot.perform(-4, 0), Err(CalcError::NegativeSquareRoot));
+    }
+
+    #[test]
+    fn operation_type_get_sign_extended() {
+        assert_eq!(OperationType::Division.get_sign(), "/");
+        assert_eq!(OperationType::Remainder.get_sign(), "%");
+        assert_eq!(OperationType::Power.get_sign(), "^");
+        assert_eq!(OperationType::SquareRoot.get_sign(), "sqrt");
+    }
+
+    #[test]
+    fn show_history_extended_operations() {
+        let mut calculator = Calculator::new();
+        calculator.division(20, 6).unwrap();
+        calculator.remainder(20, 6).unwrap();
+        calculator.power(2, 10).unwrap();
+        calculator.square_root(99).unwrap();
+        assert_eq!(calculator.division(1, 0), Err(CalcError::DivisionByZero));
+
+        let expected = "0: 20 / 6 = 3\n1: 20 % 6 = 2\n2: 2 ^ 10 = 1024\n3: sqrt(99) = 9\n\
+                        4: 1 / 0 failed: division by zero\n";
+        assert_eq!(calculator.show_history(), expected);
+    }
+
+    #[test]
+    fn failed_operations_are_recorded() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 2).unwrap();
+        assert_eq!(calculator.addition(i64::MAX, 1), Err(CalcError::Overflow));
+        assert_eq!(calculator.square_root(-1), Err(CalcError::NegativeSquareRoot));
+
+        assert_eq!(calculator.history.len(), 3);
+        assert_eq!(calculator.history[0].result, Ok(3));
+        assert_eq!(calculator.history[1].result, Err(CalcError::Overflow));
+        assert_eq!(calculator.history[2].sequence, 2);
+
+        // Repeating a failed operation fails again and is recorded again
+        assert_eq!(calculator.repeat(1), Err(CalcError::Overflow));
+        assert_eq!(calculator.history[3].sequence, 3);
+    }
+
+    #[test]
+    fn show_history_views() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 2).unwrap();
+        calculator.addition(i64::MAX, 1).unwrap_err();
+        calculator.multiplication(3, 4).unwrap();
+        calculator.square_root(-9).unwrap_err();
+
+        assert_eq!(
+            calculator.show_history_view(HistoryView::Successful),
+            "0: 1 + 2 = 3\n2: 3 * 4 = 12\n"
+        );
+        assert_eq!(
+            calculator.show_history_view(HistoryView::Failed),
+            "1: 9223372036854775807 + 1 failed: overflow\n\
+             3: sqrt(-9) failed: square root of a negative number\n"
+        );
+        assert_eq!(
+            calculator.show_history_view(HistoryView::All),
+            calculator.show_history()
+        );
+    }
+
+    #[test]
+    fn sequence_after_undo_and_clear() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 1).unwrap();
+        calculator.addition(2, 2).unwrap();
+        assert_eq!(calculator.undo().map(|op| op.sequence), Some(1));
+
+        calculator.addition(3, 3).unwrap();
+        assert_eq!(calculator.show_history(), "0: 1 + 1 = 2\n1: 3 + 3 = 6\n");
+
+        calculator.clear_history();
+        calculator.addition(4, 4).unwrap();
+        assert_eq!(calculator.show_history(), "0: 4 + 4 = 8\n");
+    }
+
+    #[test]
+    fn operation_type_perform_with_policies() {
+        use OverflowPolicy::*;
+
+        let add = OperationType::Addition;
+        assert_eq!(add.perform_with(i64::MAX, 1, Checked), Err(CalcError::Overflow));
+        assert_eq!(add.perform_with(i64::MAX, 1, Saturating), Ok(i64::MAX));
+        assert_eq!(add.perform_with(i64::MAX, 1, Wrapping), Ok(i64::MIN));
+        assert_eq!(
+            add.perform_with(i64::MAX, 1, Widening),
+            Err(CalcError::OutOfRange(i128::from(i64::MAX) + 1))
+        );
+        assert_eq!(add.perform_with(2, 3, Widening), Ok(5));
+
+        let sub = OperationType::Subtraction;
+        assert_eq!(sub.perform_with(i64::MIN, 1, Saturating), Ok(i64::MIN));
+        assert_eq!(sub.perform_with(i64::MIN, 1, Wrapping), Ok(i64::MAX));
+
+        let mul = OperationType::Multiplication;
+        assert_eq!(mul.perform_with(i64::MIN, 2, Saturating), Ok(i64::MIN));
+        assert_eq!(mul.perform_with(i64::MAX, 2, Wrapping), Ok(-2));
+
+        let div = OperationType::Division;
+        assert_eq!(div.perform_with(i64::MIN, -1, Saturating), Ok(i64::MAX));
+        assert_eq!(div.perform_with(i64::MIN, -1, Wrapping), Ok(i64::MIN));
+        assert_eq!(
+            div.perform_with(i64::MIN, -1, Widening),
+            Err(CalcError::OutOfRange(-i128::from(i64::MIN)))
+        );
+        assert_eq!(div.perform_with(1, 0, Saturating), Err(CalcError::DivisionByZero));
+
+        let rem = OperationType::Remainder;
+        assert_eq!(rem.perform_with(i64::MIN, -1, Saturating), Ok(0));
+        assert_eq!(rem.perform_with(i64::MIN, -1, Wrapping), Ok(0));
+        assert_eq!(rem.perform_with(i64::MIN, -1, Widening), Ok(0));
+
+        let pow = OperationType::Power;
+        assert_eq!(pow.perform_with(-2, 63, Checked), Ok(i64::MIN));
+        assert_eq!(pow.perform_with(-2, 65, Saturating), Ok(i64::MIN));
+        assert_eq!(pow.perform_with(-2, 64, Saturating), Ok(i64::MAX));
+        assert_eq!(pow.perform_with(3, i64::MAX, Saturating), Ok(i64::MAX));
+        assert_eq!(pow.perform_with(2, 64, Wrapping), Ok(0));
+        assert_eq!(pow.perform_with(3, 1 << 40, Wrapping), Ok(3i64.wrapping_pow(1 << 20).wrapping_pow(1 << 20)));
+        assert_eq!(
+            pow.perform_with(2, 64, Widening),
+            Err(CalcError::OutOfRange(1i128 << 64))
+        );
+        assert_eq!(pow.perform_with(2, 200, Widening), Err(CalcError::Overflow));
+        assert_eq!(pow.perform_with(2, -1, Wrapping), Err(CalcError::NegativeExponent));
+
+        let sqrt = OperationType::SquareRoot;
+        assert_eq!(sqrt.perform_with(-1, 0, Wrapping), Err(CalcError::NegativeSquareRoot));
+        assert_eq!(sqrt.perform_with(i64::MAX, 0, Widening), Ok(3037000499));
+    }
+
+    #[test]
+    fn calculator_overflow_policy() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.overflow_policy(), OverflowPolicy::Checked);
+
+        calculator.set_overflow_policy(OverflowPolicy::Saturating);
+        assert_eq!(calculator.addition(i64::MAX, 1), Ok(i64::MAX));
+        assert_eq!(calculator.evaluate("-9223372036854775808 - 1 + 1"), Ok(i64::MIN + 1));
+
+        calculator.set_overflow_policy(OverflowPolicy::Checked);
+        calculator.addition(1, 2).unwrap();
+
+        // Repeating uses the policy recorded with the operation
+        assert_eq!(calculator.repeat(0), Ok(i64::MAX));
+        assert_eq!(calculator.history[0].policy, OverflowPolicy::Saturating);
+        assert_eq!(calculator.history[4].policy, OverflowPolicy::Saturating);
+
+        let expected = "0: 9223372036854775807 + 1 = 9223372036854775807 (saturating)\n\
+                        1: -9223372036854775808 - 1 = -9223372036854775808 (saturating)\n\
+                        2: -9223372036854775808 + 1 = -9223372036854775807 (saturating)\n\
+                        3: 1 + 2 = 3\n\
+                        4: 9223372036854775807 + 1 = 9223372036854775807 (saturating)\n";
+        assert_eq!(calculator.show_history(), expected);
     }
 
     #[test]
@@ -133,7 +312,7 @@ mod calculator_tests {
     #[test]
     fn show_history_single_operation() {
         let mut calculator = Calculator::new();
-        calculator.addition(5, 3);
+        calculator.addition(5, 3).unwrap();
         
         let history = calculator.show_history();
         assert_eq!(history, "0: 5 + 3 = 8\n");
@@ -142,9 +321,9 @@ mod calculator_tests {
     #[test]
     fn show_history_multiple_operations() {
         let mut calculator = Calculator::new();
-        calculator.addition(10, 5);
-        calculator.subtraction(20, 8);
-        calculator.multiplication(3, 4);
+        calculator.addition(10, 5).unwrap();
+        calculator.subtraction(20, 8).unwrap();
+        calculator.multiplication(3, 4).unwrap();
         
         let history = calculator.show_history();
         let expected = "0: 10 + 5 = 15\n1: 20 - 8 = 12\n2: 3 * 4 = 12\n";
@@ -154,12 +333,12 @@ mod calculator_tests {
     #[test]
     fn repeat_valid_operation() {
         let mut calculator = Calculator::new();
-        calculator.addition(7, 3);
-        calculator.subtraction(15, 5);
+        calculator.addition(7, 3).unwrap();
+        calculator.subtraction(15, 5).unwrap();
         
         // Repeat the first operation (addition)
         let result = calculator.repeat(0);
-        assert_eq!(result, Some(10));
+        assert_eq!(result, Ok(10));
         
         // Check that it was added to history
         let history = calculator.show_history();
@@ -170,11 +349,11 @@ mod calculator_tests {
     #[test]
     fn repeat_invalid_index() {
         let mut calculator = Calculator::new();
-        calculator.addition(5, 2);
+        calculator.addition(5, 2).unwrap();
         
         // Try to repeat operation at index 5 (doesn't exist)
         let result = calculator.repeat(5);
-        assert_eq!(result, None);
+        assert_eq!(result, Err(CalcError::InvalidIndex(5)));
         
         // History should remain unchanged
         let history = calculator.show_history();
@@ -187,7 +366,7 @@ mod calculator_tests {
         
         // Try to repeat when no operations exist
         let result = calculator.repeat(0);
-        assert_eq!(result, None);
+        assert_eq!(result, Err(CalcError::InvalidIndex(0)));
         
         // History should still be empty
         assert_eq!(calculator.show_history(), "");
@@ -201,46 +380,1658 @@ mod calculator_tests {
     }
 
     #[test]
-    fn clear_history_with_operations() {
-        let mut calculator = Calculator::new();
-        calculator.addition(1, 2);
-        calculator.subtraction(10, 5);
-        calculator.multiplication(3, 3);
-        
-        // Verify history exists
-        assert!(!calculator.show_history().is_empty());
-        
-        // Clear history
-        calculator.clear_history();
-        assert_eq!(calculator.show_history(), "");
-        
-        // Verify repeat doesn't work after clearing
-        assert_eq!(calculator.repeat(0), None);
+    fn clear_history_with_operations() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 2).unwrap();
+        calculator.subtraction(10, 5).unwrap();
+        calculator.multiplication(3, 3).unwrap();
+        
+        // Verify history exists
+        assert!(!calculator.show_history().is_empty());
+        
+        // Clear history
+        calculator.clear_history();
+        assert_eq!(calculator.show_history(), "");
+        
+        // Verify repeat doesn't work after clearing
+        assert_eq!(calculator.repeat(0), Err(CalcError::InvalidIndex(0)));
+    }
+
+    #[test]
+    fn calculator_workflow_integration() {
+        let mut calculator = Calculator::new();
+        
+        // Perform some operations
+        calculator.addition(10, 5).unwrap();
+        calculator.multiplication(3, 4).unwrap();
+        
+        // Repeat first operation
+        calculator.repeat(0).unwrap();
+        
+        // Check complete history
+        let history = calculator.show_history();
+        let expected = "0: 10 + 5 = 15\n1: 3 * 4 = 12\n2: 10 + 5 = 15\n";
+        assert_eq!(history, expected);
+        
+        // Clear and verify
+        calculator.clear_history();
+        assert_eq!(calculator.show_history(), "");
+        
+        // Add new operation after clearing
+        calculator.subtraction(20, 7).unwrap();
+        assert_eq!(calculator.show_history(), "0: 20 - 7 = 13\n");
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 1).unwrap();
+        calculator.addition(2, 2).unwrap();
+        calculator.addition(3, 3).unwrap();
+
+        assert_eq!(calculator.undo().map(|op| op.first_num), Some(3));
+        assert_eq!(calculator.undo().map(|op| op.first_num), Some(2));
+        assert_eq!(calculator.show_history(), "0: 1 + 1 = 2\n");
+
+        assert_eq!(calculator.redo().map(|op| op.sequence), Some(1));
+        assert_eq!(calculator.show_history(), "0: 1 + 1 = 2\n1: 2 + 2 = 4\n");
+
+        // A new operation discards the remaining redo entries
+        calculator.multiplication(5, 5).unwrap();
+        assert!(calculator.redo().is_none());
+        assert_eq!(calculator.show_history(), "0: 1 + 1 = 2\n1: 2 + 2 = 4\n2: 5 * 5 = 25\n");
+
+        calculator.clear_history();
+        assert!(calculator.undo().is_none());
+        assert!(calculator.redo().is_none());
+    }
+
+    #[test]
+    fn bounded_history_evicts_oldest() {
+        let mut calculator = Calculator::with_max_history(2);
+        calculator.addition(1, 1).unwrap();
+        calculator.addition(2, 2).unwrap();
+        calculator.addition(3, 3).unwrap();
+
+        assert_eq!(calculator.history.len(), 2);
+        assert_eq!(calculator.show_history(), "1: 2 + 2 = 4\n2: 3 + 3 = 6\n");
+
+        // Indices keep referring to the same operations after eviction
+        assert_eq!(calculator.repeat(0), Err(CalcError::InvalidIndex(0)));
+        assert_eq!(calculator.repeat(2), Ok(6));
+        assert_eq!(calculator.show_history(), "2: 3 + 3 = 6\n3: 3 + 3 = 6\n");
+
+        calculator.set_max_history(Some(1));
+        assert_eq!(calculator.show_history(), "3: 3 + 3 = 6\n");
+
+        calculator.set_max_history(None);
+        calculator.addition(4, 4).unwrap();
+        assert_eq!(calculator.history.len(), 2);
+    }
+
+    #[test]
+    fn undo_and_redo_with_bounded_history() {
+        let mut calculator = Calculator::with_max_history(2);
+        calculator.addition(1, 1).unwrap();
+        calculator.addition(2, 2).unwrap();
+        calculator.addition(3, 3).unwrap();
+
+        calculator.undo().unwrap();
+        calculator.undo().unwrap();
+        // The evicted operation cannot be brought back by undo
+        assert!(calculator.undo().is_none());
+
+        calculator.redo().unwrap();
+        calculator.redo().unwrap();
+        assert_eq!(calculator.show_history(), "1: 2 + 2 = 4\n2: 3 + 3 = 6\n");
+        assert_eq!(calculator.get(2).map(|op| op.first_num), Some(3));
+    }
+
+    #[test]
+    fn evaluate_precedence_and_parentheses() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("(-4 + 9) * 3 - 2"), Ok(13));
+        assert_eq!(calculator.evaluate("2 + 3 * 4"), Ok(14));
+        assert_eq!(calculator.evaluate("2 - 3 - 4"), Ok(-5));
+        assert_eq!(calculator.evaluate("-(2 + 3) * -2"), Ok(10));
+        assert_eq!(calculator.evaluate("-9223372036854775808"), Ok(i64::MIN));
+    }
+
+    #[test]
+    fn evaluate_extended_operators() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("2 ^ 3 ^ 2"), Ok(512));
+        assert_eq!(calculator.evaluate("-2 ^ 2"), Ok(-4));
+        assert_eq!(calculator.evaluate("(-2) ^ 2"), Ok(4));
+        assert_eq!(calculator.evaluate("17 / 5 * 5 + 17 % 5"), Ok(17));
+        assert_eq!(calculator.evaluate("sqrt(3 * 3 + 4 * 4)"), Ok(5));
+        assert_eq!(calculator.evaluate("1 / (2 - 2)"), Err(CalcError::DivisionByZero));
+        assert_eq!(calculator.evaluate("2 ^ -1"), Err(CalcError::NegativeExponent));
+    }
+
+    #[test]
+    fn evaluate_records_intermediate_steps() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(calculator.evaluate("(-4 + 9) * 3 - 2"), Ok(13));
+
+        let expected = "0: -4 + 9 = 5\n1: 5 * 3 = 15\n2: 15 - 2 = 13\n";
+        assert_eq!(calculator.show_history(), expected);
+    }
+
+    #[test]
+    fn evaluate_overflow() {
+        let mut calculator = Calculator::new();
+
+        assert_eq!(
+            calculator.evaluate("1 + 9223372036854775807 * 2"),
+            Err(CalcError::Overflow)
+        );
+        assert_eq!(
+            calculator.show_history(),
+            "0: 9223372036854775807 * 2 failed: overflow\n"
+        );
+    }
+
+    #[test]
+    fn evaluate_parse_error() {
+        let mut calculator = Calculator::new();
+
+        match calculator.evaluate("2 + * 3") {
+            Err(CalcError::Parse(err)) => assert_eq!(err.position, 4),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+        assert_eq!(calculator.show_history(), "");
+    }
+
+    #[test]
+    fn unsigned_and_wide_backends() {
+        let mut calculator = Calculator::<u64>::default();
+        assert_eq!(calculator.subtraction(0, 1), Err(CalcError::Overflow));
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        assert_eq!(calculator.subtraction(0, 1), Ok(u64::MAX));
+        calculator.set_overflow_policy(OverflowPolicy::Widening);
+        assert_eq!(calculator.subtraction(0, 1), Err(CalcError::OutOfRange(-1)));
+        assert_eq!(calculator.evaluate("18446744073709551615 / 5"), Ok(u64::MAX / 5));
+        assert_eq!(calculator.repeat(1), Ok(u64::MAX));
+
+        let mut calculator = Calculator::<i128>::default();
+        assert_eq!(calculator.power(2, 100), Ok(1i128 << 100));
+        assert_eq!(calculator.power(2, 127), Err(CalcError::Overflow));
+        calculator.set_overflow_policy(OverflowPolicy::Saturating);
+        assert_eq!(calculator.multiplication(i128::MIN, 3), Ok(i128::MIN));
+        assert_eq!(calculator.show_history().lines().next(), Some("0: 2 ^ 100 = 1267650600228229401496703205376"));
+    }
+
+    #[test]
+    fn float_backend() {
+        let mut calculator = Calculator::<f64>::default();
+        assert_eq!(calculator.evaluate("1.5 * 4 - sqrt(2.25)"), Ok(4.5));
+        assert_eq!(calculator.division(1.0, 0.0), Err(CalcError::DivisionByZero));
+        assert_eq!(calculator.multiplication(f64::MAX, 2.0), Err(CalcError::Overflow));
+        assert_eq!(calculator.square_root(-4.0), Err(CalcError::NegativeSquareRoot));
+
+        calculator.set_overflow_policy(OverflowPolicy::Saturating);
+        assert_eq!(calculator.multiplication(f64::MAX, -2.0), Ok(f64::MIN));
+        assert_eq!(
+            calculator.subtraction(f64::INFINITY, f64::INFINITY),
+            Err(CalcError::NotANumber)
+        );
+
+        // Wrapping keeps whatever IEEE 754 produces
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        assert_eq!(calculator.division(1.0, 0.0), Ok(f64::INFINITY));
+        assert!(calculator.square_root(-1.0).unwrap().is_nan());
+
+        assert_eq!(calculator.history[0].first_num, 1.5);
+        assert_eq!(calculator.get(2).map(|op| op.result.clone()), Some(Ok(4.5)));
+    }
+
+    #[test]
+    fn bigint_backend() {
+        let big = |text: &str| text.parse::<BigInt>().unwrap();
+        let mut calculator = Calculator::<BigInt>::default();
+
+        assert_eq!(
+            calculator.evaluate("2 ^ 100 + 1"),
+            Ok(big("1267650600228229401496703205377"))
+        );
+        assert_eq!(
+            calculator.multiplication(big("-99999999999999999999"), big("99999999999999999999")),
+            Ok(big("-9999999999999999999800000000000000000001"))
+        );
+        assert_eq!(
+            calculator.evaluate("-1000000000000000000000007 / 1000000000000"),
+            Ok(big("-1000000000000"))
+        );
+        assert_eq!(
+            calculator.evaluate("-1000000000000000000000007 % 1000000000000"),
+            Ok(big("-7"))
+        );
+        assert_eq!(
+            calculator.square_root(big("1000000000000000000000000000000000000000")),
+            Ok(big("31622776601683793319"))
+        );
+        assert_eq!(calculator.division(big("5"), BigInt::zero()), Err(CalcError::DivisionByZero));
+        assert_eq!(calculator.power(big("2"), big("1000000")), Err(CalcError::Overflow));
+        assert_eq!(calculator.power(big("-1"), big("1000000001")), Ok(big("-1")));
+        assert_eq!(calculator.repeat(0), Ok(big("1267650600228229401496703205376")));
+
+        // Agrees with i128 wherever i128 does not overflow
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let x: i64 = rng.gen();
+            let y: i64 = rng.gen_range(-1_000_000..1_000_000);
+            for operation_type in [
+                OperationType::Addition,
+                OperationType::Subtraction,
+                OperationType::Multiplication,
+                OperationType::Division,
+                OperationType::Remainder,
+            ] {
+                let expected = operation_type.compute(&i128::from(x), &i128::from(y), OverflowPolicy::Checked);
+                let actual = operation_type.compute(&BigInt::from(x), &BigInt::from(y), OverflowPolicy::Checked);
+                assert_eq!(actual.map(|value| value.to_i128()), expected.map(Some));
+            }
+        }
+    }
+
+    #[test]
+    fn variables_and_ans() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate("let fee = 5000"), Ok(5000));
+        assert_eq!(calculator.assign("count", 3), Ok(3));
+        assert_eq!(calculator.evaluate("fee * count"), Ok(15000));
+        assert_eq!(calculator.evaluate("ans + 1"), Ok(15001));
+        assert_eq!(calculator.evaluate("7"), Ok(7));
+        assert_eq!(calculator.ans(), Some(&7));
+        assert_eq!(calculator.evaluate("let fee = fee / 2"), Ok(2500));
+        assert_eq!(calculator.lookup("fee"), Ok(2500));
+
+        let expected = "0: fee = 5000\n\
+                        1: count = 3\n\
+                        2: 5000 * 3 = 15000\n\
+                        3: 15000 + 1 = 15001\n\
+                        4: 5000 / 2 = 2500\n\
+                        5: fee = 2500\n";
+        assert_eq!(calculator.show_history(), expected);
+
+        // Undoing an assignment restores the previous value
+        calculator.undo();
+        assert_eq!(calculator.lookup("fee"), Ok(5000));
+        assert_eq!(calculator.repeat(1), Ok(3));
+        assert!(calculator.show_history().ends_with("5: count = 3\n"));
+        assert_eq!(
+            calculator.variables().collect::<Vec<_>>(),
+            vec![("count", &3), ("fee", &5000)]
+        );
+    }
+
+    #[test]
+    fn undo_assignment_after_eviction() {
+        let mut calculator = Calculator::with_max_history(2);
+        calculator.assign("x", 1).unwrap();
+        calculator.assign("x", 2).unwrap();
+        calculator.evaluate("1 + 1").unwrap();
+        assert_eq!(calculator.show_history(), "1: x = 2\n2: 1 + 1 = 2\n");
+
+        // The assignment of 1 was evicted, its value is still restored
+        calculator.undo();
+        calculator.undo();
+        assert_eq!(calculator.lookup("x"), Ok(1));
+        calculator.redo();
+        assert_eq!(calculator.lookup("x"), Ok(2));
+
+        // Undoing the first assignment of a variable removes it
+        let mut calculator = Calculator::with_max_history(1);
+        calculator.assign("y", 1).unwrap();
+        calculator.undo();
+        assert_eq!(
+            calculator.lookup("y"),
+            Err(CalcError::UnknownVariable("y".to_string()))
+        );
+    }
+
+    #[test]
+    fn variable_errors() {
+        let mut calculator = Calculator::new();
+        assert_eq!(
+            calculator.evaluate("missing + 1"),
+            Err(CalcError::UnknownVariable("missing".to_string()))
+        );
+        assert_eq!(
+            calculator.evaluate("ans"),
+            Err(CalcError::UnknownVariable("ans".to_string()))
+        );
+        assert_eq!(
+            calculator.evaluate("let ans = 2 * 3"),
+            Err(CalcError::InvalidVariableName("ans".to_string()))
+        );
+        assert_eq!(
+            calculator.assign("1st", 1),
+            Err(CalcError::InvalidVariableName("1st".to_string()))
+        );
+        assert!(matches!(calculator.evaluate("let = 4"), Err(CalcError::Parse(_))));
+        assert_eq!(calculator.show_history(), "");
+    }
+
+    #[test]
+    fn memory_registers() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate("MR"), Ok(0));
+        assert_eq!(calculator.evaluate("M+ 10 * 2"), Ok(20));
+        assert_eq!(calculator.memory_subtract(5), Ok(15));
+        assert_eq!(calculator.evaluate("MR * 2"), Ok(30));
+        assert_eq!(calculator.evaluate("M+"), Ok(45));
+        assert_eq!(calculator.memory_add(i64::MAX), Err(CalcError::Overflow));
+        assert_eq!(calculator.memory_recall(), 45);
+        calculator.memory_clear();
+        assert_eq!(calculator.evaluate("MR + 1"), Ok(1));
+
+        let expected = "0: 10 * 2 = 20\n\
+                        1: 0 + 20 = 20\n\
+                        2: M = 20\n\
+                        3: 20 - 5 = 15\n\
+                        4: M = 15\n\
+                        5: 15 * 2 = 30\n\
+                        6: 15 + 30 = 45\n\
+                        7: M = 45\n\
+                        8: 45 + 9223372036854775807 failed: overflow\n\
+                        9: M = 0\n\
+                        10: 0 + 1 = 1\n";
+        assert_eq!(calculator.show_history(), expected);
+        assert_eq!(calculator.variables().count(), 0);
+    }
+
+    #[test]
+    fn memory_in_expressions() {
+        let mut calculator = Calculator::new();
+        // M reads the memory register like MR, zero before anything is stored
+        assert_eq!(calculator.evaluate("M"), Ok(0));
+        calculator.memory_add(10).unwrap();
+        calculator.assign("x", 2).unwrap();
+
+        // Only M+ and M- written as one word change memory
+        assert_eq!(calculator.evaluate("M - 1"), Ok(9));
+        assert_eq!(calculator.evaluate("M + x"), Ok(12));
+        assert_eq!(calculator.evaluate("M+1"), Ok(11));
+        assert_eq!(calculator.evaluate("M * 2"), Ok(20));
+        assert_eq!(calculator.memory_recall(), 10);
+
+        assert_eq!(calculator.evaluate("M- x"), Ok(8));
+        assert_eq!(calculator.evaluate("M"), Ok(8));
+    }
+}
+
+#[cfg(test)]
+mod expression_tests {
+    use crate::calculator::OperationType;
+    use crate::expression::*;
+
+    #[test]
+    fn parse_folds_negative_literals() {
+        assert_eq!(parse("-4"), Ok(Expr::Number(-4)));
+        assert_eq!(
+            parse("-(4)"),
+            Ok(Expr::Negate(Box::new(Expr::Number(4))))
+        );
+    }
+
+    #[test]
+    fn parse_binds_multiplication_tighter() {
+        let expected = Expr::Binary {
+            operation_type: OperationType::Addition,
+            lhs: Box::new(Expr::Number(1)),
+            rhs: Box::new(Expr::Binary {
+                operation_type: OperationType::Multiplication,
+                lhs: Box::new(Expr::Number(2)),
+                rhs: Box::new(Expr::Number(3)),
+            }),
+        };
+        assert_eq!(parse("1 + 2 * 3"), Ok(expected));
+    }
+
+    #[test]
+    fn parse_errors_carry_position_and_reason() {
+        let err = parse("").unwrap_err();
+        assert_eq!(err.reason, ParseErrorReason::EmptyExpression);
+
+        let err = parse("12 + $").unwrap_err();
+        assert_eq!(err.position, 5);
+        assert_eq!(err.reason, ParseErrorReason::UnexpectedCharacter('$'));
+
+        let err = parse("(1 + 2").unwrap_err();
+        assert_eq!(err.position, 0);
+        assert_eq!(err.reason, ParseErrorReason::UnclosedParenthesis);
+
+        let err = parse("1 + 2)").unwrap_err();
+        assert_eq!(err.position, 5);
+        assert_eq!(err.reason, ParseErrorReason::UnexpectedToken(")".to_string()));
+
+        let err = parse("3 *").unwrap_err();
+        assert_eq!(err.position, 3);
+        assert_eq!(err.reason, ParseErrorReason::UnexpectedEnd);
+
+        let err = parse("cbrt(8)").unwrap_err();
+        assert_eq!(err.reason, ParseErrorReason::UnknownFunction("cbrt".to_string()));
+
+        let err = parse("9223372036854775808").unwrap_err();
+        assert_eq!(
+            err.reason,
+            ParseErrorReason::NumberOutOfRange("9223372036854775808".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_as_other_number_types() {
+        assert_eq!(parse_as::<f64>("-1.25"), Ok(Expr::Number(-1.25)));
+        assert_eq!(parse_as::<u64>("18446744073709551615"), Ok(Expr::Number(u64::MAX)));
+
+        let err = parse("1.5 + 1").unwrap_err();
+        assert_eq!(err.position, 0);
+        assert_eq!(err.reason, ParseErrorReason::InvalidNumber("1.5".to_string()));
+
+        let err = parse_as::<f64>("2 * 1.2.3").unwrap_err();
+        assert_eq!(err.reason, ParseErrorReason::InvalidNumber("1.2.3".to_string()));
+
+        let err = parse_as::<u64>("-1").unwrap_err();
+        assert_eq!(err.reason, ParseErrorReason::NumberOutOfRange("-1".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod persistence_tests {
+    use crate::bigint::BigInt;
+    use crate::calculator::*;
+    use crate::persistence::*;
+
+    fn sample_calculator() -> Calculator {
+        let mut calculator = Calculator::new();
+        calculator.addition(-4, 9).unwrap();
+        calculator.power(2, 10).unwrap();
+        calculator.square_root(17).unwrap();
+        calculator
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("task2-{}-{}", std::process::id(), name))
+    }
+
+    // An i64 CSV history file holding the given records
+    fn csv_file(records: &str) -> String {
+        format!(
+            "# calc-history v1 i64\n\
+             sequence,first_num,operator,second_num,policy,result,error,timestamp,variable,macro_id,rounding,first_source,second_source\n\
+             {}",
+            records
+        )
+    }
+
+    #[test]
+    fn json_file_round_trip() {
+        let calculator = sample_calculator();
+        let path = temp_path("history.json");
+        calculator.save_history_json(&path).unwrap();
+
+        let mut restored = Calculator::new();
+        restored.load_history_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.show_history(), calculator.show_history());
+        assert_eq!(restored.history[0].timestamp, calculator.history[0].timestamp);
+    }
+
+    #[test]
+    fn csv_file_round_trip() {
+        let calculator = sample_calculator();
+        let path = temp_path("history.csv");
+        calculator.save_history_csv(&path).unwrap();
+
+        let mut restored = Calculator::new();
+        restored.load_history_csv(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.show_history(), calculator.show_history());
+        assert_eq!(restored.history[2].timestamp, calculator.history[2].timestamp);
+    }
+
+    #[test]
+    fn csv_layout() {
+        let mut ok = Operation::new(5, 3, OperationType::Addition);
+        ok.timestamp = 1_700_000_000;
+        let mut failed = Operation::new(1, 0, OperationType::Division);
+        failed.sequence = 1;
+        failed.timestamp = 1_700_000_001;
+        failed.second_source = Operand::Previous;
+
+        let expected = "# calc-history v1 i64\n\
+                        sequence,first_num,operator,second_num,policy,result,error,timestamp,variable,macro_id,rounding,first_source,second_source\n\
+                        0,5,+,3,checked,8,,1700000000,,,truncate,literal,literal\n\
+                        1,1,/,0,checked,,division_by_zero,1700000001,,,truncate,literal,previous\n";
+        assert_eq!(to_csv(&[ok, failed]), expected);
+    }
+
+    #[test]
+    fn failed_operations_round_trip() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 2).unwrap();
+        calculator.division(1, 0).unwrap_err();
+        calculator.power(2, -1).unwrap_err();
+
+        let from_json = from_json(&to_json(&calculator.history)).unwrap();
+        let from_csv = from_csv(&to_csv(&calculator.history)).unwrap();
+        assert_eq!(from_json[1].result, Err(CalcError::DivisionByZero));
+        assert_eq!(from_csv[2].result, Err(CalcError::NegativeExponent));
+        assert_eq!(from_csv[2].sequence, 2);
+    }
+
+    #[test]
+    fn policies_round_trip() {
+        let mut calculator = Calculator::new();
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        calculator.addition(i64::MAX, 1).unwrap();
+        calculator.set_overflow_policy(OverflowPolicy::Widening);
+        calculator.multiplication(i64::MAX, 2).unwrap_err();
+
+        for history in [
+            from_json(&to_json(&calculator.history)).unwrap(),
+            from_csv(&to_csv(&calculator.history)).unwrap(),
+        ] {
+            assert_eq!(history[0].policy, OverflowPolicy::Wrapping);
+            assert_eq!(history[0].result, Ok(i64::MIN));
+            assert_eq!(
+                history[1].result,
+                Err(CalcError::OutOfRange(i128::from(i64::MAX) * 2))
+            );
+        }
+    }
+
+    #[test]
+    fn load_continues_sequence() {
+        let calculator = sample_calculator();
+        let path = temp_path("continue.json");
+        calculator.save_history_json(&path).unwrap();
+
+        let mut restored = Calculator::new();
+        restored.load_history_json(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        restored.addition(1, 1).unwrap();
+        assert_eq!(restored.history[3].sequence, 3);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let json = r#"{"version": 99, "entries": []}"#;
+        assert!(matches!(
+            from_json(json),
+            Err(HistoryFileError::UnsupportedVersion { found: 99, supported: FORMAT_VERSION })
+        ));
+
+        let csv = "# calc-history v99\nsomething,else\n";
+        assert!(matches!(
+            from_csv(csv),
+            Err(HistoryFileError::UnsupportedVersion { found: 99, .. })
+        ));
+
+        let csv = "# calc-history v0 i64\nsomething,else\n";
+        assert_eq!(
+            from_csv(csv).err().unwrap().to_string(),
+            "history file version 0 is not supported, expected version 1"
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_files() {
+        assert!(matches!(from_json("{not json"), Err(HistoryFileError::Corrupt { .. })));
+        assert!(matches!(
+            from_json(r#"{"operations": []}"#),
+            Err(HistoryFileError::Corrupt { .. })
+        ));
+
+        let json = r#"{"version": 1, "number": "i64", "operations": [
+            {"first_num": 5, "operator": "-", "second_num": 3, "result": 2, "timestamp": 0}
+        ]}"#;
+        assert!(matches!(from_json(json), Err(HistoryFileError::Corrupt { .. })));
+
+        let missing_columns = "# calc-history v1 i64\n\
+                               first_num,operator,second_num,result,timestamp\n\
+                               5,+,3,8,0\n";
+        assert!(matches!(
+            from_csv(missing_columns),
+            Err(HistoryFileError::Corrupt { line: Some(2), .. })
+        ));
+
+        let wrong_result = csv_file("0,5,+,3,checked,9,,0,,,truncate,literal,literal\n");
+        assert!(matches!(
+            from_csv(&wrong_result),
+            Err(HistoryFileError::Corrupt { line: Some(3), .. })
+        ));
+
+        let unknown_operator = csv_file("0,5,$,3,checked,1,,0,,,truncate,literal,literal\n");
+        assert!(matches!(
+            from_csv(&unknown_operator),
+            Err(HistoryFileError::Corrupt { line: Some(3), .. })
+        ));
+
+        let without_policy = csv_file("0,5,+,3,,8,,0,,,truncate,literal,literal\n");
+        assert!(matches!(
+            from_csv(&without_policy),
+            Err(HistoryFileError::Corrupt { line: Some(3), .. })
+        ));
+
+        let malformed = csv_file("0,1,+,1,checked,2,,0,,,truncate,literal,literal\n5,+\n");
+        assert!(matches!(
+            from_csv(&malformed),
+            Err(HistoryFileError::Corrupt { line: Some(4), .. })
+        ));
+
+        let out_of_order = csv_file(
+            "1,1,+,1,checked,2,,0,,,truncate,literal,literal\n\
+             1,2,+,2,checked,4,,0,,,truncate,literal,literal\n",
+        );
+        assert!(matches!(
+            from_csv(&out_of_order),
+            Err(HistoryFileError::Corrupt { line: Some(4), .. })
+        ));
+
+        let unknown_error = csv_file("0,1,/,0,checked,,oops,0,,,truncate,literal,literal\n");
+        assert!(matches!(
+            from_csv(&unknown_error),
+            Err(HistoryFileError::Corrupt { line: Some(3), .. })
+        ));
+    }
+
+    #[test]
+    fn load_respects_history_limit() {
+        let calculator = sample_calculator();
+        let path = temp_path("bounded.csv");
+        calculator.save_history_csv(&path).unwrap();
+
+        let mut restored = Calculator::with_max_history(2);
+        restored.load_history_csv(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.history.len(), 2);
+        assert_eq!(restored.history[0].sequence, 1);
+    }
+
+    #[test]
+    fn failed_load_keeps_history() {
+        let mut calculator = sample_calculator();
+        let path = temp_path("corrupt.json");
+        std::fs::write(&path, "[]").unwrap();
+
+        assert!(calculator.load_history_json(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(calculator.history.len(), 3);
+    }
+
+    #[test]
+    fn other_number_types_round_trip() {
+        let mut calculator = Calculator::<f64>::default();
+        calculator.division(1.0, 3.0).unwrap();
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        calculator.subtraction(f64::INFINITY, f64::INFINITY).unwrap();
+
+        let json = to_json(&calculator.history);
+        assert!(json.contains(r#""number": "f64""#));
+        for history in [
+            from_json_as::<f64>(&json).unwrap(),
+            from_csv_as::<f64>(&to_csv(&calculator.history)).unwrap(),
+        ] {
+            assert_eq!(history[0].result, Ok(1.0 / 3.0));
+            assert!(history[1].result.as_ref().unwrap().is_nan());
+        }
+
+        let mut calculator = Calculator::<BigInt>::default();
+        calculator.evaluate("3 ^ 200").unwrap();
+        let csv = to_csv(&calculator.history);
+        assert!(csv.starts_with("# calc-history v1 bigint\n"));
+        let history = from_csv_as::<BigInt>(&csv).unwrap();
+        assert_eq!(history[0].result, calculator.history[0].result);
+    }
+
+    #[test]
+    fn f64_negative_zero_round_trip() {
+        let mut calculator = Calculator::<f64>::default();
+        assert!(calculator.evaluate("0 * -1").unwrap().is_sign_negative());
+        calculator.addition(2.0, 3.0).unwrap();
+
+        let json = to_json(&calculator.history);
+        assert!(json.contains(r#""result": "-0""#));
+        assert!(json.contains(r#""result": "5""#));
+        for history in [
+            from_json_as::<f64>(&json).unwrap(),
+            from_csv_as::<f64>(&to_csv(&calculator.history)).unwrap(),
+        ] {
+            assert!(history[0].result.as_ref().unwrap().is_sign_negative());
+            assert_eq!(history[1].result, Ok(5.0));
+        }
+    }
+
+    #[test]
+    fn rejects_other_number_types() {
+        let calculator = sample_calculator();
+        assert!(matches!(
+            from_json_as::<u64>(&to_json(&calculator.history)),
+            Err(HistoryFileError::NumberTypeMismatch { expected: "u64", .. })
+        ));
+
+        let mut calculator = Calculator::<f64>::default();
+        calculator.addition(0.5, 1.0).unwrap();
+        let err = from_csv(&to_csv(&calculator.history)).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "history file holds f64 numbers, the calculator uses i64"
+        );
+
+        let csv = "# calc-history v1\nsomething,else\n";
+        assert!(matches!(from_csv(csv), Err(HistoryFileError::Corrupt { line: Some(1), .. })));
+    }
+
+    #[test]
+    fn variables_survive_save_and_load() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("let fee = 5000").unwrap();
+        calculator.evaluate("M+ fee * 2").unwrap();
+        calculator.evaluate("let fee = 7").unwrap();
+
+        for history in [
+            from_json(&to_json(&calculator.history)).unwrap(),
+            from_csv(&to_csv(&calculator.history)).unwrap(),
+        ] {
+            let mut restored = Calculator::new();
+            restored.replace_history(history);
+            assert_eq!(restored.show_history(), calculator.show_history());
+            assert_eq!(restored.evaluate("fee + MR + ans"), Ok(10014));
+        }
+
+        let without_variable = csv_file("0,1,=,0,checked,1,,0,,,truncate,literal,literal\n");
+        assert!(matches!(from_csv(&without_variable), Err(HistoryFileError::Corrupt { line: Some(3), .. })));
+        let reserved = csv_file("0,1,=,0,checked,1,,0,ans,,truncate,literal,literal\n");
+        assert!(matches!(from_csv(&reserved), Err(HistoryFileError::Corrupt { line: Some(3), .. })));
+    }
+}
+
+#[cfg(test)]
+mod macros_tests {
+    use crate::calculator::*;
+    use crate::macros::*;
+    use crate::persistence::*;
+
+    #[test]
+    fn record_from_history_and_replay() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("(5 + 3) * 2 - 1").unwrap();
+        calculator.addition(100, 1).unwrap();
+
+        let recorded = calculator.record_macro("fee", 0, 2).unwrap();
+        assert_eq!(recorded.steps.len(), 3);
+        assert_eq!(recorded.steps[0].first, MacroOperand::Parameter);
+        assert_eq!(recorded.steps[1].first, MacroOperand::Previous);
+        assert_eq!(recorded.steps[1].second, MacroOperand::Value(2));
+
+        assert_eq!(calculator.run_macro("fee", 10), Ok(25));
+        let expected = "0: 5 + 3 = 8\n\
+                        1: 8 * 2 = 16\n\
+                        2: 16 - 1 = 15\n\
+                        3: 100 + 1 = 101\n\
+                        4: 10 + 3 = 13 [macro 4]\n\
+                        5: 13 * 2 = 26 [macro 4]\n\
+                        6: 26 - 1 = 25 [macro 4]\n";
+        assert_eq!(calculator.show_history(), expected);
+
+        assert_eq!(calculator.record_macro("gap", 3, 9).err(), Some(CalcError::InvalidIndex(7)));
+        assert_eq!(
+            calculator.run_macro("missing", 1),
+            Err(CalcError::UnknownMacro("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn live_recording_skips_failures() {
+        let mut calculator = Calculator::new();
+        calculator.addition(1, 1).unwrap();
+        assert_eq!(calculator.stop_recording().err(), Some(CalcError::NotRecording));
+
+        calculator.start_recording("double_sqrt");
+        assert!(calculator.is_recording());
+        calculator.multiplication(8, 2).unwrap();
+        calculator.division(16, 0).unwrap_err();
+        calculator.evaluate("sqrt(ans)").unwrap();
+        calculator.evaluate("let side = ans").unwrap();
+        calculator.addition(7, 7).unwrap();
+        calculator.undo();
+
+        let recorded = calculator.stop_recording().unwrap();
+        assert_eq!(recorded.steps.len(), 3);
+        assert!(!calculator.is_recording());
+
+        assert_eq!(calculator.run_macro("double_sqrt", 50), Ok(10));
+        assert_eq!(calculator.lookup("side"), Ok(10));
+        assert_eq!(calculator.macros().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["double_sqrt"]);
+
+        calculator.start_recording("nothing");
+        calculator.division(1, 0).unwrap_err();
+        assert_eq!(calculator.stop_recording().err(), Some(CalcError::EmptyMacro));
+    }
+
+    #[test]
+    fn equal_values_are_not_chained() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("5 + 3").unwrap();
+        calculator.evaluate("8 * 8").unwrap();
+        calculator.evaluate("ans - 8").unwrap();
+
+        let recorded = calculator.record_macro("square", 0, 2).unwrap();
+        assert_eq!(recorded.steps[1].first, MacroOperand::Value(8));
+        assert_eq!(recorded.steps[1].second, MacroOperand::Value(8));
+        assert_eq!(recorded.steps[2].first, MacroOperand::Previous);
+        assert_eq!(recorded.steps[2].second, MacroOperand::Value(8));
+        assert_eq!(calculator.run_macro("square", 10), Ok(56));
+
+        // The result of an earlier operation is not the previous one
+        calculator.clear_history();
+        calculator.evaluate("(1 + 2) * (3 + 4)").unwrap();
+        let recorded = calculator.record_macro("product", 0, 2).unwrap();
+        assert_eq!(recorded.steps[2].first, MacroOperand::Value(3));
+        assert_eq!(recorded.steps[2].second, MacroOperand::Previous);
+        assert_eq!(calculator.run_macro("product", 2), Ok(21));
+
+        // Sources survive save and load
+        let history = from_csv(&to_csv(&calculator.history)).unwrap();
+        assert_eq!(history[2].first_source, Operand::Literal);
+        assert_eq!(history[2].second_source, Operand::Previous);
+    }
+
+    #[test]
+    fn failing_replay_records_nothing() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("let total = 2 * 2 + 9223372036854775000").unwrap();
+        calculator.record_macro("total", 0, 2).unwrap();
+        let before = calculator.show_history();
+
+        assert_eq!(
+            calculator.run_macro("total", i64::MAX),
+            Err(CalcError::MacroFailed {
+                step: 0,
+                error: Box::new(CalcError::Overflow)
+            })
+        );
+        assert_eq!(
+            calculator.run_macro("total", 1000),
+            Err(CalcError::MacroFailed {
+                step: 1,
+                error: Box::new(CalcError::Overflow)
+            })
+        );
+        assert_eq!(calculator.show_history(), before);
+        assert_eq!(calculator.lookup("total"), Ok(9223372036854775004));
+
+        calculator.run_macro("total", 3).unwrap();
+        assert_eq!(calculator.lookup("total"), Ok(9223372036854775006));
+    }
+
+    #[test]
+    fn macro_ids_survive_save_and_load() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("2 ^ 3 + 1").unwrap();
+        calculator.record_macro("step", 0, 1).unwrap();
+        calculator.run_macro("step", 3).unwrap();
+
+        for history in [
+            from_json(&to_json(&calculator.history)).unwrap(),
+            from_csv(&to_csv(&calculator.history)).unwrap(),
+        ] {
+            let ids: Vec<Option<u64>> = history.iter().map(|op| op.macro_id).collect();
+            assert_eq!(ids, [None, None, Some(2), Some(2)]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use crate::calculator::*;
+    use crate::query::*;
+    use std::collections::BTreeMap;
+
+    fn sample() -> Calculator {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("2 * 3 + 4").unwrap();
+        calculator.division(1, 0).unwrap_err();
+        calculator.evaluate("let fee = 5").unwrap();
+        calculator.multiplication(2, 3).unwrap();
+        calculator.subtraction(1, 100).unwrap();
+        calculator.multiplication(2, 3).unwrap();
+        for (index, op) in calculator.history.iter_mut().enumerate() {
+            op.timestamp = 1_700_000_000 + 10 * index as u64;
+        }
+        calculator
+    }
+
+    #[test]
+    fn filters_combine() {
+        let calculator = sample();
+        let sequences = |query: HistoryQuery| {
+            query.operations().iter().map(|op| op.sequence).collect::<Vec<_>>()
+        };
+
+        assert_eq!(calculator.query().count(), 7);
+        let multiplications = calculator.query().operation_type(OperationType::Multiplication);
+        assert_eq!(sequences(multiplications), [0, 4, 6]);
+        assert_eq!(sequences(calculator.query().view(HistoryView::Failed)), [2]);
+        assert_eq!(sequences(calculator.query().sequences(2..=4)), [2, 3, 4]);
+        assert_eq!(sequences(calculator.query().timestamps(1_700_000_050..)), [5, 6]);
+        assert_eq!(sequences(calculator.query().operands(|x, y| x > y)), [1, 2, 3]);
+        assert_eq!(sequences(calculator.query().result(|value| *value < 6)), [3, 5]);
+        assert_eq!(
+            sequences(
+                calculator
+                    .query()
+                    .operation_type(OperationType::Multiplication)
+                    .sequences(1..)
+                    .result(|value| *value == 6)
+            ),
+            [4, 6]
+        );
+        assert!(calculator.query().sequences(100..).operations().is_empty());
+    }
+
+    #[test]
+    fn statistics() {
+        let calculator = sample();
+        let stats = calculator.stats();
+        assert_eq!(stats.count, 7);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(
+            stats.per_operator,
+            BTreeMap::from([
+                (OperationType::Addition, 1),
+                (OperationType::Subtraction, 1),
+                (OperationType::Multiplication, 3),
+                (OperationType::Division, 1),
+                (OperationType::Assignment, 1),
+            ])
+        );
+        assert_eq!(stats.sum, Ok(6 + 10 + 5 + 6 - 99 + 6));
+        assert_eq!(stats.min, Some(-99));
+        assert_eq!(stats.max, Some(10));
+        assert_eq!(
+            stats.most_repeated,
+            Some(Repetition {
+                operation_type: OperationType::Multiplication,
+                first_num: 2,
+                second_num: 3,
+                count: 3,
+            })
+        );
+
+        let empty = calculator.query().view(HistoryView::Failed).stats();
+        assert_eq!(empty.sum, Ok(0));
+        assert_eq!((empty.min, empty.max, empty.most_repeated), (None, None, None));
+
+        let mut calculator = Calculator::new();
+        calculator.addition(i64::MAX, 0).unwrap();
+        calculator.addition(1, 0).unwrap();
+        assert_eq!(calculator.stats().sum, Err(CalcError::Overflow));
+        assert_eq!(calculator.stats().most_repeated, None);
+    }
+
+    #[test]
+    fn statistics_skip_nan() {
+        let mut calculator = Calculator::<f64>::default();
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        calculator.evaluate("0 / 0").unwrap();
+        calculator.evaluate("2.5 * 2").unwrap();
+        calculator.evaluate("-1.5 + 0").unwrap();
+        let stats = calculator.stats();
+        assert_eq!((stats.min, stats.max), (Some(-1.5), Some(5.0)));
+        assert_eq!(stats.sum, Err(CalcError::NotANumber));
+    }
+}
+
+#[cfg(test)]
+mod format_tests {
+    use crate::calculator::*;
+    use crate::format::*;
+    use crate::numeric::Rounding;
+
+    fn sample() -> Calculator {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("5 + 3").unwrap();
+        calculator.evaluate("sqrt(16)").unwrap();
+        calculator.division(1, 0).unwrap_err();
+        calculator.evaluate("let max_fee = 2 ^ 3").unwrap();
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        calculator.set_rounding(Rounding::Floor);
+        calculator.multiplication(7, 6).unwrap();
+        calculator.record_macro("double", 5, 5).unwrap();
+        calculator.run_macro("double", 2).unwrap();
+        calculator
+    }
+
+    #[test]
+    fn default_is_show_history() {
+        let calculator = sample();
+        let expected = "0: 5 + 3 = 8\n\
+                        1: sqrt(16) = 4\n\
+                        2: 1 / 0 failed: division by zero\n\
+                        3: 2 ^ 3 = 8\n\
+                        4: max_fee = 8\n\
+                        5: 7 * 6 = 42 (wrapping, floor)\n\
+                        6: 2 * 6 = 12 (wrapping, floor) [macro 6]\n";
+        assert_eq!(calculator.show_history(), expected);
+        assert_eq!(calculator.show_history_as("default").unwrap(), expected);
+        assert_eq!(calculator.format_history(&DefaultFormatter, HistoryView::All), expected);
+    }
+
+    #[test]
+    fn built_in_formats() {
+        let calculator = sample();
+        let rpn = "0: 5 3 + = 8\n\
+                   1: 16 sqrt = 4\n\
+                   2: 1 0 / failed: division by zero\n\
+                   3: 2 3 ^ = 8\n\
+                   4: 8 max_fee = = 8\n\
+                   5: 7 6 * = 42 (wrapping, floor)\n\
+                   6: 2 6 * = 12 (wrapping, floor) [macro 6]\n";
+        assert_eq!(calculator.show_history_as("rpn").unwrap(), rpn);
+
+        let markdown = "| # | Operation | Result | Notes |\n\
+                        | ---: | --- | --- | --- |\n\
+                        | 0 | `5 + 3` | 8 |  |\n\
+                        | 1 | `sqrt(16)` | 4 |  |\n\
+                        | 2 | `1 / 0` | failed: division by zero |  |\n\
+                        | 3 | `2 ^ 3` | 8 |  |\n\
+                        | 4 | `max_fee = 8` | 8 |  |\n\
+                        | 5 | `7 * 6` | 42 | wrapping, floor |\n\
+                        | 6 | `2 * 6` | 12 | wrapping, floor, macro 6 |\n";
+        assert_eq!(calculator.show_history_as("markdown").unwrap(), markdown);
+
+        let latex = "\\begin{tabular}{rlll}\n\
+                     \\# & Operation & Result & Notes \\\\\n\
+                     \\hline\n\
+                     0 & $5 + 3$ & $8$ &  \\\\\n\
+                     1 & $\\sqrt{16}$ & $4$ &  \\\\\n\
+                     2 & $1 \\div 0$ & failed: division by zero &  \\\\\n\
+                     3 & ${2}^{3}$ & $8$ &  \\\\\n\
+                     4 & $\\mathit{max\\_fee} = 8$ & $8$ &  \\\\\n\
+                     5 & $7 \\times 6$ & $42$ & wrapping, floor \\\\\n\
+                     6 & $2 \\times 6$ & $12$ & wrapping, floor, macro 6 \\\\\n\
+                     \\end{tabular}\n";
+        assert_eq!(calculator.show_history_as("latex").unwrap(), latex);
+
+        let empty = Calculator::new();
+        assert_eq!(empty.show_history_as("rpn").unwrap(), "");
+        assert_eq!(
+            empty.show_history_as("markdown").unwrap(),
+            "| # | Operation | Result | Notes |\n| ---: | --- | --- | --- |\n"
+        );
+    }
+
+    struct Csv;
+
+    impl HistoryFormatter<i64> for Csv {
+        fn name(&self) -> &str {
+            "csv"
+        }
+
+        fn header(&self) -> String {
+            "operation,result\n".to_string()
+        }
+
+        fn entry(&self, op: &Operation) -> String {
+            let sign = op.operation_type.get_sign();
+            let operation = format!("{} {} {}", op.first_num, sign, op.second_num);
+            format!("{},{:?}", operation, op.result.as_ref().ok())
+        }
+    }
+
+    #[test]
+    fn registered_formatters() {
+        let mut calculator = sample();
+        assert_eq!(
+            calculator.show_history_as("csv"),
+            Err(CalcError::UnknownFormatter("csv".to_string()))
+        );
+        calculator.register_formatter(Box::new(Csv));
+        assert_eq!(
+            calculator.formatter_names().collect::<Vec<_>>(),
+            ["binary", "csv", "default", "hex", "latex", "markdown", "rpn"]
+        );
+        let csv = calculator.show_history_as("csv").unwrap();
+        assert!(csv.starts_with("operation,result\n5 + 3,Some(8)\n"));
+        assert_eq!(
+            calculator.format_history(&Csv, HistoryView::Failed),
+            "operation,result\n1 / 0,None\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod rpn_tests {
+    use crate::calculator::*;
+    use crate::expression::ParseErrorReason;
+
+    #[test]
+    fn operators_record_history() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate_rpn("5 3 + 2 *"), Ok(Some(16)));
+        assert_eq!(calculator.evaluate_rpn("16 sqrt -"), Ok(Some(12)));
+        assert_eq!(calculator.stack(), [12]);
+        assert_eq!(
+            calculator.show_history(),
+            "0: 5 + 3 = 8\n1: 8 * 2 = 16\n2: sqrt(16) = 4\n3: 16 - 4 = 12\n"
+        );
+
+        assert_eq!(calculator.repeat(1), Ok(16));
+        assert_eq!(calculator.stack(), [12]);
+        assert_eq!(calculator.evaluate_rpn("ans 2 ^"), Ok(Some(256)));
+        assert_eq!(calculator.evaluate_rpn(""), Ok(Some(256)));
+    }
+
+    #[test]
+    fn stack_commands() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate_rpn("1 2 swap"), Ok(Some(1)));
+        assert_eq!(calculator.stack(), [2, 1]);
+        assert_eq!(calculator.evaluate_rpn("dup *"), Ok(Some(1)));
+        assert_eq!(calculator.evaluate_rpn("-"), Ok(Some(1)));
+        assert_eq!(calculator.evaluate_rpn("drop"), Ok(None));
+        assert_eq!(
+            calculator.evaluate_rpn("drop"),
+            Err(CalcError::StackUnderflow { needed: 1, found: 0 })
+        );
+
+        calculator.push(7);
+        calculator.push(9);
+        assert_eq!(calculator.pop(), Ok(9));
+        assert_eq!(calculator.swap(), Err(CalcError::StackUnderflow { needed: 2, found: 1 }));
+        calculator.clear_stack();
+        assert!(calculator.stack().is_empty());
+    }
+
+    #[test]
+    fn errors_keep_operands() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("let rate = 4").unwrap();
+        assert_eq!(calculator.evaluate_rpn("10 0 / 5 +"), Err(CalcError::DivisionByZero));
+        assert_eq!(calculator.stack(), [10, 0]);
+        assert_eq!(calculator.history.back().unwrap().result, Err(CalcError::DivisionByZero));
+
+        assert_eq!(calculator.evaluate_rpn("drop rate *"), Ok(Some(40)));
+        assert_eq!(
+            calculator.evaluate_rpn("+"),
+            Err(CalcError::StackUnderflow { needed: 2, found: 1 })
+        );
+        assert_eq!(calculator.history.len(), 3);
+
+        assert_eq!(
+            calculator.evaluate_rpn("1 fee"),
+            Err(CalcError::UnknownVariable("fee".to_string()))
+        );
+        match calculator.evaluate_rpn("2 1.5") {
+            Err(CalcError::Parse(err)) => {
+                assert_eq!(err.position, 2);
+                assert_eq!(err.reason, ParseErrorReason::InvalidNumber("1.5".to_string()));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(calculator.evaluate_rpn("="), Err(CalcError::Parse(_))));
+        assert_eq!(calculator.stack(), [40, 1, 2]);
+    }
+}
+
+#[cfg(test)]
+mod bitwise_tests {
+    use crate::bigint::BigInt;
+    use crate::calculator::*;
+    use crate::expression::*;
+    use crate::fixed::SolAmount;
+    use crate::numeric::{Number, Radix};
+    use crate::persistence::*;
+
+    #[test]
+    fn operators() {
+        let compute = |operation_type: OperationType, x: i64, y: i64| {
+            operation_type.compute(&x, &y, OverflowPolicy::Checked)
+        };
+        assert_eq!(compute(OperationType::And, 0b1100, 0b1010), Ok(0b1000));
+        assert_eq!(compute(OperationType::Or, 0b1100, 0b1010), Ok(0b1110));
+        assert_eq!(compute(OperationType::Xor, 0b1100, 0b1010), Ok(0b0110));
+        assert_eq!(compute(OperationType::Not, 0, 0), Ok(-1));
+        assert_eq!(compute(OperationType::ShiftLeft, 1, 63), Ok(i64::MIN));
+        assert_eq!(compute(OperationType::ShiftRight, -16, 2), Ok(-4));
+        assert_eq!(compute(OperationType::RotateLeft, i64::MIN, 1), Ok(1));
+        assert_eq!(compute(OperationType::RotateRight, 1, 1), Ok(i64::MIN));
+        assert_eq!(
+            OperationType::Not.compute(&0u64, &0, OverflowPolicy::Checked),
+            Ok(u64::MAX)
+        );
+        assert_eq!(
+            OperationType::ShiftRight.compute(&u64::MAX, &60, OverflowPolicy::Checked),
+            Ok(0xf)
+        );
+    }
+
+    #[test]
+    fn shifts_are_checked() {
+        let shift = |operation_type: OperationType, x: i64, y: i64, policy| {
+            operation_type.compute(&x, &y, policy)
+        };
+        for operation_type in [OperationType::ShiftLeft, OperationType::RotateRight] {
+            assert_eq!(
+                shift(operation_type.clone(), 1, 64, OverflowPolicy::Checked),
+                Err(CalcError::InvalidShift { amount: 64, bits: 64 })
+            );
+            assert_eq!(
+                shift(operation_type, 1, 64, OverflowPolicy::Widening),
+                Err(CalcError::InvalidShift { amount: 64, bits: 64 })
+            );
+        }
+        assert_eq!(
+            shift(OperationType::ShiftRight, 1, -1, OverflowPolicy::Wrapping),
+            Err(CalcError::InvalidShift { amount: -1, bits: 64 })
+        );
+        assert_eq!(
+            OperationType::ShiftLeft.compute(&1u64, &u64::MAX, OverflowPolicy::Checked),
+            Err(CalcError::InvalidShift { amount: u64::MAX as i128, bits: 64 })
+        );
+
+        assert_eq!(shift(OperationType::ShiftLeft, 3, 65, OverflowPolicy::Wrapping), Ok(6));
+        assert_eq!(shift(OperationType::RotateLeft, 3, 64, OverflowPolicy::Wrapping), Ok(3));
+        assert_eq!(shift(OperationType::ShiftLeft, 3, 64, OverflowPolicy::Saturating), Ok(0));
+        assert_eq!(shift(OperationType::ShiftRight, -3, 99, OverflowPolicy::Saturating), Ok(-1));
+        assert_eq!(shift(OperationType::RotateLeft, 3, 65, OverflowPolicy::Saturating), Ok(6));
+        assert_eq!(shift(OperationType::ShiftLeft, 1, 63, OverflowPolicy::Widening), Ok(i64::MIN));
+        assert_eq!(
+            OperationType::ShiftLeft.compute(&1i128, &127, OverflowPolicy::Checked),
+            Ok(i128::MIN)
+        );
+    }
+
+    #[test]
+    fn only_integers_have_bits() {
+        let unsupported = |number| CalcError::UnsupportedOperation {
+            operator: "&".to_string(),
+            number,
+        };
+        assert_eq!(
+            OperationType::And.compute(&1.0, &1.0, OverflowPolicy::Checked),
+            Err(unsupported("f64"))
+        );
+        let big = BigInt::from(1i64);
+        assert_eq!(
+            OperationType::And.compute(&big, &big, OverflowPolicy::Checked),
+            Err(unsupported("bigint"))
+        );
+        let one = SolAmount::from_base_units(1);
+        assert_eq!(
+            OperationType::And.compute(&one, &one, OverflowPolicy::Checked),
+            Err(unsupported("sol"))
+        );
+        assert_eq!(unsupported("f64").to_string(), "operator & is not supported for f64 numbers");
+        assert_eq!(1.5.to_radix(Radix::Hex), None);
+    }
+
+    #[test]
+    fn parses_bitwise_expressions() {
+        let mut calculator = Calculator::new();
+        assert_eq!(calculator.evaluate("1 << 2 + 1"), Ok(8));
+        assert_eq!(calculator.evaluate("6 & 3 | 8"), Ok(10));
+        assert_eq!(calculator.evaluate("1 | 6 xor 3 & 5"), Ok(7));
+        assert_eq!(calculator.evaluate("~0xff & 0x1ff"), Ok(0x100));
+        assert_eq!(calculator.evaluate("-0b101 >> 1"), Ok(-3));
+        assert_eq!(calculator.evaluate("1 rotr 1 rotl 1"), Ok(1));
+        assert_eq!(calculator.evaluate("0x7fffffffffffffff"), Ok(i64::MAX));
+        assert_eq!(
+            calculator.evaluate("1 << 64"),
+            Err(CalcError::InvalidShift { amount: 64, bits: 64 })
+        );
+
+        let reason = |input: &str| parse(input).unwrap_err().reason;
+        assert_eq!(
+            reason("0x8000000000000000"),
+            ParseErrorReason::NumberOutOfRange("0x8000000000000000".to_string())
+        );
+        assert_eq!(reason("0b102"), ParseErrorReason::InvalidNumber("0b102".to_string()));
+        assert_eq!(reason("0x"), ParseErrorReason::InvalidNumber("0x".to_string()));
+        assert_eq!(reason("1 < 2"), ParseErrorReason::UnexpectedCharacter('<'));
+        assert_eq!(
+            parse_as::<f64>("0xff").unwrap_err().reason,
+            ParseErrorReason::InvalidNumber("0xff".to_string())
+        );
+        assert_eq!(
+            calculator.assign("rotl", 1),
+            Err(CalcError::InvalidVariableName("rotl".to_string()))
+        );
+    }
+
+    #[test]
+    fn hex_and_binary_history() {
+        let mut calculator = Calculator::new();
+        calculator.evaluate("0xf0 | 0x0f").unwrap();
+        calculator.evaluate("~0").unwrap();
+        calculator.evaluate("1 << 99").unwrap_err();
+        let decimal = "0: 240 | 15 = 255\n\
+                       1: ~(0) = -1\n\
+                       2: 1 << 99 failed: shift by 99 is out of range for 64 bit numbers\n";
+        assert_eq!(calculator.show_history(), decimal);
+        let hex = "0: 0xf0 | 0xf = 0xff\n\
+                   1: ~(0x0) = -0x1\n\
+                   2: 0x1 << 0x63 failed: shift by 99 is out of range for 64 bit numbers\n";
+        assert_eq!(calculator.show_history_as("hex").unwrap(), hex);
+        let binary = calculator.show_history_as("binary").unwrap();
+        assert!(binary.starts_with("0: 0b11110000 | 0b1111 = 0b11111111\n"));
+
+        for history in [
+            from_json(&to_json(&calculator.history)).unwrap(),
+            from_csv(&to_csv(&calculator.history)).unwrap(),
+        ] {
+            assert_eq!(history[0].operation_type, OperationType::Or);
+            assert_eq!(history[1].result, Ok(-1));
+            assert_eq!(history[2].result, Err(CalcError::InvalidShift { amount: 99, bits: 64 }));
+        }
+    }
+
+    #[test]
+    fn radix_output_parses_back() {
+        for radix in [Radix::Hex, Radix::Binary] {
+            for value in [0, -4, 255, i64::MIN, i64::MAX] {
+                let literal = value.to_radix(radix).unwrap();
+                assert_eq!(i64::parse_literal(&literal), Some(value), "{}", literal);
+            }
+            let literal = i128::MIN.to_radix(radix).unwrap();
+            assert_eq!(i128::parse_literal(&literal), Some(i128::MIN));
+            let literal = u64::MAX.to_radix(radix).unwrap();
+            assert_eq!(u64::parse_literal(&literal), Some(u64::MAX));
+        }
+
+        let mut calculator = Calculator::new();
+        calculator.evaluate("~3").unwrap();
+        assert_eq!(calculator.show_history_as("hex").unwrap(), "0: ~(0x3) = -0x4\n");
+        assert_eq!(calculator.evaluate("-0x4 + 0b100"), Ok(0));
+    }
+}
+
+#[cfg(test)]
+mod fixed_tests {
+    use crate::calculator::*;
+    use crate::expression::*;
+    use crate::fixed::*;
+    use crate::numeric::{Number, Rounding};
+    use crate::persistence::*;
+
+    fn sol(literal: &str) -> SolAmount {
+        SolAmount::parse_literal(literal).unwrap()
+    }
+
+    #[test]
+    fn parses_and_displays_amounts() {
+        assert_eq!(sol("1.5").base_units(), 1_500_000_000);
+        assert_eq!(sol("-0.000000001").base_units(), -1);
+        assert_eq!(sol("3").to_string(), "3");
+        assert_eq!(sol("0.25").to_string(), "0.25");
+        assert_eq!(SolAmount::parse_literal("0.0000000001"), None);
+        assert_eq!(SolAmount::parse_literal("1."), None);
+        assert_eq!(SolAmount::parse_literal("9223372037"), None);
+        assert_eq!(SolAmount::from_whole_units(2), Some(sol("2")));
+
+        assert_eq!(sol("1.5").format_in(Unit::Whole), "1.5 SOL");
+        assert_eq!(sol("1.5").format_in(Unit::Base), "1500000000 lamports");
+    }
+
+    #[test]
+    fn parses_units_in_expressions() {
+        let mut calculator = Calculator::<SolAmount>::default();
+        assert_eq!(
+            calculator.evaluate("1.5 SOL + 1500000000 lamports"),
+            Ok(sol("3"))
+        );
+        assert_eq!(calculator.evaluate("-1 lamport * 2"), Ok(sol("-0.000000002")));
+        assert_eq!(calculator.evaluate("-2 sol ^ 2"), Ok(sol("-4")));
+
+        let err = parse_as::<SolAmount>("0.5 lamports").unwrap_err();
+        assert_eq!(err.reason, ParseErrorReason::InvalidNumber("0.5 lamports".to_string()));
+        // Other number types have no units
+        assert!(parse_as::<i64>("1 SOL").is_err());
+    }
+
+    #[test]
+    fn division_rounding_modes() {
+        let cases = [
+            (Rounding::Truncate, "0.666666666", "-0.666666666"),
+            (Rounding::Floor, "0.666666666", "-0.666666667"),
+            (Rounding::Ceil, "0.666666667", "-0.666666666"),
+            (Rounding::HalfUp, "0.666666667", "-0.666666667"),
+            (Rounding::HalfEven, "0.666666667", "-0.666666667"),
+        ];
+        for (rounding, positive, negative) in cases {
+            let mut calculator = Calculator::<SolAmount>::default();
+            calculator.set_rounding(rounding);
+            assert_eq!(calculator.evaluate("2 / 3"), Ok(sol(positive)));
+            assert_eq!(calculator.evaluate("-2 / 3"), Ok(sol(negative)));
+        }
+
+        assert_eq!(Rounding::HalfUp.divide(5, 2), Some(3));
+        assert_eq!(Rounding::HalfEven.divide(5, 2), Some(2));
+        assert_eq!(Rounding::HalfEven.divide(-7, 2), Some(-4));
+        assert_eq!(Rounding::Floor.divide(7, -2), Some(-4));
+        assert_eq!(Rounding::Truncate.divide(1, 0), None);
+    }
+
+    #[test]
+    fn arithmetic_follows_overflow_policies() {
+        let max = SolAmount::from_base_units(i64::MAX);
+        let two = sol("2");
+        let multiply = |policy| OperationType::Multiplication.compute(&max, &two, policy);
+        assert_eq!(multiply(OverflowPolicy::Checked), Err(CalcError::Overflow));
+        assert_eq!(multiply(OverflowPolicy::Saturating), Ok(max));
+        assert_eq!(multiply(OverflowPolicy::Wrapping), Ok(SolAmount::from_base_units(-2)));
+        assert_eq!(
+            multiply(OverflowPolicy::Widening),
+            Err(CalcError::OutOfRange(2 * i64::MAX as i128))
+        );
+        assert_eq!(
+            OperationType::Addition.compute(&max, &two, OverflowPolicy::Saturating),
+            Ok(max)
+        );
+
+        let mut calculator = Calculator::<SolAmount>::default();
+        assert_eq!(calculator.evaluate("1.5 * 1.5"), Ok(sol("2.25")));
+        assert_eq!(calculator.evaluate("sqrt(2)"), Ok(sol("1.414213562")));
+        assert_eq!(calculator.evaluate("1 / 0"), Err(CalcError::DivisionByZero));
+        assert_eq!(calculator.evaluate("4 ^ 0.5"), Err(CalcError::NonIntegerExponent));
+        assert_eq!(calculator.evaluate("10 ^ 10"), Err(CalcError::Overflow));
+    }
+
+    #[test]
+    fn history_in_either_unit() {
+        let mut calculator = Calculator::<SolAmount>::default();
+        calculator.evaluate("1.5 SOL + 2 lamports").unwrap();
+        calculator.set_rounding(Rounding::HalfEven);
+        calculator.evaluate("1 / 3").unwrap();
+
+        assert_eq!(
+            calculator.show_history(),
+            "0: 1.5 + 0.000000002 = 1.500000002
+1: 1 / 3 = 0.333333333 (half-even)
+"
+        );
+        assert_eq!(
+            calculator.show_history_in(Unit::Base),
+            "0: 1500000000 lamports + 2 lamports = 1500000002 lamports
+\
+             1: 1000000000 lamports / 3000000000 lamports = 333333333 lamports (half-even)\n"
+        );
+        let whole = calculator.show_history_in(Unit::Whole);
+        assert!(whole.starts_with("0: 1.5 SOL + 0.000000002 SOL = 1.500000002 SOL\n"));
+    }
+
+    #[test]
+    fn rounding_survives_save_and_load() {
+        let mut calculator = Calculator::<SolAmount>::default();
+        calculator.set_rounding(Rounding::Ceil);
+        calculator.evaluate("let fee = 1 / 3").unwrap();
+        calculator.set_overflow_policy(OverflowPolicy::Wrapping);
+        calculator.evaluate("fee * 2").unwrap();
+
+        let csv = to_csv(&calculator.history);
+        assert!(csv.starts_with("# calc-history v1 sol\n"));
+        for history in [
+            from_json_as::<SolAmount>(&to_json(&calculator.history)).unwrap(),
+            from_csv_as::<SolAmount>(&csv).unwrap(),
+        ] {
+            assert_eq!(history[0].rounding, Rounding::Ceil);
+            assert_eq!(history[2].result, Ok(sol("0.666666668")));
+            assert_eq!(history[2].policy, OverflowPolicy::Wrapping);
+        }
+
+        // A result that only matches with another rounding is rejected
+        let tampered = csv.replace(",ceil", ",floor");
+        assert!(matches!(
+            from_csv_as::<SolAmount>(&tampered),
+            Err(HistoryFileError::Corrupt { line: Some(3), .. })
+        ));
+        assert!(matches!(
+            from_csv_as::<i64>(&csv),
+            Err(HistoryFileError::NumberTypeMismatch { .. })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod repl_tests {
+    use crate::fixed::SolAmount;
+    use crate::repl::{run, run_as};
+
+    fn session(input: &str) -> String {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, false).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn evaluates_expressions_and_commands() {
+        let input = "5 + 3\n(-4 + 9) * 3\nrepeat 0\nhistory\n";
+        let expected = "8\n15\n8\n0: 5 + 3 = 8\n1: -4 + 9 = 5\n2: 5 * 3 = 15\n3: 5 + 3 = 8\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn reports_errors_and_keeps_going() {
+        let input = "1 / 0\n2 +\nrepeat 7\nrepeat x\n2 * 2\n";
+        let expected = "error: division by zero\n\
+                        error: parse error at position 3: unexpected end of expression\n\
+                        error: no operation at index 7\n\
+                        error: usage: repeat <index>\n\
+                        4\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn undo_and_redo_commands() {
+        let input = "1 + 1\n2 + 2\nundo\nredo\nredo\nhistory\n";
+        let expected = "2\n4\nundone\nredone\nerror: nothing to redo\n0: 1 + 1 = 2\n1: 2 + 2 = 4\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn policy_command() {
+        let input = "policy\npolicy wrapping\n9223372036854775807 + 1\npolicy sideways\n";
+        let expected = "checked\n\
+                        overflow policy: wrapping\n\
+                        -9223372036854775808\n\
+                        error: unknown overflow policy 'sideways'\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn undo_clear_and_quit() {
+        let input = "# script comment\n\n1 + 1\n2 + 2\nundo\nhistory\nclear\nhistory\nundo\nquit\n3 + 3\n";
+        let expected = "2\n4\nundone\n0: 1 + 1 = 2\nhistory cleared\nerror: nothing to undo\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn variables_and_memory_commands() {
+        let input = "let rate = 3\nrate * 4\nM+\nM- 2\nvars\nlet 9 = 1\n";
+        let expected = "3\n12\n12\n10\nrate = 3\nmemory = 10\n\
+                        error: parse error at position 4: unexpected token '9'\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn command_names_are_not_variables() {
+        let input = "let history = 1\nlet undo=2\nlet histories = 3\nhistory\n";
+        let expected = "error: invalid variable name 'history', it is a command\n\
+                        error: invalid variable name 'undo', it is a command\n\
+                        3\n0: histories = 3\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn macro_commands() {
+        let input = "record add_fee\n1 + 5\nans * 2\nstop\nrun add_fee 10\nmacro twice 1 1\nrun twice 4\nrun add_fee x\nstop\n";
+        let expected = "recording macro 'add_fee'\n6\n12\n\
+                        macro 'add_fee' saved with 2 steps\n30\n\
+                        macro 'twice' saved with 1 steps\n8\n\
+                        error: usage: run <name> <value>\n\
+                        error: no macro is being recorded\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn history_formats() {
+        let input = "5 + 3\nhistory rpn\nhistory yaml\nhistory rpn markdown\n";
+        let expected = "8\n0: 5 3 + = 8\n\
+                        error: unknown history format 'yaml'\n\
+                        error: usage: history [format]\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn rpn_commands() {
+        let input = "rpn 5 3 + 2\nstack\nrpn *\nrpn drop\nstack\nrpn swap\nhistory\n";
+        let expected = "2\n8 2\n16\nstack is empty\nstack is empty\n\
+                        error: stack holds 0 values, 2 needed\n\
+                        0: 5 + 3 = 8\n1: 8 * 2 = 16\n";
+        assert_eq!(session(input), expected);
+    }
+
+    #[test]
+    fn sol_session() {
+        let input = "1 SOL / 3\nrounding\nrounding half-up\n2 / 3\nrounding up\nhistory\n";
+        let expected = "0.333333333\ntruncate\nrounding: half-up\n0.666666667\n\
+                        error: unknown rounding 'up'\n\
+                        0: 1 / 3 = 0.333333333\n1: 2 / 3 = 0.666666667 (half-up)\n";
+        let mut output = Vec::new();
+        run_as::<SolAmount, _, _>(input.as_bytes(), &mut output, false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
     }
 
     #[test]
-    fn calculator_workflow_integration() {
-        let mut calculator = Calculator::new();
-        
-        // Perform some operations
-        calculator.addition(10, 5);
-        calculator.multiplication(3, 4);
-        
-        // Repeat first operation
-        calculator.repeat(0);
-        
-        // Check complete history
-        let history = calculator.show_history();
-        let expected = "0: 10 + 5 = 15\n1: 3 * 4 = 12\n2: 10 + 5 = 15\n";
-        assert_eq!(history, expected);
-        
-        // Clear and verify
-        calculator.clear_history();
-        assert_eq!(calculator.show_history(), "");
-        
-        // Add new operation after clearing
-        calculator.subtraction(20, 7);
-        assert_eq!(calculator.show_history(), "0: 20 - 7 = 13\n");
+    fn sol_history_in_units() {
+        let input = "1.5 SOL + 2 lamports
+history base-units
+history whole-units
+";
+        let expected = "1.500000002
+\
+                        0: 1500000000 lamports + 2 lamports = 1500000002 lamports
+\
+                        0: 1.5 SOL + 0.000000002 SOL = 1.500000002 SOL
+";
+        let mut output = Vec::new();
+        run_as::<SolAmount, _, _>(input.as_bytes(), &mut output, false).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+
+        assert_eq!(session("history base-units
+"), "error: unknown history format 'base-units'
+");
     }
 }
 
@@ -497,4 +2288,999 @@ mod shapes_tests {
             }
         }
     }
+
+    #[test]
+    fn triangle_area_and_perimeter() {
+        let mut triangle = Triangle::new(3.0, 4.0, 5.0).unwrap();
+
+        assert_approx_eq!(f64, triangle.area(), 6.0, MARGIN);
+        assert_approx_eq!(f64, triangle.perimeter(), 12.0, MARGIN);
+
+        let res = triangle.set_sides(2.0, 2.0, 2.0);
+        assert!(res.is_ok());
+        assert_approx_eq!(f64, triangle.area(), 3.0_f64.sqrt(), MARGIN);
+
+        // a flat triangle has no area
+        let triangle = Triangle::new(1.0, 2.0, 3.0).unwrap();
+        assert_eq!(triangle.area(), 0.0);
+    }
+
+    #[test]
+    fn triangle_wrong_input() {
+        assert_eq!(Triangle::new(-3.0, 4.0, 5.0).err(), Some(Error::InvalidSide(-3.0)));
+        assert_eq!(Triangle::new(1.0, 2.0, 4.0).err(), Some(Error::InvalidTriangle(1.0, 2.0, 4.0)));
+
+        let mut triangle = Triangle::new(3.0, 4.0, 5.0).unwrap();
+        let res = triangle.set_sides(10.0, 4.0, 5.0);
+
+        assert_eq!(res, Err(Error::InvalidTriangle(10.0, 4.0, 5.0)));
+        assert_eq!(triangle.get_sides(), (3.0, 4.0, 5.0));
+    }
+
+    #[test]
+    fn ellipse_area_and_perimeter() {
+        let mut ellipse = Ellipse::new(5.0, 3.0).unwrap();
+
+        let (a, b) = (5.0, 3.0);
+        assert_approx_eq!(f64, ellipse.area(), a * b * std::f64::consts::PI, MARGIN);
+        // reference value of the complete elliptic integral
+        assert_approx_eq!(f64, ellipse.perimeter(), 25.526998863398, epsilon = 1e-6);
+
+        // a circle is an ellipse with equal semi-axes
+        let r_in: f64 = 4.0;
+        assert!(ellipse.set_semi_axis_x(r_in).is_ok());
+        assert!(ellipse.set_semi_axis_y(r_in).is_ok());
+        assert_approx_eq!(f64, ellipse.perimeter(), perimeter!(r_in), MARGIN);
+        assert_approx_eq!(f64, ellipse.area(), area!(r_in), MARGIN);
+
+        assert_eq!(ellipse.set_semi_axis_y(-1.0), Err(Error::InvalidSemiAxis(-1.0)));
+        assert_eq!(Ellipse::new(-1.0, 2.0).err(), Some(Error::InvalidSemiAxis(-1.0)));
+    }
+
+    #[test]
+    fn regular_polygon_area_and_perimeter() {
+        let side: f64 = 7.0;
+        let mut square = RegularPolygon::new(4, side).unwrap();
+
+        assert_approx_eq!(f64, square.area(), area!(side, side), MARGIN);
+        assert_approx_eq!(f64, square.perimeter(), perimeter!(side, side), MARGIN);
+
+        assert!(square.set_sides(6).is_ok());
+        let reference_area = 3.0 * 3.0_f64.sqrt() / 2.0 * side * side;
+        assert_approx_eq!(f64, square.area(), reference_area, epsilon = 1e-9);
+        assert_approx_eq!(f64, square.perimeter(), 6.0 * side, MARGIN);
+
+        assert_eq!(square.set_sides(2), Err(Error::InvalidSideCount(2)));
+        assert_eq!(square.set_side_length(-1.0), Err(Error::InvalidSideLength(-1.0)));
+        assert_eq!(RegularPolygon::new(5, -1.0).err(), Some(Error::InvalidSideLength(-1.0)));
+    }
+
+    #[test]
+    fn polygon_area_and_perimeter() {
+        // an L shape, clockwise
+        let vertices = vec![(0.0, 0.0), (0.0, 2.0), (1.0, 2.0), (1.0, 1.0), (2.0, 1.0), (2.0, 0.0)];
+        let polygon = Polygon::new(vertices).unwrap();
+
+        assert_approx_eq!(f64, polygon.area(), 3.0, MARGIN);
+        assert_approx_eq!(f64, polygon.perimeter(), 8.0, MARGIN);
+
+        let mut polygon = Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)]).unwrap();
+        assert_approx_eq!(f64, polygon.area(), 6.0, MARGIN);
+        assert_approx_eq!(f64, polygon.perimeter(), 12.0, MARGIN);
+
+        let res = polygon.set_vertices(vec![(0.0, 0.0), (1.0, 0.0)]);
+        assert_eq!(res, Err(Error::TooFewVertices(2)));
+        assert_eq!(polygon.get_vertices().len(), 3);
+    }
+
+    #[test]
+    fn polygon_self_intersecting() {
+        // a bow tie
+        let res = Polygon::new(vec![(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]);
+        assert_eq!(res.err(), Some(Error::SelfIntersecting));
+
+        // a vertex touching another edge
+        let res = Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (2.0, 0.0), (2.0, 3.0)]);
+        assert_eq!(res.err(), Some(Error::SelfIntersecting));
+
+        // an edge folding back onto the previous one
+        let res = Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (2.0, 0.0)]);
+        assert_eq!(res.err(), Some(Error::SelfIntersecting));
+
+        // a repeated vertex
+        let res = Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (4.0, 0.0), (0.0, 3.0)]);
+        assert_eq!(res.err(), Some(Error::SelfIntersecting));
+
+        // collinear neighbouring edges continuing in the same direction are fine
+        let res = Polygon::new(vec![(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (0.0, 3.0)]);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn non_finite_dimensions() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(Rectangle::new(value, 1.0), Err(Error::InvalidWidth(_))));
+            assert!(matches!(Rectangle::new(1.0, value), Err(Error::InvalidHeight(_))));
+            assert!(matches!(Circle::new(value), Err(Error::InvalidRadius(_))));
+            assert!(matches!(Triangle::new(1.0, value, 1.0), Err(Error::InvalidSide(_))));
+            assert!(matches!(Ellipse::new(1.0, value), Err(Error::InvalidSemiAxis(_))));
+            let res = RegularPolygon::new(3, value);
+            assert!(matches!(res, Err(Error::InvalidSideLength(_))));
+            let res = Polygon::new(vec![(0.0, 0.0), (value, 0.0), (0.0, 1.0)]);
+            assert!(matches!(res, Err(Error::InvalidVertex(_, 0.0))));
+
+            let mut rectangle = Rectangle::new(2.0, 3.0).unwrap();
+            assert!(rectangle.set_width(value).is_err());
+            assert!(rectangle.set_height(value).is_err());
+            assert_eq!(rectangle.area(), 6.0);
+
+            let mut circle = Circle::new(2.0).unwrap();
+            assert!(circle.set_radius(value).is_err());
+            assert_eq!(circle.get_radius(), 2.0);
+        }
+        assert_eq!(Circle::new(f64::INFINITY).err(), Some(Error::InvalidRadius(f64::INFINITY)));
+    }
+
+    #[test]
+    fn error_messages() {
+        fn area_of(width: f64, height: f64) -> Result<f64, Box<dyn std::error::Error>> {
+            Ok(Rectangle::new(width, height)?.area())
+        }
+
+        let err = area_of(2.0, -3.5).unwrap_err();
+        assert_eq!(err.to_string(), "invalid height -3.5");
+
+        let err = Circle::new(f64::NAN).err().unwrap();
+        assert_eq!(err.to_string(), "invalid radius NaN");
+
+        let err = Triangle::new(1.0, 2.0, 4.0).err().unwrap();
+        assert_eq!(err.to_string(), "sides 1, 2 and 4 do not form a triangle");
+
+        let err = Polygon::new(vec![(0.0, 0.0), (1.0, 1.0)]).err().unwrap();
+        assert_eq!(err.to_string(), "a polygon needs at least 3 vertices, got 2");
+    }
+}
+
+#[cfg(test)]
+mod placement_tests {
+    use crate::placement::*;
+    use crate::shapes::*;
+    use crate::units::{Length, LengthUnit};
+    use float_cmp::assert_approx_eq;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
+
+    fn rectangle(width: f64, height: f64, x: f64, y: f64) -> Positioned<Rectangle> {
+        Positioned::at(Rectangle::new(width, height).unwrap(), x, y)
+    }
+
+    fn circle(radius: f64, x: f64, y: f64) -> Positioned<Circle> {
+        Positioned::at(Circle::new(radius).unwrap(), x, y)
+    }
+
+    fn ellipse(a: f64, b: f64, x: f64, y: f64, rotation: f64) -> Positioned<Ellipse> {
+        let placement = Placement {
+            position: (x, y),
+            rotation,
+        };
+        Positioned::new(Ellipse::new(a, b).unwrap(), placement)
+    }
+
+    fn assert_box(bounds: BoundingBox, expected: (f64, f64, f64, f64)) {
+        assert_approx_eq!(f64, bounds.min_x, expected.0, epsilon = 1e-9);
+        assert_approx_eq!(f64, bounds.min_y, expected.1, epsilon = 1e-9);
+        assert_approx_eq!(f64, bounds.max_x, expected.2, epsilon = 1e-9);
+        assert_approx_eq!(f64, bounds.max_y, expected.3, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        let mut square = rectangle(2.0, 2.0, 5.0, 1.0);
+        assert_box(square.bounding_box(), (4.0, 0.0, 6.0, 2.0));
+
+        square.rotate(FRAC_PI_4);
+        assert_box(square.bounding_box(), (5.0 - SQRT_2, 1.0 - SQRT_2, 5.0 + SQRT_2, 1.0 + SQRT_2));
+
+        let ellipse = ellipse(3.0, 1.0, 0.0, 0.0, FRAC_PI_2);
+        assert_box(ellipse.bounding_box(), (-1.0, -3.0, 1.0, 3.0));
+
+        // the bottom side of a hexagon is horizontal
+        let hexagon = Positioned::at(RegularPolygon::new(6, 2.0).unwrap(), 0.0, 0.0);
+        let apothem = 3.0_f64.sqrt();
+        assert_box(hexagon.bounding_box(), (-2.0, -apothem, 2.0, apothem));
+
+        // side c lies along the x axis, the centroid is at the position
+        let triangle = Positioned::at(Triangle::new(4.0, 3.0, 5.0).unwrap(), 0.0, 0.0);
+        let bounds = triangle.bounding_box();
+        assert_approx_eq!(f64, bounds.max_x - bounds.min_x, 5.0, epsilon = 1e-9);
+        assert_approx_eq!(f64, bounds.max_y - bounds.min_y, 2.4, epsilon = 1e-9);
+        assert_approx_eq!(f64, bounds.min_y, -0.8, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn contains_point() {
+        let mut rectangle = rectangle(4.0, 2.0, 0.0, 0.0);
+        assert!(rectangle.contains_point((1.9, 0.9)));
+        assert!(rectangle.contains_point((2.0, 1.0)));
+        assert!(!rectangle.contains_point((0.0, 1.5)));
+
+        rectangle.rotate(FRAC_PI_2);
+        assert!(rectangle.contains_point((0.0, 1.5)));
+        assert!(!rectangle.contains_point((1.5, 0.0)));
+
+        rectangle.translate(10.0, 0.0);
+        assert!(rectangle.contains_point((10.0, 1.5)));
+        assert!(!rectangle.contains_point((0.0, 0.0)));
+
+        let ellipse = ellipse(3.0, 1.0, 1.0, 1.0, FRAC_PI_2);
+        assert!(ellipse.contains_point((1.0, 3.9)));
+        assert!(!ellipse.contains_point((3.9, 1.0)));
+
+        // the notch of an L shape is outside
+        let vertices = vec![(0.0, 0.0), (0.0, 2.0), (1.0, 2.0), (1.0, 1.0), (2.0, 1.0), (2.0, 0.0)];
+        let polygon = Positioned::at(Polygon::new(vertices).unwrap(), 0.0, 0.0);
+        assert!(polygon.contains_point((0.5, 1.5)));
+        assert!(polygon.contains_point((1.5, 0.5)));
+        assert!(!polygon.contains_point((1.5, 1.5)));
+    }
+
+    #[test]
+    fn polygons_intersect() {
+        let first = rectangle(2.0, 2.0, 0.0, 0.0);
+        assert!(first.intersects(&rectangle(2.0, 2.0, 1.5, 1.5)).unwrap());
+        // touching sides
+        assert!(first.intersects(&rectangle(2.0, 2.0, 2.0, 0.0)).unwrap());
+        assert!(!first.intersects(&rectangle(2.0, 2.0, 2.1, 0.0)).unwrap());
+        // one inside the other
+        assert!(first.intersects(&rectangle(0.5, 0.5, 0.0, 0.0)).unwrap());
+        assert!(rectangle(0.5, 0.5, 0.0, 0.0).intersects(&first).unwrap());
+
+        // overlapping a corner, turned into a diamond it clears it
+        let mut diamond = rectangle(2.0, 2.0, 1.9, 1.9);
+        assert!(first.intersects(&diamond).unwrap());
+        diamond.rotate(FRAC_PI_4);
+        assert!(!first.intersects(&diamond).unwrap());
+    }
+
+    #[test]
+    fn ellipses_intersect() {
+        let first = circle(1.0, 0.0, 0.0);
+        assert!(first.intersects(&circle(1.0, 2.0, 0.0)).unwrap());
+        assert!(!first.intersects(&circle(1.0, 2.01, 0.0)).unwrap());
+        assert!(first.intersects(&circle(3.0, 1.0, 0.0)).unwrap());
+
+        // a cross
+        let horizontal = ellipse(5.0, 1.0, 0.0, 0.0, 0.0);
+        let vertical = ellipse(5.0, 1.0, 0.0, 0.0, FRAC_PI_2);
+        assert!(horizontal.intersects(&vertical).unwrap());
+
+        // side by side, then turned towards each other
+        let mut left = ellipse(1.0, 3.0, -1.5, 0.0, 0.0);
+        let right = ellipse(1.0, 3.0, 1.5, 0.0, 0.0);
+        assert!(!left.intersects(&right).unwrap());
+        left.rotate(FRAC_PI_2);
+        assert!(left.intersects(&right).unwrap());
+    }
+
+    #[test]
+    fn mixed_shapes_intersect() {
+        // the corner of the square is 1.13 away from the center of the circle
+        let circle = circle(1.0, 0.0, 0.0);
+        let square = rectangle(2.0, 2.0, 1.8, 1.8);
+        assert!(!circle.intersects(&square).unwrap());
+        assert!(!square.intersects(&circle).unwrap());
+        assert!(circle.intersects(&rectangle(2.0, 2.0, 1.6, 1.6)).unwrap());
+
+        // a small square inside a large ellipse
+        let ellipse = ellipse(10.0, 5.0, 0.0, 0.0, 0.3);
+        assert!(ellipse.intersects(&rectangle(1.0, 1.0, 0.0, 0.0)).unwrap());
+
+        // a zero radius circle is a point
+        let point = Positioned::at(Circle::new(0.0).unwrap(), 1.0, 1.0);
+        assert!(point.intersects(&square).unwrap());
+        assert!(!point.intersects(&rectangle(1.0, 1.0, 0.0, 0.0)).unwrap());
+
+        let widgets: Vec<Box<dyn Placed>> = vec![
+            Box::new(rectangle(4.0, 1.0, 0.0, 0.0)),
+            Box::new(ellipse),
+            Box::new(Positioned::at(Triangle::new(3.0, 4.0, 5.0).unwrap(), 20.0, 0.0)),
+        ];
+        let hits: Vec<usize> = (0..widgets.len())
+            .filter(|&i| widgets[i].contains_point((1.9, 0.4)))
+            .collect();
+        assert_eq!(hits, vec![0, 1]);
+        assert!(!widgets[2].intersects(widgets[1].as_ref()).unwrap());
+    }
+
+    #[test]
+    fn units_must_match() {
+        let side = Length::new(100.0, LengthUnit::Centimetre);
+        let square = Rectangle::from_lengths(side, side).unwrap();
+        let square = Positioned::at(square, 0.0, 0.0);
+        let circle = circle(1.0, 0.0, 0.0);
+        assert_eq!(
+            square.intersects(&circle),
+            Err(Error::MixedUnits(LengthUnit::Centimetre, LengthUnit::Metre))
+        );
+        assert_eq!(
+            circle.intersects(&square),
+            Err(Error::MixedUnits(LengthUnit::Metre, LengthUnit::Centimetre))
+        );
+
+        let radius = Length::new(1.0, LengthUnit::Centimetre);
+        let small = Positioned::at(Circle::from_length(radius).unwrap(), 60.0, 0.0);
+        assert_eq!(square.intersects(&small), Ok(false));
+    }
+}
+
+#[cfg(test)]
+mod scene_tests {
+    use crate::scene::*;
+    use crate::shapes::*;
+    use float_cmp::assert_approx_eq;
+
+    fn scene() -> Scene {
+        let mut scene = Scene::new();
+        scene.push(Box::new(Rectangle::new(7.0, 3.0).unwrap()));
+        scene.push(Box::new(Circle::new(1.0).unwrap()));
+        scene.push(Box::new(Triangle::new(3.0, 4.0, 5.0).unwrap()));
+        scene.push(Box::new(Rectangle::new(2.0, 2.0).unwrap()));
+        scene.push(Box::new(RegularPolygon::new(4, 3.0).unwrap()));
+        scene
+    }
+
+    fn areas(shapes: &[&dyn SceneShape]) -> Vec<f64> {
+        shapes.iter().map(|shape| shape.area()).collect()
+    }
+
+    #[test]
+    fn totals() {
+        let scene = scene();
+        let pi = std::f64::consts::PI;
+
+        assert_eq!(scene.len(), 5);
+        assert_approx_eq!(f64, scene.total_area(), 21.0 + pi + 6.0 + 4.0 + 9.0, epsilon = 1e-9);
+        let reference_perimeter = 20.0 + 2.0 * pi + 12.0 + 8.0 + 12.0;
+        assert_approx_eq!(f64, scene.total_perimeter(), reference_perimeter, epsilon = 1e-9);
+
+        assert!(Scene::new().is_empty());
+        assert_eq!(Scene::new().total_area(), 0.0);
+        assert!(Scene::new().largest().is_none());
+    }
+
+    #[test]
+    fn sort_and_filter_by_area() {
+        let mut scene = scene();
+
+        let medium = scene.filter_by_area(4.0..9.0);
+        assert_eq!(areas(&medium), vec![6.0, 4.0]);
+        assert_eq!(scene.filter_by_area(10.0..).len(), 1);
+
+        scene.sort_by_area();
+        let names: Vec<&str> = scene.shapes().iter().map(|shape| shape.name()).collect();
+        assert_eq!(
+            names,
+            vec!["circle", "rectangle", "triangle", "regular_polygon", "rectangle"]
+        );
+    }
+
+    #[test]
+    fn largest_and_smallest() {
+        let mut scene = scene();
+        assert_eq!(scene.largest().unwrap().area(), 21.0);
+        assert_eq!(scene.smallest().unwrap().kind(), ShapeKind::Circle);
+
+        // the first one wins a tie
+        scene.push(Box::new(Rectangle::new(3.0, 7.0).unwrap()));
+        assert_eq!(scene.largest().unwrap().to_record(), scene.shapes()[0].to_record());
+    }
+
+    #[test]
+    fn group_by_kind() {
+        let scene = scene();
+        let groups = scene.group_by_kind();
+
+        let kinds: Vec<ShapeKind> = groups.keys().copied().collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ShapeKind::Rectangle,
+                ShapeKind::Circle,
+                ShapeKind::Triangle,
+                ShapeKind::RegularPolygon
+            ]
+        );
+        assert_eq!(areas(&groups[&ShapeKind::Rectangle]), vec![21.0, 4.0]);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut scene = scene();
+        scene.push(Box::new(Ellipse::new(2.0, 1.0).unwrap()));
+        scene.push(Box::new(Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)]).unwrap()));
+
+        let json = serde_json::to_string(&scene).unwrap();
+        let first = r#"{"shapes":[{"kind":"rectangle","width":7.0,"height":3.0,"unit":"m"}"#;
+        assert!(json.starts_with(first));
+        assert!(json.contains(r#"{"kind":"regular_polygon","sides":4,"side_length":3.0}"#));
+
+        let restored: Scene = serde_json::from_str(&json).unwrap();
+        let records = |scene: &Scene| -> Vec<ShapeRecord> {
+            scene.shapes().iter().map(|shape| shape.to_record()).collect()
+        };
+        assert_eq!(records(&restored), records(&scene));
+        assert_eq!(format!("{:?}", restored), format!("{:?}", scene));
+    }
+
+    #[test]
+    fn json_validates_shapes() {
+        let json = r#"{"shapes":[{"kind":"circle","radius":-2.0,"unit":"m"}]}"#;
+        let err = serde_json::from_str::<Scene>(json).unwrap_err();
+        assert!(err.to_string().starts_with("invalid radius -2"));
+
+        let json = r#"{"shapes":[{"kind":"hexagon","side":1.0}]}"#;
+        assert!(serde_json::from_str::<Scene>(json).is_err());
+    }
+}
+
+#[cfg(test)]
+mod units_tests {
+    use crate::scene::*;
+    use crate::shapes::*;
+    use crate::units::LengthUnit::*;
+    use crate::units::*;
+    use float_cmp::assert_approx_eq;
+
+    #[test]
+    fn conversions() {
+        let length = Length::new(12.0, Inch);
+        assert_approx_eq!(f64, length.to(Foot).value(), 1.0, epsilon = 1e-12);
+        assert_approx_eq!(f64, length.to(Centimetre).value(), 30.48, epsilon = 1e-12);
+        assert_eq!(length.to(Inch), length);
+
+        let area = Area::new(1.0, Metre);
+        assert_approx_eq!(f64, area.to(Centimetre).value(), 10_000.0, epsilon = 1e-9);
+        assert_approx_eq!(f64, Area::new(1.0, Foot).to(Inch).value(), 144.0, epsilon = 1e-9);
+
+        assert_eq!(LengthUnit::from_name("mm"), Some(Millimetre));
+        assert_eq!(LengthUnit::from_name("yd"), None);
+        assert_eq!(Length::new(2.5, Centimetre).to_string(), "2.5 cm");
+        assert_eq!(Area::new(6.0, Foot).to_string(), "6 ft²");
+    }
+
+    #[test]
+    fn mixing_units_is_checked() {
+        let inch = Length::new(1.0, Inch);
+        let centimetres = Length::new(3.0, Centimetre);
+
+        assert_eq!(inch.checked_add(centimetres), Err(Error::MixedUnits(Inch, Centimetre)));
+        assert_eq!(inch.partial_cmp(&centimetres), None);
+        assert_eq!((inch < centimetres, inch > centimetres), (false, false));
+        assert!(inch.to(Centimetre) < centimetres);
+
+        let res = Rectangle::from_lengths(inch, centimetres);
+        assert_eq!(res.err(), Some(Error::MixedUnits(Inch, Centimetre)));
+        assert_eq!(
+            Error::MixedUnits(Inch, Centimetre).to_string(),
+            "cannot mix lengths in in and cm"
+        );
+    }
+
+    #[test]
+    fn shapes_with_units() {
+        let width = Length::new(7.0, Inch);
+        let height = Length::new(3.0, Inch);
+        let rectangle = Rectangle::from_lengths(width, height).unwrap();
+
+        assert_eq!(rectangle.width(), width);
+        assert_eq!(rectangle.get_height(), 3.0);
+        assert_eq!(rectangle.measured_area(), Area::new(21.0, Inch));
+        assert_eq!(rectangle.measured_perimeter(), Length::new(20.0, Inch));
+
+        let converted = rectangle.to_unit(Centimetre).unwrap();
+        assert_eq!(converted.get_unit(), Centimetre);
+        let reference_area = rectangle.measured_area().to(Centimetre).value();
+        assert_approx_eq!(f64, converted.area(), reference_area, epsilon = 1e-9);
+
+        let circle = Circle::from_length(Length::new(-1.0, Foot));
+        assert_eq!(circle.err(), Some(Error::InvalidRadius(-1.0)));
+
+        // plain numbers are metres
+        assert_eq!(Circle::new(2.0).unwrap().radius(), Length::new(2.0, Metre));
+        assert_eq!(Triangle::new(3.0, 4.0, 5.0).unwrap().unit(), Metre);
+    }
+
+    #[test]
+    fn scene_totals_with_units() {
+        let mut scene = Scene::new();
+        let side = Length::new(2.0, Foot);
+        let square = Rectangle::from_lengths(side, side).unwrap();
+        scene.push(Box::new(square.clone()));
+        scene.push(Box::new(square));
+        assert_eq!(scene.total_measured_area(), Ok(Area::new(8.0, Foot)));
+
+        scene.push(Box::new(Circle::from_length(Length::new(1.0, Millimetre)).unwrap()));
+        assert_eq!(scene.total_measured_area(), Err(Error::MixedUnits(Foot, Millimetre)));
+        assert!(scene.total_measured_perimeter().is_err());
+
+        // the unit survives a round trip and is required
+        let json = serde_json::to_string(&scene).unwrap();
+        let restored: Scene = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.shapes()[2].unit(), Millimetre);
+
+        let json = r#"{"shapes":[{"kind":"circle","radius":1.0}]}"#;
+        let err = serde_json::from_str::<Scene>(json).unwrap_err();
+        assert!(err.to_string().starts_with("missing field `unit`"));
+    }
+
+    #[test]
+    fn scene_queries_convert_units() {
+        let mut scene = Scene::new();
+        let side = Length::new(100.0, Centimetre);
+        scene.push(Box::new(Rectangle::from_lengths(side, side).unwrap()));
+        scene.push(Box::new(Rectangle::new(2.0, 2.0).unwrap()));
+        let foot = Length::new(1.0, Foot);
+        scene.push(Box::new(Rectangle::from_lengths(foot, foot).unwrap()));
+
+        let foot_area = 0.3048 * 0.3048;
+        assert_approx_eq!(f64, scene.total_area(), 5.0 + foot_area, epsilon = 1e-9);
+        assert_approx_eq!(f64, scene.total_perimeter(), 12.0 + 4.0 * 0.3048, epsilon = 1e-9);
+        assert_eq!(scene.largest().unwrap().unit(), Metre);
+        assert_eq!(scene.smallest().unwrap().unit(), Foot);
+        let medium = scene.filter_by_area(0.5..2.0);
+        assert_eq!(medium.len(), 1);
+        assert_eq!(medium[0].unit(), Centimetre);
+
+        scene.sort_by_area();
+        let units: Vec<LengthUnit> = scene.shapes().iter().map(|shape| shape.unit()).collect();
+        assert_eq!(units, vec![Foot, Centimetre, Metre]);
+    }
+}
+
+#[cfg(test)]
+mod shape_dsl_tests {
+    use crate::shape_dsl::*;
+    use crate::shapes::Error;
+    use crate::units::LengthUnit;
+
+    fn report(input: &str) -> (String, bool) {
+        let mut output = Vec::new();
+        let valid = run(input.as_bytes(), &mut output).unwrap();
+        (String::from_utf8(output).unwrap(), valid)
+    }
+
+    #[test]
+    fn parses_shapes() {
+        let shape = parse_line("rect w=7 h=3").unwrap().unwrap();
+        assert_eq!(shape.area(), 21.0);
+
+        let shape = parse_line("  circle radius=2 unit=cm ").unwrap().unwrap();
+        assert_eq!(shape.name(), "circle");
+        assert_eq!(shape.unit(), LengthUnit::Centimetre);
+
+        // keys in any order, long and short forms mixed
+        let shape = parse_line("rectangle height=3 w=7").unwrap().unwrap();
+        assert_eq!(shape.perimeter(), 20.0);
+
+        assert!(parse_line("").unwrap().is_none());
+        assert!(parse_line("# a comment").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        let err = |line: &str| parse_line(line).err().unwrap();
+
+        assert_eq!(err("square s=1"), ShapeLineError::UnknownShape("square".to_string()));
+        assert_eq!(err("rect w=7 3"), ShapeLineError::Malformed("3".to_string()));
+        assert_eq!(err("circle d=2"), ShapeLineError::UnknownKey("d".to_string()));
+        assert_eq!(err("circle r=1 radius=2"), ShapeLineError::DuplicateKey("radius".to_string()));
+        assert_eq!(err("rect w=7"), ShapeLineError::MissingKey("height"));
+        assert_eq!(err("circle r=x"), ShapeLineError::InvalidNumber("x".to_string()));
+        assert_eq!(err("circle r=1 unit=yd"), ShapeLineError::UnknownUnit("yd".to_string()));
+        assert_eq!(err("rect w=7 h=-3"), ShapeLineError::Shape(Error::InvalidHeight(-3.0)));
+        assert!(matches!(err("circle r=NaN"), ShapeLineError::Shape(Error::InvalidRadius(_))));
+    }
+
+    #[test]
+    fn numbers_lines() {
+        let input = "# shapes\nrect w=7 h=3\n\ncircle r=-1\ncircle r=1\n";
+        let parsed = parse(input);
+
+        let lines: Vec<usize> = parsed
+            .iter()
+            .map(|entry| match entry {
+                Ok((line, _)) => *line,
+                Err(err) => err.line,
+            })
+            .collect();
+        assert_eq!(lines, vec![2, 4, 5]);
+        let err = parsed[1].as_ref().err().unwrap();
+        assert_eq!(err.to_string(), "line 4: invalid radius -1");
+    }
+
+    #[test]
+    fn reports_metrics_and_totals() {
+        let (output, valid) = report("rect w=7 h=3\nrect w=2 h=2\n");
+        let expected = "line 1: rectangle area 21 m² perimeter 20 m\n\
+                        line 2: rectangle area 4 m² perimeter 8 m\n\
+                        total: 2 shapes, area 25 m² perimeter 28 m\n";
+        assert_eq!(output, expected);
+        assert!(valid);
+
+        let (output, valid) = report("rect w=1 h=1 unit=in\nrect w=x h=1\nrect w=1 h=1 unit=cm\n");
+        let expected = "line 1: rectangle area 1 in² perimeter 4 in\n\
+                        error: line 2: invalid number 'x'\n\
+                        line 3: rectangle area 1 cm² perimeter 4 cm\n\
+                        total: 2 shapes, cannot mix lengths in in and cm\n";
+        assert_eq!(output, expected);
+        assert!(!valid);
+
+        let (output, valid) = report("");
+        assert_eq!(output, "total: 0 shapes, area 0 m² perimeter 0 m\n");
+        assert!(valid);
+    }
+}
+
+#[cfg(test)]
+mod svg_tests {
+    use crate::placement::*;
+    use crate::shapes::*;
+    use crate::svg::*;
+    use crate::units::{Length, LengthUnit};
+    use std::f64::consts::FRAC_PI_4;
+    use std::path::PathBuf;
+
+    // Compare with tests/golden/<name>.svg; UPDATE_GOLDEN=1 rewrites the file instead
+    fn assert_golden(name: &str, document: &SvgDocument) {
+        let rendered = document.render();
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+            .iter()
+            .collect();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &rendered).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(rendered, expected, "output differs from {}", path.display());
+    }
+
+    #[test]
+    fn single_rectangle() {
+        let mut document = SvgDocument::new();
+        document.add_shape(Rectangle::new(7.0, 3.0).unwrap(), Style::default()).unwrap();
+        assert_golden("rectangle.svg", &document);
+    }
+
+    #[test]
+    fn styled_and_labelled_shapes() {
+        let mut document = SvgDocument::new();
+        document.set_margin(0.5);
+
+        let circle = Positioned::at(Circle::new(2.0).unwrap(), 6.0, 2.0);
+        let style = Style {
+            stroke: "#1f77b4".to_string(),
+            stroke_width: 0.05,
+            fill: "lightblue".to_string(),
+            label: Label::AreaAndPerimeter,
+        };
+        document.add(circle, style).unwrap();
+
+        let mut square = Positioned::at(Rectangle::new(2.0, 2.0).unwrap(), 0.0, 0.0);
+        square.rotate(FRAC_PI_4);
+        let style = Style {
+            fill: "orange".to_string(),
+            label: Label::Area,
+            ..Style::default()
+        };
+        document.add(square, style).unwrap();
+
+        let ellipse = Placement {
+            position: (3.0, -2.0),
+            rotation: FRAC_PI_4,
+        };
+        let ellipse = Positioned::new(Ellipse::new(2.0, 0.5).unwrap(), ellipse);
+        let style = Style {
+            label: Label::Perimeter,
+            ..Style::default()
+        };
+        document.add(ellipse, style).unwrap();
+
+        let vertices = vec![(-1.0, -3.0), (1.0, -3.0), (0.0, -4.5)];
+        document.add_shape(Polygon::new(vertices).unwrap(), Style::default()).unwrap();
+        assert_golden("scene.svg", &document);
+    }
+
+    #[test]
+    fn view_box() {
+        let mut document = SvgDocument::new();
+        let view_box = document.view_box();
+        assert_eq!((view_box.min_x, view_box.max_y), (-1.0, 1.0));
+
+        let circle = Positioned::at(Circle::new(1.0).unwrap(), 10.0, 5.0);
+        document.add(circle, Style::default()).unwrap();
+        document.add_shape(Rectangle::new(2.0, 2.0).unwrap(), Style::default()).unwrap();
+        let view_box = document.view_box();
+        assert_eq!(
+            (view_box.min_x, view_box.min_y, view_box.max_x, view_box.max_y),
+            (-2.0, -2.0, 12.0, 7.0)
+        );
+        assert!(document.render().contains("viewBox=\"-2 -7 14 9\""));
+    }
+
+    #[test]
+    fn shapes_share_one_unit() {
+        let mut document = SvgDocument::new();
+        assert_eq!(document.unit(), None);
+        let side = Length::new(2.0, LengthUnit::Centimetre);
+        let square = Rectangle::from_lengths(side, side).unwrap();
+        document.add_shape(square, Style::default()).unwrap();
+        assert_eq!(document.unit(), Some(LengthUnit::Centimetre));
+
+        let circle = Circle::new(1.0).unwrap();
+        assert_eq!(
+            document.add_shape(circle.clone(), Style::default()),
+            Err(Error::MixedUnits(LengthUnit::Centimetre, LengthUnit::Metre))
+        );
+        let circle = circle.to_unit(LengthUnit::Centimetre).unwrap();
+        document.add(Positioned::at(circle, 200.0, 0.0), Style::default()).unwrap();
+        assert!(document.render().contains("<circle cx=\"200\" cy=\"0\" r=\"100\""));
+    }
+
+    #[test]
+    fn escapes_styles() {
+        let mut document = SvgDocument::new();
+        let style = Style {
+            fill: "url(\"#a\") & <b>".to_string(),
+            ..Style::default()
+        };
+        document.add_shape(Circle::new(1.0).unwrap(), style).unwrap();
+
+        let mut output = Vec::new();
+        document.write_to(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("fill=\"url(&quot;#a&quot;) &amp; &lt;b&gt;\""));
+    }
+}
+
+#[cfg(test)]
+mod transform_tests {
+    use crate::shapes::*;
+    use crate::transform::*;
+    use crate::units::{Length, LengthUnit};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn scale_shapes() {
+        let rectangle = Rectangle::new(7.0, 3.0).unwrap().scale(2.0).unwrap();
+        assert_eq!((rectangle.get_width(), rectangle.get_height()), (14.0, 6.0));
+
+        let radius = Length::new(1.5, LengthUnit::Inch);
+        let circle = Circle::from_length(radius).unwrap().scale(2.0).unwrap();
+        assert_eq!(circle.radius(), Length::new(3.0, LengthUnit::Inch));
+
+        let triangle = Triangle::new(3.0, 4.0, 5.0).unwrap().scale(0.5).unwrap();
+        assert_eq!(triangle.get_sides(), (1.5, 2.0, 2.5));
+
+        let hexagon = RegularPolygon::new(6, 2.0).unwrap().scale(3.0).unwrap();
+        assert_eq!(hexagon.get_side_length(), 6.0);
+
+        let polygon = Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)]).unwrap();
+        let doubled = polygon.scale(2.0).unwrap();
+        assert_eq!(doubled.get_vertices(), &[(0.0, 0.0), (8.0, 0.0), (0.0, 6.0)]);
+    }
+
+    #[test]
+    fn scale_by_negative_factor() {
+        let rectangle = Rectangle::new(7.0, 3.0).unwrap();
+        assert_eq!(rectangle.scale(-1.0).err(), Some(Error::InvalidWidth(-7.0)));
+
+        let circle = Circle::new(2.0).unwrap();
+        assert_eq!(circle.scale(-0.5).err(), Some(Error::InvalidRadius(-1.0)));
+        assert!(matches!(circle.scale(f64::NAN), Err(Error::InvalidRadius(_))));
+        assert!(matches!(circle.scale(f64::MAX), Err(Error::InvalidRadius(_))));
+
+        let ellipse = Ellipse::new(2.0, 1.0).unwrap();
+        assert_eq!(ellipse.scale(-1.0).err(), Some(Error::InvalidSemiAxis(-2.0)));
+        let triangle = Triangle::new(3.0, 4.0, 5.0).unwrap();
+        assert_eq!(triangle.scale(-1.0).err(), Some(Error::InvalidSide(-3.0)));
+
+        let polygon = Polygon::new(vec![(0.0, 0.0), (4.0, 0.0), (0.0, 3.0)]).unwrap();
+        assert_eq!(polygon.scale(-1.0).err(), Some(Error::InvalidScaleFactor(-1.0)));
+        assert_eq!(polygon.scale(0.0).err(), Some(Error::InvalidScaleFactor(0.0)));
+        assert!(matches!(polygon.scale(f64::NAN), Err(Error::InvalidScaleFactor(_))));
+        assert_eq!(
+            Error::InvalidScaleFactor(-1.0).to_string(),
+            "invalid scale factor -1"
+        );
+    }
+
+    #[test]
+    fn similarity() {
+        let rectangle = Rectangle::new(4.0, 2.0).unwrap();
+        assert!(rectangle.is_similar_to(&Rectangle::new(1.0, 2.0).unwrap()));
+        assert!(rectangle.is_similar_to(&rectangle.scale(0.1).unwrap()));
+        assert!(!rectangle.is_similar_to(&Rectangle::new(3.0, 2.0).unwrap()));
+
+        let triangle = Triangle::new(3.0, 4.0, 5.0).unwrap();
+        assert!(triangle.is_similar_to(&Triangle::new(10.0, 6.0, 8.0).unwrap()));
+        assert!(!triangle.is_similar_to(&Triangle::new(3.0, 3.0, 3.0).unwrap()));
+
+        let ellipse = Ellipse::new(3.0, 1.0).unwrap();
+        assert!(ellipse.is_similar_to(&Ellipse::new(0.5, 1.5).unwrap()));
+
+        let square = RegularPolygon::new(4, 1.0).unwrap();
+        assert!(square.is_similar_to(&RegularPolygon::new(4, 9.0).unwrap()));
+        assert!(!square.is_similar_to(&RegularPolygon::new(5, 1.0).unwrap()));
+
+        assert!(Circle::new(1.0).unwrap().is_similar_to(&Circle::new(7.0).unwrap()));
+    }
+
+    #[test]
+    fn polygon_similarity() {
+        // an L shape
+        let vertices = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0)];
+        let shape = Polygon::new(vertices).unwrap();
+
+        // rotated by a quarter turn, scaled, shifted and starting elsewhere
+        let vertices = vec![(5.0, 5.0), (5.0, 3.0), (1.0, 3.0), (1.0, 1.0), (7.0, 1.0), (7.0, 5.0)];
+        let rotated = Polygon::new(vertices).unwrap();
+        // mirrored and clockwise
+        let vertices = vec![
+            (0.0, 0.0),
+            (0.0, 3.0),
+            (-1.0, 3.0),
+            (-1.0, 1.0),
+            (-2.0, 1.0),
+            (-2.0, 0.0),
+        ];
+        let mirrored = Polygon::new(vertices).unwrap();
+        // a longer foot
+        let vertices = vec![(0.0, 0.0), (3.0, 0.0), (3.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0)];
+        let other = Polygon::new(vertices).unwrap();
+
+        assert!(shape.is_similar_to(&rotated));
+        assert!(shape.is_similar_to(&mirrored));
+        assert!(!shape.is_similar_to(&other));
+        let triangle = Polygon::new(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).unwrap();
+        assert!(!shape.is_similar_to(&triangle));
+    }
+
+    #[test]
+    fn approx_eq() {
+        let rectangle = Rectangle::new(7.0, 3.0).unwrap();
+        let close = Rectangle::new(7.0 + 1e-10, 3.0).unwrap();
+        assert!(rectangle.approx_eq(&close, 1e-9));
+        assert!(!rectangle.approx_eq(&close, 1e-11));
+        assert!(!rectangle.approx_eq(&Rectangle::new(3.0, 7.0).unwrap(), 1e-9));
+
+        // compared in the unit of the first shape
+        let inch = Circle::from_length(Length::new(1.0, LengthUnit::Inch)).unwrap();
+        let centimetres = Circle::from_length(Length::new(2.54, LengthUnit::Centimetre)).unwrap();
+        assert!(inch.approx_eq(&centimetres, 1e-9));
+        assert!(!inch.approx_eq(&Circle::new(1.0).unwrap(), 1e-9));
+
+        let triangle = Triangle::new(3.0, 4.0, 5.0).unwrap();
+        assert!(triangle.approx_eq(&triangle.scale(1.0 + 1e-12).unwrap(), 1e-9));
+        let polygon = Polygon::new(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).unwrap();
+        let moved = Polygon::new(vec![(0.0, 0.0), (1.0, 0.001), (0.0, 1.0)]).unwrap();
+        assert!(polygon.approx_eq(&moved, 0.01));
+        assert!(!polygon.approx_eq(&moved, 0.0001));
+    }
+
+    #[test]
+    fn ordering_by_area() {
+        let rectangle = Rectangle::new(7.0, 3.0).unwrap();
+        let circle = Circle::new(1.0).unwrap();
+        assert_eq!(rectangle.partial_cmp_area(&circle), Some(Ordering::Greater));
+        assert_eq!(circle.partial_cmp_area(&rectangle), Some(Ordering::Less));
+        let square = Rectangle::new(3.0, 7.0).unwrap();
+        assert_eq!(rectangle.partial_cmp_area(&square), Some(Ordering::Equal));
+
+        let mut shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(rectangle),
+            Box::new(circle.clone()),
+            Box::new(Triangle::new(3.0, 4.0, 5.0).unwrap()),
+        ];
+        shapes.sort_by(|first, second| first.partial_cmp_area(second.as_ref()).unwrap());
+        let names: Vec<&str> = shapes.iter().map(|shape| shape.name()).collect();
+        assert_eq!(names, vec!["circle", "triangle", "rectangle"]);
+
+        // areas in different units are compared in square metres, like scenes do
+        let inch = Circle::from_length(Length::new(1.0, LengthUnit::Inch)).unwrap();
+        assert_eq!(inch.partial_cmp_area(&circle), Some(Ordering::Less));
+        let metre = Length::new(1.0, LengthUnit::Metre);
+        let centimetres = Length::new(100.0, LengthUnit::Centimetre);
+        let large = Rectangle::from_lengths(centimetres, centimetres).unwrap();
+        let square = Rectangle::from_lengths(metre, metre).unwrap();
+        assert_eq!(large.partial_cmp_area(&square), Some(Ordering::Equal));
+    }
+}
+
+#[cfg(test)]
+mod solids_tests {
+    use crate::shapes::*;
+    use crate::solids::*;
+    use crate::units::{Length, LengthUnit};
+    use float_cmp::{assert_approx_eq, F64Margin};
+    use std::f64::consts::PI;
+
+    const MARGIN: F64Margin = F64Margin {
+        epsilon: f64::EPSILON,
+        ulps: 4,
+    };
+
+    #[test]
+    fn volumes_and_surface_areas() {
+        let cuboid = Cuboid::new(2.0, 3.0, 4.0).unwrap();
+        assert_eq!(cuboid.volume(), 24.0);
+        assert_eq!(cuboid.surface_area(), 52.0);
+
+        let sphere = Sphere::new(3.0).unwrap();
+        assert_approx_eq!(f64, sphere.volume(), 36.0 * PI, MARGIN);
+        assert_approx_eq!(f64, sphere.surface_area(), 36.0 * PI, MARGIN);
+
+        let cylinder = Cylinder::new(2.0, 5.0).unwrap();
+        assert_approx_eq!(f64, cylinder.volume(), 20.0 * PI, MARGIN);
+        assert_approx_eq!(f64, cylinder.surface_area(), 28.0 * PI, MARGIN);
+
+        let cone = Cone::new(3.0, 4.0).unwrap();
+        assert_eq!(cone.slant_height(), 5.0);
+        assert_approx_eq!(f64, cone.volume(), 12.0 * PI, MARGIN);
+        assert_approx_eq!(f64, cone.surface_area(), 24.0 * PI, MARGIN);
+
+        let prism = Prism::extrude(Triangle::new(3.0, 4.0, 5.0).unwrap(), 10.0).unwrap();
+        assert_eq!(prism.volume(), 60.0);
+        assert_eq!(prism.surface_area(), 132.0);
+    }
+
+    #[test]
+    fn extrude_and_revolve() {
+        let width = Length::new(2.0, LengthUnit::Inch);
+        let height = Length::new(3.0, LengthUnit::Inch);
+        let rectangle = Rectangle::from_lengths(width, height).unwrap();
+
+        let cuboid = Cuboid::extrude(&rectangle, 4.0).unwrap();
+        let dimensions = (cuboid.get_width(), cuboid.get_height(), cuboid.get_depth());
+        assert_eq!(dimensions, (2.0, 3.0, 4.0));
+        assert_eq!(cuboid.unit(), LengthUnit::Inch);
+
+        let cylinder = Cylinder::revolve(&rectangle);
+        assert_eq!((cylinder.get_radius(), cylinder.get_height()), (2.0, 3.0));
+        assert_eq!(cylinder.unit(), LengthUnit::Inch);
+
+        let circle = Circle::new(2.0).unwrap();
+        assert_eq!(Cylinder::extrude(&circle, 5.0), Cylinder::new(2.0, 5.0));
+        assert_eq!(Sphere::revolve(&circle), Sphere::new(2.0).unwrap());
+        assert_eq!(Cone::from_base(&circle, 1.0), Cone::new(2.0, 1.0));
+
+        // a prism over a circle is a cylinder
+        let prism = Prism::extrude(circle, 5.0).unwrap();
+        let cylinder = Cylinder::new(2.0, 5.0).unwrap();
+        assert_approx_eq!(f64, prism.volume(), cylinder.volume(), MARGIN);
+        assert_approx_eq!(f64, prism.surface_area(), cylinder.surface_area(), MARGIN);
+        assert_eq!(prism.get_base().get_radius(), 2.0);
+    }
+
+    #[test]
+    fn wrong_input() {
+        assert_eq!(Cuboid::new(-1.0, 1.0, 1.0), Err(Error::InvalidWidth(-1.0)));
+        assert_eq!(Cuboid::new(1.0, 1.0, -2.0), Err(Error::InvalidDepth(-2.0)));
+        assert_eq!(Sphere::new(-3.0), Err(Error::InvalidRadius(-3.0)));
+        assert!(matches!(Cylinder::new(1.0, f64::NAN), Err(Error::InvalidHeight(_))));
+        assert_eq!(Cone::new(1.0, -1.0), Err(Error::InvalidHeight(-1.0)));
+
+        let rectangle = Rectangle::new(2.0, 3.0).unwrap();
+        assert_eq!(Cuboid::extrude(&rectangle, -4.0), Err(Error::InvalidDepth(-4.0)));
+        let circle = Circle::new(1.0).unwrap();
+        assert_eq!(Cylinder::extrude(&circle, -5.0), Err(Error::InvalidHeight(-5.0)));
+        let res = Cone::from_base(&circle, f64::INFINITY);
+        assert_eq!(res, Err(Error::InvalidHeight(f64::INFINITY)));
+        let res = Prism::extrude(circle, -1.0);
+        assert_eq!(res.err(), Some(Error::InvalidHeight(-1.0)));
+
+        let mut sphere = Sphere::new(1.0).unwrap();
+        assert!(sphere.set_radius(-1.0).is_err());
+        assert_eq!(sphere.get_radius(), 1.0);
+        let mut cuboid = Cuboid::new(1.0, 1.0, 1.0).unwrap();
+        assert_eq!(cuboid.set_depth(-1.0), Err(Error::InvalidDepth(-1.0)));
+        assert_eq!(Error::InvalidDepth(-1.0).to_string(), "invalid depth -1");
+    }
 }
@@ -0,0 +1,240 @@
+//! Scaling and comparing shapes of the same kind.
+//!
+//! `Transform` complements `Shape` for concrete shape types:
+//!
+//! - `scale` multiplies every length by a factor and builds the result with
+//!   the constructor, so a negative factor fails with the same error as a
+//!   negative dimension, e.g. `Error::InvalidWidth` for a rectangle; polygons,
+//!   whose coordinates may be negative, reject such a factor with
+//!   `Error::InvalidScaleFactor`
+//! - `is_similar_to` tells whether two shapes are equal up to scaling,
+//!   rotation and reflection, whatever their units
+//! - `approx_eq` tells whether two shapes have the same dimensions within an
+//!   absolute tolerance, in the unit of the first one
+//!
+//! Ordering by area is `Shape::partial_cmp_area`, which also works between
+//! shapes of different kinds and units.
+
+use std::f64::consts::PI;
+
+use crate::shapes::*;
+use crate::units::Length;
+
+// Relative difference below which lengths, ratios and angles count as equal
+// when checking similarity, to absorb rounding
+const SIMILARITY_TOLERANCE: f64 = 1e-9;
+
+pub trait Transform: Shape + Sized {
+    // A new shape with every length multiplied by factor, validated by the constructor
+    fn scale(&self, factor: f64) -> Result<Self, Error>;
+
+    fn is_similar_to(&self, other: &Self) -> bool;
+
+    // Whether every dimension differs by at most tolerance
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool;
+}
+
+impl Transform for Rectangle {
+    fn scale(&self, factor: f64) -> Result<Self, Error> {
+        Rectangle::from_lengths(scaled(self.width(), factor), scaled(self.height(), factor))
+    }
+
+    // Same aspect ratio, in either orientation
+    fn is_similar_to(&self, other: &Self) -> bool {
+        same_ratio(
+            self.get_width(),
+            self.get_height(),
+            other.get_width(),
+            other.get_height(),
+        ) || same_ratio(
+            self.get_width(),
+            self.get_height(),
+            other.get_height(),
+            other.get_width(),
+        )
+    }
+
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        match other.to_unit(self.get_unit()) {
+            Ok(other) => {
+                within(self.get_width(), other.get_width(), tolerance)
+                    && within(self.get_height(), other.get_height(), tolerance)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl Transform for Circle {
+    fn scale(&self, factor: f64) -> Result<Self, Error> {
+        Circle::from_length(scaled(self.radius(), factor))
+    }
+
+    // All circles are similar
+    fn is_similar_to(&self, _other: &Self) -> bool {
+        true
+    }
+
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        match other.to_unit(self.get_unit()) {
+            Ok(other) => within(self.get_radius(), other.get_radius(), tolerance),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Transform for Triangle {
+    fn scale(&self, factor: f64) -> Result<Self, Error> {
+        let (a, b, c) = self.get_sides();
+        Triangle::new(a * factor, b * factor, c * factor)
+    }
+
+    // Proportional sides, whichever order they are given in
+    fn is_similar_to(&self, other: &Self) -> bool {
+        let [a, b, c] = sorted_sides(self);
+        let [x, y, z] = sorted_sides(other);
+        same_ratio(a, b, x, y) && same_ratio(b, c, y, z) && same_ratio(a, c, x, z)
+    }
+
+    // Sides are compared in the order they are given in
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        let (a, b, c) = self.get_sides();
+        let (x, y, z) = other.get_sides();
+        within(a, x, tolerance) && within(b, y, tolerance) && within(c, z, tolerance)
+    }
+}
+
+impl Transform for Ellipse {
+    fn scale(&self, factor: f64) -> Result<Self, Error> {
+        Ellipse::new(
+            self.get_semi_axis_x() * factor,
+            self.get_semi_axis_y() * factor,
+        )
+    }
+
+    // Same ratio of the semi-axes, in either orientation
+    fn is_similar_to(&self, other: &Self) -> bool {
+        let (a, b) = (self.get_semi_axis_x(), self.get_semi_axis_y());
+        let (x, y) = (other.get_semi_axis_x(), other.get_semi_axis_y());
+        same_ratio(a, b, x, y) || same_ratio(a, b, y, x)
+    }
+
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        within(self.get_semi_axis_x(), other.get_semi_axis_x(), tolerance)
+            && within(self.get_semi_axis_y(), other.get_semi_axis_y(), tolerance)
+    }
+}
+
+impl Transform for RegularPolygon {
+    fn scale(&self, factor: f64) -> Result<Self, Error> {
+        RegularPolygon::new(self.get_sides(), self.get_side_length() * factor)
+    }
+
+    fn is_similar_to(&self, other: &Self) -> bool {
+        self.get_sides() == other.get_sides()
+    }
+
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        self.get_sides() == other.get_sides()
+            && within(self.get_side_length(), other.get_side_length(), tolerance)
+    }
+}
+
+impl Transform for Polygon {
+    // Vertices are scaled around the origin of their coordinates
+    // Return Err(Error::InvalidScaleFactor) unless factor is finite and positive, since
+    // a negative factor would turn the polygon by half a turn and 0 would collapse it
+    fn scale(&self, factor: f64) -> Result<Self, Error> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(Error::InvalidScaleFactor(factor));
+        }
+        let vertices = self
+            .get_vertices()
+            .iter()
+            .map(|(x, y)| (x * factor, y * factor))
+            .collect();
+        Polygon::new(vertices)
+    }
+
+    // Same sequence of side ratios and angles, starting at any vertex and
+    // going around in either direction
+    fn is_similar_to(&self, other: &Self) -> bool {
+        if self.get_vertices().len() != other.get_vertices().len() {
+            return false;
+        }
+        let first = signature(&counterclockwise(self.get_vertices().to_vec()));
+        let mirrored = other.get_vertices().iter().map(|(x, y)| (-x, *y)).collect();
+        [other.get_vertices().to_vec(), mirrored]
+            .into_iter()
+            .map(|vertices| signature(&counterclockwise(vertices)))
+            .any(|second| (0..first.len()).any(|shift| same_signature(&first, &second, shift)))
+    }
+
+    // Vertices are compared in order, starting with the first one
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        self.get_vertices().len() == other.get_vertices().len()
+            && self
+                .get_vertices()
+                .iter()
+                .zip(other.get_vertices())
+                .all(|(p, q)| within(p.0, q.0, tolerance) && within(p.1, q.1, tolerance))
+    }
+}
+
+fn scaled(length: Length, factor: f64) -> Length {
+    Length::new(length.value() * factor, length.unit())
+}
+
+fn within(first: f64, second: f64, tolerance: f64) -> bool {
+    (first - second).abs() <= tolerance
+}
+
+// Whether a : b equals x : y, compared crosswise so zero lengths need no division
+fn same_ratio(a: f64, b: f64, x: f64, y: f64) -> bool {
+    let (first, second) = (a * y, b * x);
+    (first - second).abs() <= SIMILARITY_TOLERANCE * first.abs().max(second.abs())
+}
+
+fn sorted_sides(triangle: &Triangle) -> [f64; 3] {
+    let (a, b, c) = triangle.get_sides();
+    let mut sides = [a, b, c];
+    sides.sort_by(f64::total_cmp);
+    sides
+}
+
+// The vertices in counterclockwise order
+fn counterclockwise(mut vertices: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    let twice_area: f64 = edges(&vertices).map(|(p, q)| p.0 * q.1 - q.0 * p.1).sum();
+    if twice_area < 0.0 {
+        vertices.reverse();
+    }
+    vertices
+}
+
+// Per edge, its share of the perimeter and the turn to the next edge in radians
+// Both stay the same under scaling and rotation
+fn signature(vertices: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let edges: Vec<((f64, f64), (f64, f64))> = edges(vertices).collect();
+    let perimeter: f64 = edges
+        .iter()
+        .map(|(p, q)| (q.0 - p.0).hypot(q.1 - p.1))
+        .sum();
+    (0..edges.len())
+        .map(|i| {
+            let (p, q) = edges[i];
+            let (r, s) = edges[(i + 1) % edges.len()];
+            let (d, e) = ((q.0 - p.0, q.1 - p.1), (s.0 - r.0, s.1 - r.1));
+            let turn = (d.0 * e.1 - d.1 * e.0).atan2(d.0 * e.0 + d.1 * e.1);
+            (d.0.hypot(d.1) / perimeter, turn)
+        })
+        .collect()
+}
+
+// Whether second, starting at shift, matches first
+fn same_signature(first: &[(f64, f64)], second: &[(f64, f64)], shift: usize) -> bool {
+    first.iter().enumerate().all(|(i, (share, turn))| {
+        let (other_share, other_turn) = second[(i + shift) % second.len()];
+        (share - other_share).abs() <= SIMILARITY_TOLERANCE
+            && (turn - other_turn).abs() <= SIMILARITY_TOLERANCE * PI
+    })
+}
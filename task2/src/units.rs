@@ -0,0 +1,183 @@
+//! Lengths and areas that know their unit.
+//!
+//! A `Length` is a value in one of the `LengthUnit`s, an `Area` a value in a
+//! squared unit. Both convert to any other unit with `to`, but never mix units
+//! on their own: adding values in different units is an `Error::MixedUnits`,
+//! and comparing them gives `None`, so `Length::new(1.0, Inch) < Length::new(3.0,
+//! Centimetre)` is false, as is `>`. Convert one side first.
+//!
+//! Shapes built from plain numbers are in metres, see `Shape::unit`.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shapes::Error;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LengthUnit {
+    #[serde(rename = "mm")]
+    Millimetre,
+    #[serde(rename = "cm")]
+    Centimetre,
+    #[default]
+    #[serde(rename = "m")]
+    Metre,
+    #[serde(rename = "in")]
+    Inch,
+    #[serde(rename = "ft")]
+    Foot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Length {
+    value: f64,
+    unit: LengthUnit,
+}
+
+// A value in the square of unit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Area {
+    value: f64,
+    unit: LengthUnit,
+}
+
+impl LengthUnit {
+    pub const ALL: [LengthUnit; 5] = [
+        LengthUnit::Millimetre,
+        LengthUnit::Centimetre,
+        LengthUnit::Metre,
+        LengthUnit::Inch,
+        LengthUnit::Foot,
+    ];
+
+    // Symbol of the unit, e.g. "cm"
+    pub fn name(&self) -> &'static str {
+        match self {
+            LengthUnit::Millimetre => "mm",
+            LengthUnit::Centimetre => "cm",
+            LengthUnit::Metre => "m",
+            LengthUnit::Inch => "in",
+            LengthUnit::Foot => "ft",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LengthUnit> {
+        Self::ALL.into_iter().find(|unit| unit.name() == name)
+    }
+
+    // Length of one unit in metres; inches and feet are defined exactly in millimetres
+    pub fn metres(&self) -> f64 {
+        match self {
+            LengthUnit::Millimetre => 0.001,
+            LengthUnit::Centimetre => 0.01,
+            LengthUnit::Metre => 1.0,
+            LengthUnit::Inch => 0.0254,
+            LengthUnit::Foot => 0.3048,
+        }
+    }
+
+    // Factor turning a value in self into a value in unit
+    fn factor(&self, unit: LengthUnit) -> f64 {
+        if *self == unit {
+            1.0
+        } else {
+            self.metres() / unit.metres()
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Both operands must be in the same unit
+fn same_unit(first: LengthUnit, second: LengthUnit) -> Result<LengthUnit, Error> {
+    if first == second {
+        Ok(first)
+    } else {
+        Err(Error::MixedUnits(first, second))
+    }
+}
+
+impl Length {
+    pub fn new(value: f64, unit: LengthUnit) -> Self {
+        Self { value, unit }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+
+    // The same length in another unit
+    pub fn to(&self, unit: LengthUnit) -> Length {
+        Length::new(self.value * self.unit.factor(unit), unit)
+    }
+
+    // Return Err(Error::MixedUnits) if the units differ
+    pub fn checked_add(&self, other: Length) -> Result<Length, Error> {
+        let unit = same_unit(self.unit, other.unit)?;
+        Ok(Length::new(self.value + other.value, unit))
+    }
+}
+
+impl PartialOrd for Length {
+    // None for different units
+    fn partial_cmp(&self, other: &Length) -> Option<Ordering> {
+        same_unit(self.unit, other.unit).ok()?;
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+impl Area {
+    pub fn new(value: f64, unit: LengthUnit) -> Self {
+        Self { value, unit }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    // The unit whose square the value is in
+    pub fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+
+    // The same area in the square of another unit
+    pub fn to(&self, unit: LengthUnit) -> Area {
+        Area::new(self.value * self.unit.factor(unit).powi(2), unit)
+    }
+
+    // Return Err(Error::MixedUnits) if the units differ
+    pub fn checked_add(&self, other: Area) -> Result<Area, Error> {
+        let unit = same_unit(self.unit, other.unit)?;
+        Ok(Area::new(self.value + other.value, unit))
+    }
+}
+
+impl PartialOrd for Area {
+    // None for different units
+    fn partial_cmp(&self, other: &Area) -> Option<Ordering> {
+        same_unit(self.unit, other.unit).ok()?;
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}²", self.value, self.unit)
+    }
+}
@@ -0,0 +1,286 @@
+//! The quiz questions as data.
+//!
+//! Every question of `crate::questions` is a `Question` in `QUESTIONS`, with
+//! its prompt, its four options (a to d), the letter of the correct option, an
+//! explanation of the answer and the vulnerability it covers. The
+//! `question_N()` functions return the answer of the question with id N.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topic {
+    // Missing signer and authority checks
+    AccessControl,
+    AccountReloading,
+    // Moving lamports between accounts
+    Lamports,
+    Reinitialization,
+    ArbitraryCpi,
+    DuplicateMutableAccounts,
+    BumpSeeds,
+    PdaSharing,
+    ClosingAccounts,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Question {
+    // Numbered from 1, as the question_N() functions
+    pub id: u32,
+    pub prompt: &'static str,
+    // The options a) to d), without their letter
+    pub options: [&'static str; 4],
+    pub answer: char,
+    pub explanation: &'static str,
+    pub topic: Topic,
+}
+
+pub const LETTERS: [char; 4] = ['a', 'b', 'c', 'd'];
+
+impl Topic {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topic::AccessControl => "Access control",
+            Topic::AccountReloading => "Account reloading",
+            Topic::Lamports => "Lamports",
+            Topic::Reinitialization => "Reinitialization",
+            Topic::ArbitraryCpi => "Arbitrary CPI",
+            Topic::DuplicateMutableAccounts => "Duplicate mutable accounts",
+            Topic::BumpSeeds => "Bump seeds",
+            Topic::PdaSharing => "PDA sharing",
+            Topic::ClosingAccounts => "Closing accounts",
+        }
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Question {
+    // The text of the option with the given letter
+    pub fn option(&self, letter: char) -> Option<&'static str> {
+        LETTERS
+            .iter()
+            .position(|given| *given == letter)
+            .map(|index| self.options[index])
+    }
+
+    pub fn is_correct(&self, letter: char) -> bool {
+        letter == self.answer
+    }
+}
+
+// The prompt followed by one line per option, as in the original doc comments
+impl fmt::Display for Question {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.prompt)?;
+        writeln!(f)?;
+        for (letter, option) in LETTERS.iter().zip(self.options) {
+            writeln!(f, "{}) {}", letter, option)?;
+        }
+        Ok(())
+    }
+}
+
+// The question with the given id
+pub fn question(id: u32) -> Option<&'static Question> {
+    QUESTIONS.iter().find(|question| question.id == id)
+}
+
+// The correct answer of the question with the given id
+// Panics if there is no such question
+pub fn answer(id: u32) -> char {
+    question(id).expect("unknown question").answer
+}
+
+pub fn by_topic(topic: Topic) -> impl Iterator<Item = &'static Question> {
+    QUESTIONS
+        .iter()
+        .filter(move |question| question.topic == topic)
+}
+
+pub static QUESTIONS: [Question; 10] = [
+    Question {
+        id: 1,
+        prompt: r#"What vulnerability is present in the following code?
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub value: u8
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>
+}
+
+pub fn update_config(ctx: Context<UpdateConfig>, data: u8) -> Result<()> {
+
+    if !ctx.accounts.admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature.into());
+    }
+
+    let config = &mut ctx.accounts.config;
+    config.value = data;
+    Ok(())
+}"#,
+        options: [
+            "The config account should always be immutable, this instruction should not exist.",
+            "The config account is not reloaded after a CPI.",
+            "Anyone can update the config.",
+            "The update config instruction is safe and does not contain any vulnerability.",
+        ],
+        answer: 'c',
+        explanation: "Signer already checks the signature, but nothing ties the signer to \
+                      config.admin, so any signer can change the value; a has_one = admin \
+                      constraint closes the hole.",
+        topic: Topic::AccessControl,
+    },
+    Question {
+        id: 2,
+        prompt: "How can account reloading vulnerability be prevented?",
+        options: [
+            "It cannot be prevented because of Solana's runtime policy.",
+            "By calling two CPIs in a row.",
+            "By calling reload() on the accounts modified by the CPI.",
+            "By ensuring all of the accounts included in the CPI are rent-exempt.",
+        ],
+        answer: 'c',
+        explanation: "A CPI updates the account on chain but not the copy deserialized by \
+                      Anchor; reload() reads the account data again.",
+        topic: Topic::AccountReloading,
+    },
+    Question {
+        id: 3,
+        prompt: "Which of the following conditions must be met for the balances of all \
+                 accounts after a transaction?",
+        options: [
+            "Balances must show a net positive gain.",
+            "The sum of the balances before and after the transaction must remain the same.",
+            "Balances of read-only accounts must increase.",
+            "Balances must reflect transaction fees deducted.",
+        ],
+        answer: 'b',
+        explanation: "Programs can move lamports between accounts but cannot create or \
+                      destroy them, which the runtime checks after every instruction.",
+        topic: Topic::Lamports,
+    },
+    Question {
+        id: 4,
+        prompt: "How can you prevent an account from being re-initialized and having its \
+                 existing data overridden?",
+        options: [
+            "By using an account discriminator or an initialization flag.",
+            "By increasing the account's balance.",
+            "By comparing the account's pubkey with another account.",
+            "By checking if the account signed the transaction.",
+        ],
+        answer: 'a',
+        explanation: "A discriminator or a flag tells that the account is already \
+                      initialized; Anchor's init constraint relies on the discriminator.",
+        topic: Topic::Reinitialization,
+    },
+    Question {
+        id: 5,
+        prompt: "How can you check that the correct program is being invoked before calling a \
+                 CPI?",
+        options: [
+            "By checking the executable account's balance.",
+            "By ensuring that an authorized account is specified as the signer of the \
+             instruction which calls the CPI.",
+            "By comparing the public key of the passed-in program with the program you \
+             expected.",
+            "By checking the account's discriminator.",
+        ],
+        answer: 'c',
+        explanation: "Without comparing its key, any program can be passed in and invoked \
+                      with the accounts of the CPI; Anchor's Program type does the check.",
+        topic: Topic::ArbitraryCpi,
+    },
+    Question {
+        id: 6,
+        prompt: "How can you prevent the duplicate mutable accounts vulnerability?",
+        options: [
+            "By comparing balances of the mutable accounts.",
+            "By comparing the public keys of the accounts and throwing an error if they \
+             match.",
+            "By checking if all accounts are initialized.",
+            "By checking whether both account have the same owner.",
+        ],
+        answer: 'b',
+        explanation: "The same account can be passed for both mutable accounts, so the \
+                      second write overrides the first unless their keys are compared.",
+        topic: Topic::DuplicateMutableAccounts,
+    },
+    Question {
+        id: 7,
+        prompt: "How does the find_program_address method differ from the \
+                 create_program_address method?",
+        options: [
+            "find_program_address uses the canonical bump for the PDA derivation.",
+            "find_program_address derives a PDA without searching for the canonical bump.",
+            "Both functions work the same, they only differ in name.",
+            "It automatically stores the derived bump in an account's data field for later \
+             reference.",
+        ],
+        answer: 'a',
+        explanation: "find_program_address searches for the highest bump giving an address \
+                      off the curve, while create_program_address uses the bump it is \
+                      given.",
+        topic: Topic::BumpSeeds,
+    },
+    Question {
+        id: 8,
+        prompt: "Anyone can increase the account balance. Under what circumstances can the \
+                 account balance be decreased?",
+        options: [
+            "Every time the account signs a transaction.",
+            "Only if the data stored in the account is zeroed out.",
+            "This can be done only to accounts owned by the System Program.",
+            "Account owner is able to subtract lamports from the account.",
+        ],
+        answer: 'c',
+        explanation: "Only the program owning an account can debit it; for wallets this is \
+                      the System Program, which transfers lamports when the wallet signs.",
+        topic: Topic::Lamports,
+    },
+    Question {
+        id: 9,
+        prompt: "How can you prevent the same PDA from being used for multiple accounts,\n\
+                 thereby avoiding unauthorized access to data and funds?",
+        options: [
+            "By setting the account's discriminator to CLOSED_ACCOUNT_DISCRIMINATOR.",
+            "By using the same seeds for all accounts.",
+            "By using user-specific and/or domain-specific seeds to prevent the same PDA \
+             from being used across different accounts.",
+            "By making the account with the original PDA rent-exempt.",
+        ],
+        answer: 'c',
+        explanation: "Seeds with the user or the purpose of the account in them give every \
+                      user and purpose a PDA of its own.",
+        topic: Topic::PdaSharing,
+    },
+    Question {
+        id: 10,
+        prompt: "Which of these things does Anchor's close constraint not do?",
+        options: [
+            "It transfers all lamports to a specified account.",
+            "It creates a new account to replace the closed one.",
+            "It zeroes out the account data.",
+            "It sets the account discriminator to CLOSED_ACCOUNT_DISCRIMINATOR.",
+        ],
+        answer: 'b',
+        explanation: "close moves the lamports out and wipes the account so it cannot be \
+                      used again; nothing takes its place.",
+        topic: Topic::ClosingAccounts,
+    },
+];
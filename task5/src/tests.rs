@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod quiz_tests {
+    use crate::questions::*;
+    use crate::quiz::*;
+
+    #[test]
+    fn registry() {
+        for (index, question) in QUESTIONS.iter().enumerate() {
+            assert_eq!(question.id as usize, index + 1);
+            assert!(LETTERS.contains(&question.answer));
+            assert!(!question.prompt.is_empty());
+            assert!(!question.explanation.is_empty());
+            assert!(question.options.iter().all(|option| !option.is_empty()));
+        }
+        assert_eq!(question(0), None);
+        assert_eq!(question(11), None);
+    }
+
+    #[test]
+    fn wrappers() {
+        let answers = [
+            question_1(),
+            question_2(),
+            question_3(),
+            question_4(),
+            question_5(),
+            question_6(),
+            question_7(),
+            question_8(),
+            question_9(),
+            question_10(),
+        ];
+        let expected: Vec<char> = QUESTIONS.iter().map(|question| question.answer).collect();
+        assert_eq!(answers.to_vec(), expected);
+        assert_eq!(answers.iter().collect::<String>(), "ccbacbaccb");
+    }
+
+    #[test]
+    fn options() {
+        let question = question(1).unwrap();
+        assert!(question.prompt.contains("pub struct UpdateConfig<'info> {"));
+        assert_eq!(question.option('c'), Some("Anyone can update the config."));
+        assert_eq!(question.option('e'), None);
+        assert!(question.is_correct('c'));
+        assert!(question.to_string().ends_with(
+            "Ok(())\n}\n\na) The config account should always be immutable, \
+                        this instruction should not exist.\nb) The config account is not \
+                        reloaded after a CPI.\nc) Anyone can update the config.\nd) The update \
+                        config instruction is safe and does not contain any vulnerability.\n"
+        ));
+    }
+
+    #[test]
+    fn topics() {
+        let ids: Vec<u32> = by_topic(Topic::Lamports)
+            .map(|question| question.id)
+            .collect();
+        assert_eq!(ids, vec![3, 8]);
+        assert_eq!(Topic::ArbitraryCpi.to_string(), "Arbitrary CPI");
+    }
+}